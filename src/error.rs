@@ -6,6 +6,8 @@ use scroll;
 #[derive(Debug)]
 pub enum Error {
     BadSignature(u64),
+    BadRva(u32),
+    Malformed(String),
     Scroll(scroll::Error),
     #[cfg(feature = "std")]
     IO(io::Error),
@@ -17,7 +19,9 @@ impl error::Error for Error {
         match *self {
             Error::IO(ref io) => Some(io),
             Error::Scroll(ref scroll) => Some(scroll),
-            Error::BadSignature(_) => None
+            Error::BadSignature(_) => None,
+            Error::BadRva(_) => None,
+            Error::Malformed(_) => None
         }
     }
 }
@@ -42,6 +46,8 @@ impl fmt::Display for Error {
             Error::IO(ref err) => write!(fmt, "{}", err),
             Error::Scroll(ref err) => write!(fmt, "{}", err),
             Error::BadSignature(signature) => write!(fmt, "Invalid signature: 0x{:x}", signature),
+            Error::BadRva(rva) => write!(fmt, "Invalid RVA: 0x{:x}", rva),
+            Error::Malformed(ref msg) => write!(fmt, "Malformed entity: {}", msg),
         }
     }
 }
@@ -0,0 +1,179 @@
+use scroll::{IOread, IOwrite, Pread, Pwrite, SizeWith};
+use serde::{Deserialize, Serialize, Serializer};
+use serde::ser::SerializeStruct;
+
+use crate::error;
+use crate::pe::header::{DataDirectory, Headers};
+
+#[derive(Debug, PartialEq, Copy, Clone, Default, Pread, Pwrite, IOread, IOwrite, SizeWith, Deserialize)]
+pub struct BoundImportDescriptor {
+    pub time_date_stamp: u32,
+    pub offset_module_name: u16,
+    pub number_of_module_forwarder_refs: u16,
+}
+
+impl BoundImportDescriptor {
+    pub fn parse(bytes: &[u8], offset: &mut usize) -> error::Result<Self> {
+        Ok(bytes.gread_with(offset, scroll::LE)?)
+    }
+}
+
+#[derive(Debug, PartialEq, Copy, Clone, Default, Pread, Pwrite, IOread, IOwrite, SizeWith, Deserialize)]
+pub struct BoundForwarderRef {
+    pub time_date_stamp: u32,
+    pub offset_module_name: u16,
+    pub reserved: u16,
+}
+
+impl BoundForwarderRef {
+    pub fn parse(bytes: &[u8], offset: &mut usize) -> error::Result<Self> {
+        Ok(bytes.gread_with(offset, scroll::LE)?)
+    }
+}
+
+/// DLL referenced by a bound import through a forwarded export
+#[derive(Debug, PartialEq, Clone, Default, Deserialize)]
+pub struct BoundForwarder {
+    pub name: String,
+    pub time_date_stamp: u32,
+}
+
+impl Serialize for BoundForwarder {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("BoundForwarder", 2)?;
+        state.serialize_field("name", &self.name)?;
+        state.serialize_field("time_date_stamp", &format!("0x{:x}", &self.time_date_stamp))?;
+        state.end()
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Default, Deserialize)]
+pub struct BoundImport {
+    pub name: String,
+    pub time_date_stamp: u32,
+    pub forwarders: Vec<BoundForwarder>,
+}
+
+impl Serialize for BoundImport {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("BoundImport", 3)?;
+        state.serialize_field("name", &self.name)?;
+        state.serialize_field("time_date_stamp", &format!("0x{:x}", &self.time_date_stamp))?;
+        state.serialize_field("forwarders", &self.forwarders)?;
+        state.end()
+    }
+}
+
+/// Binding whose timestamp does not match the DLL it was bound against
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct StaleBinding {
+    pub name: String,
+    /// Bound import owning the forwarder ref, `None` for a direct binding
+    pub forwarded_by: Option<String>,
+    pub bound_time_date_stamp: u32,
+    pub time_date_stamp: u32,
+}
+
+/// Bound import directory, stored in the headers and referenced by file offset
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct BoundImportDirectory {
+    pub imports: Vec<BoundImport>
+}
+
+impl BoundImportDirectory {
+    pub fn parse(bytes: &[u8], headers: &Headers, data_directory: DataDirectory) -> error::Result<Self> {
+        let start:usize = headers.rva_to_offset(data_directory.virtual_address).ok_or(error::Error::BadRva(data_directory.virtual_address))?;
+        // Module names are located by offsets relative to the beginning of the directory
+        let get_name = |offset_module_name: u16| -> error::Result<String> {
+            Ok(bytes.pread::<&str>(start + offset_module_name as usize)?.to_string())
+        };
+        let mut offset:usize = start;
+        let mut imports:Vec<BoundImport> = Vec::new();
+        loop {
+            let descriptor:BoundImportDescriptor = BoundImportDescriptor::parse(bytes, &mut offset)?;
+            if descriptor.time_date_stamp == 0 && descriptor.offset_module_name == 0 {
+                break;
+            }
+            let mut forwarders:Vec<BoundForwarder> = Vec::new();
+            for _ in 0..descriptor.number_of_module_forwarder_refs {
+                let forwarder_ref:BoundForwarderRef = BoundForwarderRef::parse(bytes, &mut offset)?;
+                forwarders.push(BoundForwarder {
+                    name: get_name(forwarder_ref.offset_module_name)?,
+                    time_date_stamp: forwarder_ref.time_date_stamp
+                });
+            }
+            imports.push(BoundImport {
+                name: get_name(descriptor.offset_module_name)?,
+                time_date_stamp: descriptor.time_date_stamp,
+                forwarders
+            });
+        }
+        Ok(BoundImportDirectory { imports })
+    }
+    /// Compares every binding to `dll_name` with the COFF timestamp of the supplied DLL headers
+    pub fn stale_bindings(&self, dll_name: &str, dll: &Headers) -> Vec<StaleBinding> {
+        let time_date_stamp:u32 = dll.coff.time_date_stamp;
+        let mut stale_bindings:Vec<StaleBinding> = Vec::new();
+        for import in &self.imports {
+            if import.name.eq_ignore_ascii_case(dll_name) && import.time_date_stamp != time_date_stamp {
+                stale_bindings.push(StaleBinding {
+                    name: import.name.clone(),
+                    forwarded_by: None,
+                    bound_time_date_stamp: import.time_date_stamp,
+                    time_date_stamp
+                });
+            }
+            for forwarder in &import.forwarders {
+                if forwarder.name.eq_ignore_ascii_case(dll_name) && forwarder.time_date_stamp != time_date_stamp {
+                    stale_bindings.push(StaleBinding {
+                        name: forwarder.name.clone(),
+                        forwarded_by: Some(import.name.clone()),
+                        bound_time_date_stamp: forwarder.time_date_stamp,
+                        time_date_stamp
+                    });
+                }
+            }
+        }
+        stale_bindings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use scroll::Pwrite;
+
+    use super::{BoundForwarderRef, BoundImportDescriptor, BoundImportDirectory};
+    use crate::pe::header::{DataDirectory, Headers};
+
+    #[test]
+    fn bound_imports() {
+        let mut bytes = vec![0u8; 0x400];
+        bytes.pwrite_with(BoundImportDescriptor { time_date_stamp: 0x1111, offset_module_name: 0x20, number_of_module_forwarder_refs: 1 }, 0x300, scroll::LE).unwrap();
+        bytes.pwrite_with(BoundForwarderRef { time_date_stamp: 0x2222, offset_module_name: 0x2d, reserved: 0 }, 0x308, scroll::LE).unwrap();
+        bytes[0x320..0x32c].copy_from_slice(b"KERNEL32.dll");
+        bytes[0x32d..0x336].copy_from_slice(b"NTDLL.DLL");
+
+        let mut headers = Headers::default();
+        headers.optional.specific_fields.size_of_headers = 0x400;
+        let data_directory = DataDirectory { virtual_address: 0x300, size: 0x40 };
+        let directory = BoundImportDirectory::parse(&bytes, &headers, data_directory).unwrap();
+        assert_eq!(directory.imports.len(), 1);
+        assert_eq!(directory.imports[0].name, "KERNEL32.dll");
+        assert_eq!(directory.imports[0].forwarders[0].name, "NTDLL.DLL");
+
+        let mut ntdll = Headers::default();
+        ntdll.coff.time_date_stamp = 0x2222;
+        assert!(directory.stale_bindings("ntdll.dll", &ntdll).is_empty());
+        ntdll.coff.time_date_stamp = 0x3333;
+        let stale_bindings = directory.stale_bindings("ntdll.dll", &ntdll);
+        assert_eq!(stale_bindings.len(), 1);
+        assert_eq!(stale_bindings[0].forwarded_by.as_deref(), Some("KERNEL32.dll"));
+        assert_eq!(stale_bindings[0].bound_time_date_stamp, 0x2222);
+    }
+}
@@ -0,0 +1,407 @@
+use scroll::Pread;
+use serde::{Deserialize, Serialize, Serializer};
+use serde::ser::SerializeStruct;
+
+use crate::error;
+use crate::pe::header::Headers;
+
+/// SHA-256 of ".net core bundle", stored in the apphost right after the manifest offset
+pub const BUNDLE_SIGNATURE: [u8; 32] = [
+    0x8b, 0x12, 0x02, 0xb9, 0x6a, 0x61, 0x20, 0x38, 0x72, 0x7b, 0x93, 0x02, 0x14, 0xd7, 0xa0, 0x32,
+    0x13, 0xf5, 0xb9, 0xe6, 0xef, 0xae, 0x33, 0x18, 0xee, 0x3b, 0x2d, 0xce, 0x24, 0xb3, 0x6a, 0xae,
+];
+
+/// Bundles produced for .NET Core 3 apps extract everything to disk on startup
+pub const BUNDLE_FLAG_NETCOREAPP3_COMPAT_MODE: u64 = 0x1;
+
+/// Kind of a file embedded in a single-file bundle
+#[derive(Debug, PartialEq, Copy, Clone, Default, Serialize, Deserialize)]
+pub enum BundleFileType {
+    #[default]
+    Unknown,
+    Assembly,
+    NativeBinary,
+    DepsJson,
+    RuntimeConfigJson,
+    Symbols,
+}
+
+impl BundleFileType {
+    pub fn from_u8(file_type: u8) -> Self {
+        match file_type {
+            1 => BundleFileType::Assembly,
+            2 => BundleFileType::NativeBinary,
+            3 => BundleFileType::DepsJson,
+            4 => BundleFileType::RuntimeConfigJson,
+            5 => BundleFileType::Symbols,
+            _ => BundleFileType::Unknown
+        }
+    }
+}
+
+/// Location of a file inside the bundle, offsets are relative to the start of the host file
+#[derive(Debug, PartialEq, Copy, Clone, Default, Deserialize)]
+pub struct BundleLocation {
+    pub offset: u64,
+    pub size: u64,
+}
+
+impl Serialize for BundleLocation {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("BundleLocation", 2)?;
+        state.serialize_field("offset", &format!("0x{:x}", &self.offset))?;
+        state.serialize_field("size", &self.size)?;
+        state.end()
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Default, Deserialize)]
+pub struct BundleFileEntry {
+    pub offset: u64,
+    pub size: u64,
+    /// Size of the deflate stream, 0 when the file is stored uncompressed
+    pub compressed_size: u64,
+    pub file_type: BundleFileType,
+    pub path: String,
+}
+
+impl Serialize for BundleFileEntry {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("BundleFileEntry", 5)?;
+        state.serialize_field("offset", &format!("0x{:x}", &self.offset))?;
+        state.serialize_field("size", &self.size)?;
+        state.serialize_field("compressed_size", &self.compressed_size)?;
+        state.serialize_field("file_type", &self.file_type)?;
+        state.serialize_field("path", &self.path)?;
+        state.end()
+    }
+}
+
+impl BundleFileEntry {
+    pub fn parse(bytes: &[u8], offset: &mut usize, major_version: u32) -> error::Result<Self> {
+        let offset_in_bundle:i64 = bytes.gread_with(offset, scroll::LE)?;
+        let size:i64 = bytes.gread_with(offset, scroll::LE)?;
+        let compressed_size:i64 = match major_version {
+            6..=u32::MAX => bytes.gread_with(offset, scroll::LE)?,
+            _ => 0
+        };
+        let file_type:u8 = bytes.gread_with(offset, scroll::LE)?;
+        let path:String = read_string(bytes, offset)?;
+        if offset_in_bundle < 0 || size < 0 || compressed_size < 0 {
+            return Err(error::Error::Malformed(format!("Negative bundle file location for {}", path)));
+        }
+        Ok(BundleFileEntry {
+            offset: offset_in_bundle as u64,
+            size: size as u64,
+            compressed_size: compressed_size as u64,
+            file_type: BundleFileType::from_u8(file_type),
+            path
+        })
+    }
+    pub fn is_compressed(&self) -> bool {
+        self.compressed_size != 0
+    }
+    /// Returns the content of the file, inflated if it was compressed
+    pub fn extract(&self, bytes: &[u8]) -> error::Result<Vec<u8>> {
+        let stored_size:u64 = if self.is_compressed() { self.compressed_size } else { self.size };
+        let data:&[u8] = get_range(bytes, self.offset, stored_size)
+            .ok_or_else(|| error::Error::Malformed(format!("Bundle file {} out of bounds", self.path)))?;
+        if !self.is_compressed() {
+            return Ok(data.to_vec());
+        }
+        // the declared size bounds the output, a larger stream is rejected rather than inflated
+        let inflated:Vec<u8> = miniz_oxide::inflate::decompress_to_vec_with_limit(data, self.size as usize)
+            .map_err(|e| error::Error::Malformed(format!("Cannot inflate bundle file {}: {}", self.path, e)))?;
+        if inflated.len() as u64 != self.size {
+            return Err(error::Error::Malformed(format!("Bundle file {} inflated to {} bytes instead of {}", self.path, inflated.len(), self.size)));
+        }
+        Ok(inflated)
+    }
+}
+
+/// Manifest of a .NET single-file bundle appended to the apphost
+#[derive(Debug, PartialEq, Clone, Default, Deserialize)]
+pub struct Bundle {
+    /// File offset of the manifest
+    pub header_offset: u64,
+    pub major_version: u32,
+    pub minor_version: u32,
+    pub bundle_id: String,
+    pub deps_json: Option<BundleLocation>,
+    pub runtime_config_json: Option<BundleLocation>,
+    pub flags: u64,
+    pub files: Vec<BundleFileEntry>,
+}
+
+impl Serialize for Bundle {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("Bundle", 8)?;
+        state.serialize_field("header_offset", &format!("0x{:x}", &self.header_offset))?;
+        state.serialize_field("major_version", &self.major_version)?;
+        state.serialize_field("minor_version", &self.minor_version)?;
+        state.serialize_field("bundle_id", &self.bundle_id)?;
+        state.serialize_field("deps_json", &self.deps_json)?;
+        state.serialize_field("runtime_config_json", &self.runtime_config_json)?;
+        state.serialize_field("flags", &format!("0x{:x}", &self.flags))?;
+        state.serialize_field("files", &self.files)?;
+        state.end()
+    }
+}
+
+impl Bundle {
+    /// Looks for the bundle marker in the section data, `None` if the image is not a bundle
+    pub fn parse(bytes: &[u8], headers: &Headers) -> error::Result<Option<Self>> {
+        // a marker whose manifest cannot be read is not taken for a bundle, the search goes on
+        for header_offset in find_header_offsets(bytes, headers) {
+            if let Ok(bundle) = Bundle::parse_manifest(bytes, header_offset) {
+                return Ok(Some(bundle));
+            }
+        }
+        Ok(None)
+    }
+    fn parse_manifest(bytes: &[u8], header_offset: u64) -> error::Result<Self> {
+        let mut offset:usize = header_offset as usize;
+        let major_version:u32 = bytes.gread_with(&mut offset, scroll::LE)?;
+        let minor_version:u32 = bytes.gread_with(&mut offset, scroll::LE)?;
+        let file_count:i32 = bytes.gread_with(&mut offset, scroll::LE)?;
+        let bundle_id:String = read_string(bytes, &mut offset)?;
+        let mut deps_json:Option<BundleLocation> = None;
+        let mut runtime_config_json:Option<BundleLocation> = None;
+        let mut flags:u64 = 0;
+        if major_version >= 2 {
+            deps_json = read_location(bytes, &mut offset)?;
+            runtime_config_json = read_location(bytes, &mut offset)?;
+            flags = bytes.gread_with(&mut offset, scroll::LE)?;
+        }
+        if file_count < 0 {
+            return Err(error::Error::Malformed(format!("Negative bundle file count {}", file_count)));
+        }
+        // the files are kept up to the first entry that cannot be read
+        let mut files:Vec<BundleFileEntry> = Vec::new();
+        for _ in 0..file_count {
+            match BundleFileEntry::parse(bytes, &mut offset, major_version) {
+                Ok(file) => files.push(file),
+                Err(_) => break
+            }
+        }
+        Ok(Bundle {
+            header_offset,
+            major_version,
+            minor_version,
+            bundle_id,
+            deps_json,
+            runtime_config_json,
+            flags,
+            files
+        })
+    }
+    pub fn get_version(&self) -> String {
+        format!("{}.{}", self.major_version, self.minor_version)
+    }
+    pub fn get_file(&self, path: &str) -> Option<&BundleFileEntry> {
+        self.files.iter().find(|file| file.path == path)
+    }
+    pub fn get_assemblies(&self) -> Vec<&BundleFileEntry> {
+        self.files.iter().filter(|file| file.file_type == BundleFileType::Assembly).collect()
+    }
+    /// Content of the embedded `*.deps.json`
+    pub fn get_deps_json(&self, bytes: &[u8]) -> error::Result<Option<String>> {
+        self.get_json(bytes, BundleFileType::DepsJson, self.deps_json)
+    }
+    /// Content of the embedded `*.runtimeconfig.json`
+    pub fn get_runtime_config_json(&self, bytes: &[u8]) -> error::Result<Option<String>> {
+        self.get_json(bytes, BundleFileType::RuntimeConfigJson, self.runtime_config_json)
+    }
+    fn get_json(&self, bytes: &[u8], file_type: BundleFileType, location: Option<BundleLocation>) -> error::Result<Option<String>> {
+        // the file entry also knows about compression, the header location is only used by older bundles without one
+        let data:Vec<u8> = match (self.files.iter().find(|file| file.file_type == file_type), location) {
+            (Some(file), _) => file.extract(bytes)?,
+            (None, Some(location)) => get_range(bytes, location.offset, location.size)
+                .ok_or_else(|| error::Error::Malformed(format!("Bundle {:?} out of bounds", file_type)))?
+                .to_vec(),
+            (None, None) => return Ok(None)
+        };
+        Ok(Some(String::from_utf8_lossy(&data).into_owned()))
+    }
+}
+
+/// Manifest offsets stored before the bundle signature, searched in the raw section data
+fn find_header_offsets(bytes: &[u8], headers: &Headers) -> Vec<u64> {
+    let overlay_offset:u64 = headers.get_overlay_offset() as u64;
+    let mut header_offsets:Vec<u64> = Vec::new();
+    for section in &headers.sections.items {
+        let start:usize = section.pointer_to_raw_data as usize;
+        let end:usize = start.saturating_add(section.size_of_raw_data as usize).min(bytes.len());
+        if start + 8 >= end {
+            continue;
+        }
+        let positions = bytes[start + 8..end].windows(BUNDLE_SIGNATURE.len())
+            .enumerate()
+            .filter(|(_, window)| *window == BUNDLE_SIGNATURE)
+            .map(|(position, _)| start + position);
+        for position in positions {
+            // the apphost template carries the marker with a null offset, and the signature bytes
+            // may appear by chance in any image: only an offset into the overlay is a manifest
+            let header_offset:i64 = bytes.pread_with(position, scroll::LE).unwrap_or(0);
+            if header_offset > 0 && header_offset as u64 >= overlay_offset && (header_offset as u64) < bytes.len() as u64 {
+                header_offsets.push(header_offset as u64);
+            }
+        }
+    }
+    header_offsets
+}
+
+fn read_location(bytes: &[u8], offset: &mut usize) -> error::Result<Option<BundleLocation>> {
+    let location_offset:i64 = bytes.gread_with(offset, scroll::LE)?;
+    let size:i64 = bytes.gread_with(offset, scroll::LE)?;
+    if location_offset <= 0 || size < 0 {
+        return Ok(None);
+    }
+    Ok(Some(BundleLocation { offset: location_offset as u64, size: size as u64 }))
+}
+
+/// Reads a string prefixed with its 7-bit encoded length, as written by BinaryWriter
+fn read_string(bytes: &[u8], offset: &mut usize) -> error::Result<String> {
+    let mut length:usize = 0;
+    for shift in (0..35).step_by(7) {
+        let byte:u8 = bytes.gread_with(offset, scroll::LE)?;
+        length |= ((byte & 0x7f) as usize) << shift;
+        if byte & 0x80 == 0 {
+            let data:&[u8] = get_range(bytes, *offset as u64, length as u64)
+                .ok_or_else(|| error::Error::Malformed(format!("Bundle string of {} bytes out of bounds", length)))?;
+            *offset += length;
+            return Ok(String::from_utf8_lossy(data).into_owned());
+        }
+    }
+    Err(error::Error::Malformed(String::from("Bad 7-bit encoded string length in bundle")))
+}
+
+fn get_range(bytes: &[u8], offset: u64, size: u64) -> Option<&[u8]> {
+    let end:u64 = offset.checked_add(size)?;
+    if end > bytes.len() as u64 {
+        return None;
+    }
+    Some(&bytes[offset as usize..end as usize])
+}
+
+#[cfg(test)]
+mod tests {
+    use scroll::Pwrite;
+
+    use super::{Bundle, BundleFileEntry, BundleFileType, BUNDLE_SIGNATURE};
+    use crate::pe::header::test_headers;
+
+    fn push_string(bytes: &mut Vec<u8>, string: &str) {
+        bytes.push(string.len() as u8);
+        bytes.extend_from_slice(string.as_bytes());
+    }
+
+    #[test]
+    fn bundle() {
+        let headers = test_headers(0x1000, 0x200);
+        let assembly:Vec<u8> = b"MZ managed assembly MZ managed assembly MZ managed assembly".to_vec();
+        let compressed:Vec<u8> = miniz_oxide::deflate::compress_to_vec(&assembly, 6);
+        let runtime_config:&[u8] = b"{\"runtimeOptions\":{\"tfm\":\"net8.0\"}}";
+
+        let mut bytes = vec![0u8; 0x200];
+        bytes.pwrite_with(0x300u64, 0x100, scroll::LE).unwrap();
+        bytes[0x108..0x128].copy_from_slice(&BUNDLE_SIGNATURE);
+        bytes.extend_from_slice(&compressed);
+        let config_offset:usize = bytes.len();
+        bytes.extend_from_slice(runtime_config);
+        bytes.resize(0x300, 0);
+
+        let mut manifest:Vec<u8> = Vec::new();
+        for value in &[6u32, 0, 2] {
+            manifest.extend_from_slice(&value.to_le_bytes());
+        }
+        push_string(&mut manifest, "bundle-id");
+        for value in &[0i64, 0, config_offset as i64, runtime_config.len() as i64] {
+            manifest.extend_from_slice(&value.to_le_bytes());
+        }
+        manifest.extend_from_slice(&0u64.to_le_bytes());
+        for value in &[0x200i64, assembly.len() as i64, compressed.len() as i64] {
+            manifest.extend_from_slice(&value.to_le_bytes());
+        }
+        manifest.push(1);
+        push_string(&mut manifest, "App.dll");
+        for value in &[config_offset as i64, runtime_config.len() as i64, 0] {
+            manifest.extend_from_slice(&value.to_le_bytes());
+        }
+        manifest.push(4);
+        push_string(&mut manifest, "App.runtimeconfig.json");
+        bytes.extend_from_slice(&manifest);
+
+        let bundle = Bundle::parse(&bytes, &headers).unwrap().unwrap();
+        assert_eq!(bundle.get_version(), "6.0");
+        assert_eq!(bundle.bundle_id, "bundle-id");
+        assert_eq!(bundle.deps_json, None);
+        assert_eq!(bundle.files.len(), 2);
+        let file = bundle.get_file("App.dll").unwrap();
+        assert_eq!(file.file_type, BundleFileType::Assembly);
+        assert!(file.is_compressed());
+        assert_eq!(file.extract(&bytes).unwrap(), assembly);
+        assert_eq!(bundle.get_assemblies().len(), 1);
+        assert_eq!(bundle.get_runtime_config_json(&bytes).unwrap().unwrap(), "{\"runtimeOptions\":{\"tfm\":\"net8.0\"}}");
+        assert_eq!(bundle.get_deps_json(&bytes).unwrap(), None);
+
+        bytes.pwrite_with(0u64, 0x100, scroll::LE).unwrap();
+        assert_eq!(Bundle::parse(&bytes, &headers).unwrap(), None);
+    }
+
+    #[test]
+    fn bundle_malformed() {
+        let headers = test_headers(0x1000, 0x200);
+        let mut bytes = vec![0u8; 0x300];
+        // a stray signature with an offset inside the sections, then the apphost template marker
+        bytes.pwrite_with(0x80u64, 0x40, scroll::LE).unwrap();
+        bytes[0x48..0x68].copy_from_slice(&BUNDLE_SIGNATURE);
+        bytes.pwrite_with(-1i64, 0x100, scroll::LE).unwrap();
+        bytes[0x108..0x128].copy_from_slice(&BUNDLE_SIGNATURE);
+        assert_eq!(Bundle::parse(&bytes, &headers).unwrap(), None);
+        bytes.pwrite_with(0x1000u64, 0x100, scroll::LE).unwrap();
+        assert_eq!(Bundle::parse(&bytes, &headers).unwrap(), None);
+
+        // the manifest after a stray signature is still found
+        bytes.pwrite_with(0x200u64, 0x100, scroll::LE).unwrap();
+        bytes.pwrite_with(6u32, 0x200, scroll::LE).unwrap();
+        assert_eq!(Bundle::parse(&bytes, &headers).unwrap().unwrap().get_version(), "6.0");
+        // negative file count, the marker is not taken for a bundle
+        bytes.pwrite_with(-1i32, 0x208, scroll::LE).unwrap();
+        assert_eq!(Bundle::parse(&bytes, &headers).unwrap(), None);
+        // a manifest running past the end of the file is skipped for the next marker,
+        // whose files are kept up to the end of the file
+        bytes.pwrite_with(0x1000i32, 0x208, scroll::LE).unwrap();
+        bytes.pwrite_with(0x2f0u64, 0x100, scroll::LE).unwrap();
+        bytes.pwrite_with(6u32, 0x2f0, scroll::LE).unwrap();
+        bytes.pwrite_with(0x200u64, 0x140, scroll::LE).unwrap();
+        bytes[0x148..0x168].copy_from_slice(&BUNDLE_SIGNATURE);
+        let bundle = Bundle::parse(&bytes, &headers).unwrap().unwrap();
+        assert_eq!(bundle.header_offset, 0x200);
+        assert_eq!(bundle.files.len(), 7);
+        // bad 7-bit encoded length of the bundle id
+        bytes[0x20c..0x211].copy_from_slice(&[0xff; 5]);
+        assert_eq!(Bundle::parse(&bytes, &headers).unwrap(), None);
+
+        // stream inflating past its declared size, and one shorter than it
+        let compressed:Vec<u8> = miniz_oxide::deflate::compress_to_vec(&[0u8; 0x10000], 6);
+        let mut file = BundleFileEntry { offset: 0x300, size: 0x100, compressed_size: compressed.len() as u64, file_type: BundleFileType::Assembly, path: String::from("App.dll") };
+        bytes.extend_from_slice(&compressed);
+        assert!(file.extract(&bytes).is_err());
+        file.size = 0x20000;
+        assert!(file.extract(&bytes).is_err());
+        file.size = 0x10000;
+        assert_eq!(file.extract(&bytes).unwrap().len(), 0x10000);
+        file.offset = bytes.len() as u64;
+        assert!(file.extract(&bytes).is_err());
+    }
+}
@@ -0,0 +1,355 @@
+use scroll::{IOread, IOwrite, Pread, Pwrite, SizeWith};
+use serde::{Deserialize, Serialize, Serializer};
+use serde::ser::SerializeStruct;
+
+use crate::error;
+use crate::pe::header::{Headers, IMAGE_DIRECTORY_ENTRY_IAT, OPTIONAL_HEADER_SIGNATURE_64};
+use crate::pe::load_config::LoadConfigDirectory;
+
+/// Architecture stored in the two low bits of a code range start
+pub const IMAGE_CHPE_RANGE_ARM64: u32 = 0;
+pub const IMAGE_CHPE_RANGE_ARM64EC: u32 = 1;
+pub const IMAGE_CHPE_RANGE_AMD64: u32 = 2;
+
+/// Hybrid metadata of ARM64EC and ARM64X images, fields are RVAs
+#[derive(Debug, PartialEq, Copy, Clone, Default, Pread, Pwrite, IOread, IOwrite, SizeWith, Deserialize)]
+pub struct Arm64EcMetadata {
+    pub version: u32,
+    pub code_map: u32,
+    pub code_map_count: u32,
+    pub code_ranges_to_entry_points: u32,
+    pub redirection_metadata: u32,
+    pub os_arm64x_dispatch_call_no_redirect: u32,
+    pub os_arm64x_dispatch_ret: u32,
+    pub os_arm64x_dispatch_call: u32,
+    pub os_arm64x_dispatch_icall: u32,
+    pub os_arm64x_dispatch_icall_cfg: u32,
+    pub alternate_entry_point: u32,
+    pub auxiliary_iat: u32,
+    pub code_ranges_to_entry_points_count: u32,
+    pub redirection_metadata_count: u32,
+    pub get_x64_information_function_pointer: u32,
+    pub set_x64_information_function_pointer: u32,
+    pub extra_rfe_table: u32,
+    pub extra_rfe_table_size: u32,
+    pub os_arm64x_dispatch_fptr: u32,
+    pub auxiliary_iat_copy: u32,
+}
+
+impl Serialize for Arm64EcMetadata {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("Arm64EcMetadata", 20)?;
+        state.serialize_field("version", &self.version)?;
+        state.serialize_field("code_map", &format!("0x{:x}", &self.code_map))?;
+        state.serialize_field("code_map_count", &self.code_map_count)?;
+        state.serialize_field("code_ranges_to_entry_points", &format!("0x{:x}", &self.code_ranges_to_entry_points))?;
+        state.serialize_field("redirection_metadata", &format!("0x{:x}", &self.redirection_metadata))?;
+        state.serialize_field("os_arm64x_dispatch_call_no_redirect", &format!("0x{:x}", &self.os_arm64x_dispatch_call_no_redirect))?;
+        state.serialize_field("os_arm64x_dispatch_ret", &format!("0x{:x}", &self.os_arm64x_dispatch_ret))?;
+        state.serialize_field("os_arm64x_dispatch_call", &format!("0x{:x}", &self.os_arm64x_dispatch_call))?;
+        state.serialize_field("os_arm64x_dispatch_icall", &format!("0x{:x}", &self.os_arm64x_dispatch_icall))?;
+        state.serialize_field("os_arm64x_dispatch_icall_cfg", &format!("0x{:x}", &self.os_arm64x_dispatch_icall_cfg))?;
+        state.serialize_field("alternate_entry_point", &format!("0x{:x}", &self.alternate_entry_point))?;
+        state.serialize_field("auxiliary_iat", &format!("0x{:x}", &self.auxiliary_iat))?;
+        state.serialize_field("code_ranges_to_entry_points_count", &self.code_ranges_to_entry_points_count)?;
+        state.serialize_field("redirection_metadata_count", &self.redirection_metadata_count)?;
+        state.serialize_field("get_x64_information_function_pointer", &format!("0x{:x}", &self.get_x64_information_function_pointer))?;
+        state.serialize_field("set_x64_information_function_pointer", &format!("0x{:x}", &self.set_x64_information_function_pointer))?;
+        state.serialize_field("extra_rfe_table", &format!("0x{:x}", &self.extra_rfe_table))?;
+        state.serialize_field("extra_rfe_table_size", &self.extra_rfe_table_size)?;
+        state.serialize_field("os_arm64x_dispatch_fptr", &format!("0x{:x}", &self.os_arm64x_dispatch_fptr))?;
+        state.serialize_field("auxiliary_iat_copy", &format!("0x{:x}", &self.auxiliary_iat_copy))?;
+        state.end()
+    }
+}
+
+impl Arm64EcMetadata {
+    pub fn parse(bytes: &[u8], offset: &mut usize) -> error::Result<Self> {
+        Ok(bytes.gread_with(offset, scroll::LE)?)
+    }
+}
+
+#[derive(Debug, PartialEq, Copy, Clone, Serialize, Deserialize)]
+pub enum ChpeCodeType {
+    Arm64,
+    Arm64Ec,
+    Amd64,
+    Unknown,
+}
+
+/// Range of the code map, the architecture is encoded in the low bits of `start_offset`
+#[derive(Debug, PartialEq, Copy, Clone, Default, Pread, Pwrite, IOread, IOwrite, SizeWith, Deserialize)]
+pub struct ChpeRangeEntry {
+    pub start_offset: u32,
+    pub length: u32,
+}
+
+impl Serialize for ChpeRangeEntry {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("ChpeRangeEntry", 4)?;
+        state.serialize_field("start", &format!("0x{:x}", &self.get_start()))?;
+        state.serialize_field("end", &format!("0x{:x}", &self.get_end()))?;
+        state.serialize_field("length", &self.length)?;
+        state.serialize_field("code_type", &self.get_code_type())?;
+        state.end()
+    }
+}
+
+impl ChpeRangeEntry {
+    pub fn parse(bytes: &[u8], offset: &mut usize) -> error::Result<Self> {
+        Ok(bytes.gread_with(offset, scroll::LE)?)
+    }
+    pub fn get_start(&self) -> u32 {
+        self.start_offset & !3
+    }
+    pub fn get_end(&self) -> u32 {
+        self.get_start().wrapping_add(self.length)
+    }
+    pub fn get_code_type(&self) -> ChpeCodeType {
+        match self.start_offset & 3 {
+            IMAGE_CHPE_RANGE_ARM64 => ChpeCodeType::Arm64,
+            IMAGE_CHPE_RANGE_ARM64EC => ChpeCodeType::Arm64Ec,
+            IMAGE_CHPE_RANGE_AMD64 => ChpeCodeType::Amd64,
+            _ => ChpeCodeType::Unknown
+        }
+    }
+}
+
+/// x64 code range and the entry point used when it is called from ARM64EC code
+#[derive(Debug, PartialEq, Copy, Clone, Default, Pread, Pwrite, IOread, IOwrite, SizeWith, Deserialize)]
+pub struct CodeRangeEntryPoint {
+    pub start_rva: u32,
+    pub end_rva: u32,
+    pub entry_point: u32,
+}
+
+impl Serialize for CodeRangeEntryPoint {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("CodeRangeEntryPoint", 3)?;
+        state.serialize_field("start_rva", &format!("0x{:x}", &self.start_rva))?;
+        state.serialize_field("end_rva", &format!("0x{:x}", &self.end_rva))?;
+        state.serialize_field("entry_point", &format!("0x{:x}", &self.entry_point))?;
+        state.end()
+    }
+}
+
+impl CodeRangeEntryPoint {
+    pub fn parse(bytes: &[u8], offset: &mut usize) -> error::Result<Self> {
+        Ok(bytes.gread_with(offset, scroll::LE)?)
+    }
+}
+
+/// Entry point redirection, `source` exported entry is redirected to `destination`
+#[derive(Debug, PartialEq, Copy, Clone, Default, Pread, Pwrite, IOread, IOwrite, SizeWith, Deserialize)]
+pub struct RedirectionEntry {
+    pub source: u32,
+    pub destination: u32,
+}
+
+impl Serialize for RedirectionEntry {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("RedirectionEntry", 2)?;
+        state.serialize_field("source", &format!("0x{:x}", &self.source))?;
+        state.serialize_field("destination", &format!("0x{:x}", &self.destination))?;
+        state.end()
+    }
+}
+
+impl RedirectionEntry {
+    pub fn parse(bytes: &[u8], offset: &mut usize) -> error::Result<Self> {
+        Ok(bytes.gread_with(offset, scroll::LE)?)
+    }
+}
+
+/// Reads a table of the metadata, truncated at the end of the file and empty when it lies outside of the image
+fn parse_table<T, F: Fn(&[u8], &mut usize) -> error::Result<T>>(bytes: &[u8], headers: &Headers, rva: u32, count: u32, parse: F) -> Vec<T> {
+    let mut entries:Vec<T> = Vec::new();
+    if rva == 0 {
+        return entries;
+    }
+    if let Some(mut offset) = headers.rva_to_offset(rva) {
+        for _ in 0..count {
+            match parse(bytes, &mut offset) {
+                Ok(entry) => entries.push(entry),
+                Err(_) => break
+            }
+        }
+    }
+    entries
+}
+
+#[derive(Debug, PartialEq, Clone, Default, Deserialize)]
+pub struct HybridMetadata {
+    pub metadata: Arm64EcMetadata,
+    /// Version 2 only
+    pub auxiliary_delayload_iat: Option<u32>,
+    /// Version 2 only
+    pub auxiliary_delayload_iat_copy: Option<u32>,
+    pub code_ranges: Vec<ChpeRangeEntry>,
+    pub entry_points: Vec<CodeRangeEntryPoint>,
+    pub redirections: Vec<RedirectionEntry>,
+    /// Pointers of the auxiliary IAT, parallel to the IAT slots
+    pub auxiliary_iat: Vec<u64>,
+}
+
+impl Serialize for HybridMetadata {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("HybridMetadata", 7)?;
+        state.serialize_field("metadata", &self.metadata)?;
+        state.serialize_field("auxiliary_delayload_iat", &self.auxiliary_delayload_iat.map(|rva| format!("0x{:x}", rva)))?;
+        state.serialize_field("auxiliary_delayload_iat_copy", &self.auxiliary_delayload_iat_copy.map(|rva| format!("0x{:x}", rva)))?;
+        state.serialize_field("code_ranges", &self.code_ranges)?;
+        state.serialize_field("entry_points", &self.entry_points)?;
+        state.serialize_field("redirections", &self.redirections)?;
+        state.serialize_field("auxiliary_iat", &self.auxiliary_iat.iter().map(|pointer| format!("0x{:x}", pointer)).collect::<Vec<String>>())?;
+        state.end()
+    }
+}
+
+impl HybridMetadata {
+    /// Follows the CHPE pointer of PE32+ images, `None` when the image is not hybrid
+    pub fn parse(bytes: &[u8], headers: &Headers, load_config: &LoadConfigDirectory) -> error::Result<Option<Self>> {
+        if load_config.chpe_metadata_pointer == 0 || headers.optional.standard_fields.signature != OPTIONAL_HEADER_SIGNATURE_64 {
+            return Ok(None);
+        }
+        let image_base:u64 = headers.optional.specific_fields.image_base;
+        let metadata_rva:u32 = load_config.chpe_metadata_pointer.wrapping_sub(image_base) as u32;
+        let mut offset:usize = match headers.rva_to_offset(metadata_rva) {
+            Some(offset) => offset,
+            None => return Ok(None)
+        };
+        let metadata:Arm64EcMetadata = match Arm64EcMetadata::parse(bytes, &mut offset) {
+            Ok(metadata) => metadata,
+            Err(_) => return Ok(None)
+        };
+        let mut hybrid_metadata:HybridMetadata = HybridMetadata { metadata, ..Default::default() };
+        if metadata.version >= 2 {
+            hybrid_metadata.auxiliary_delayload_iat = bytes.gread_with(&mut offset, scroll::LE).ok();
+            hybrid_metadata.auxiliary_delayload_iat_copy = bytes.gread_with(&mut offset, scroll::LE).ok();
+        }
+
+        hybrid_metadata.code_ranges = parse_table(bytes, headers, metadata.code_map, metadata.code_map_count, ChpeRangeEntry::parse);
+        hybrid_metadata.entry_points = parse_table(bytes, headers, metadata.code_ranges_to_entry_points, metadata.code_ranges_to_entry_points_count, CodeRangeEntryPoint::parse);
+        hybrid_metadata.redirections = parse_table(bytes, headers, metadata.redirection_metadata, metadata.redirection_metadata_count, RedirectionEntry::parse);
+        // The auxiliary IAT has as many slots as the IAT
+        if let Some(iat) = headers.get_data_directory(IMAGE_DIRECTORY_ENTRY_IAT) {
+            hybrid_metadata.auxiliary_iat = parse_table(bytes, headers, metadata.auxiliary_iat, iat.size / 8, |bytes, offset| Ok(bytes.gread_with(offset, scroll::LE)?));
+        }
+        Ok(Some(hybrid_metadata))
+    }
+    /// Architecture of the code at `rva`, `None` outside of the code map
+    pub fn get_code_type(&self, rva: u32) -> Option<ChpeCodeType> {
+        self.code_ranges.iter()
+            .find(|range| range.get_start() <= rva && rva < range.get_end())
+            .map(|range| range.get_code_type())
+    }
+    /// Entry point used by ARM64EC callers of the x64 code at `rva`
+    pub fn get_x64_entry_point(&self, rva: u32) -> Option<u32> {
+        self.entry_points.iter()
+            .find(|entry_point| entry_point.start_rva <= rva && rva < entry_point.end_rva)
+            .map(|entry_point| entry_point.entry_point)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use scroll::Pwrite;
+
+    use super::{Arm64EcMetadata, ChpeCodeType, CodeRangeEntryPoint, HybridMetadata, RedirectionEntry};
+    use crate::pe::header::{DataDirectory, IMAGE_DIRECTORY_ENTRY_IAT, IMAGE_DIRECTORY_ENTRY_LOAD_CONFIG, OPTIONAL_HEADER_SIGNATURE_64, test_headers, test_image};
+    use crate::pe::load_config::LoadConfigDirectory;
+    use crate::pe::pe::PE;
+
+    #[test]
+    fn hybrid_metadata() {
+        let mut headers = test_headers(0x1000, 0x200);
+        headers.optional.standard_fields.signature = OPTIONAL_HEADER_SIGNATURE_64;
+        headers.optional.specific_fields.image_base = 0x1_8000_0000;
+        headers.optional.data_directories.items = vec![DataDirectory::default(); IMAGE_DIRECTORY_ENTRY_IAT + 1];
+        headers.optional.data_directories.items[IMAGE_DIRECTORY_ENTRY_IAT] = DataDirectory { virtual_address: 0x1180, size: 16 };
+        let mut bytes = vec![0u8; 0x200];
+        bytes.pwrite_with(Arm64EcMetadata {
+            version: 1,
+            code_map: 0x1100,
+            code_map_count: 3,
+            code_ranges_to_entry_points: 0x1120,
+            code_ranges_to_entry_points_count: 1,
+            redirection_metadata: 0x1140,
+            redirection_metadata_count: 1,
+            auxiliary_iat: 0x1160,
+            ..Default::default()
+        }, 0, scroll::LE).unwrap();
+        bytes.pwrite_with(0x1000u32, 0x100, scroll::LE).unwrap();
+        bytes.pwrite_with(0x100u32, 0x104, scroll::LE).unwrap();
+        bytes.pwrite_with(0x1101u32, 0x108, scroll::LE).unwrap();
+        bytes.pwrite_with(0x100u32, 0x10c, scroll::LE).unwrap();
+        bytes.pwrite_with(0x1202u32, 0x110, scroll::LE).unwrap();
+        bytes.pwrite_with(0x80u32, 0x114, scroll::LE).unwrap();
+        bytes.pwrite_with(CodeRangeEntryPoint { start_rva: 0x1200, end_rva: 0x1280, entry_point: 0x1210 }, 0x120, scroll::LE).unwrap();
+        bytes.pwrite_with(RedirectionEntry { source: 0x1010, destination: 0x1110 }, 0x140, scroll::LE).unwrap();
+        bytes.pwrite_with(0x1_8000_1040u64, 0x160, scroll::LE).unwrap();
+        bytes.pwrite_with(0x1_8000_1048u64, 0x168, scroll::LE).unwrap();
+
+        let load_config = LoadConfigDirectory { chpe_metadata_pointer: 0x1_8000_1000, ..Default::default() };
+        let hybrid_metadata = HybridMetadata::parse(&bytes, &headers, &load_config).unwrap().unwrap();
+        assert_eq!(hybrid_metadata.code_ranges.len(), 3);
+        assert_eq!(hybrid_metadata.get_code_type(0x1050), Some(ChpeCodeType::Arm64));
+        assert_eq!(hybrid_metadata.get_code_type(0x1150), Some(ChpeCodeType::Arm64Ec));
+        assert_eq!(hybrid_metadata.get_code_type(0x1250), Some(ChpeCodeType::Amd64));
+        assert_eq!(hybrid_metadata.get_code_type(0x1300), None);
+        assert_eq!(hybrid_metadata.get_x64_entry_point(0x1240), Some(0x1210));
+        assert_eq!(hybrid_metadata.redirections[0].destination, 0x1110);
+        assert_eq!(hybrid_metadata.auxiliary_iat, vec![0x1_8000_1040, 0x1_8000_1048]);
+        assert!(HybridMetadata::parse(&bytes, &headers, &LoadConfigDirectory::default()).unwrap().is_none());
+    }
+
+    #[test]
+    fn hybrid_metadata_malformed() {
+        let mut data = vec![0u8; 0x200];
+        // 64-bit load configuration up to the CHPE metadata pointer
+        data.pwrite_with(0xd0u32, 0, scroll::LE).unwrap();
+        data.pwrite_with(0x401100u64, 200, scroll::LE).unwrap();
+        // code map running past the end of the file, entry points outside of the image
+        data.pwrite_with(Arm64EcMetadata {
+            version: 1,
+            code_map: 0x11f0,
+            code_map_count: 0x1000,
+            code_ranges_to_entry_points: 0x9000,
+            code_ranges_to_entry_points_count: 1,
+            redirection_metadata: 0x1180,
+            redirection_metadata_count: 1,
+            ..Default::default()
+        }, 0x100, scroll::LE).unwrap();
+        data.pwrite_with(0x1001u32, 0x1f0, scroll::LE).unwrap();
+        data.pwrite_with(0x100u32, 0x1f4, scroll::LE).unwrap();
+        data.pwrite_with(RedirectionEntry { source: 0x1010, destination: 0x1110 }, 0x180, scroll::LE).unwrap();
+        let load_config_directory = DataDirectory { virtual_address: 0x1000, size: 0xd0 };
+        let pe = PE::new(&test_image(&[(IMAGE_DIRECTORY_ENTRY_LOAD_CONFIG, load_config_directory)], &data)).unwrap();
+        let hybrid_metadata = pe.hybrid_metadata.unwrap();
+        assert_eq!(hybrid_metadata.code_ranges.len(), 2);
+        assert_eq!(hybrid_metadata.get_code_type(0x1050), Some(ChpeCodeType::Arm64Ec));
+        assert!(hybrid_metadata.entry_points.is_empty());
+        assert_eq!(hybrid_metadata.redirections[0].destination, 0x1110);
+
+        // metadata pointer outside of the image
+        data.pwrite_with(0x409000u64, 200, scroll::LE).unwrap();
+        let pe = PE::new(&test_image(&[(IMAGE_DIRECTORY_ENTRY_LOAD_CONFIG, load_config_directory)], &data)).unwrap();
+        assert!(pe.hybrid_metadata.is_none());
+        assert_eq!(pe.load_config_directory.unwrap().chpe_metadata_pointer, 0x409000);
+    }
+}
@@ -0,0 +1,209 @@
+use scroll::{IOread, IOwrite, Pread, Pwrite, SizeWith};
+use serde::{Deserialize, Serialize, Serializer};
+use serde::ser::SerializeStruct;
+
+use crate::error;
+use crate::pe::header::{DataDirectory, Headers};
+use crate::pe::index;
+
+pub const COMIMAGE_FLAGS_ILONLY: u32 = 0x00000001;
+pub const COMIMAGE_FLAGS_32BITREQUIRED: u32 = 0x00000002;
+pub const COMIMAGE_FLAGS_IL_LIBRARY: u32 = 0x00000004;
+pub const COMIMAGE_FLAGS_STRONGNAMESIGNED: u32 = 0x00000008;
+pub const COMIMAGE_FLAGS_NATIVE_ENTRYPOINT: u32 = 0x00000010;
+pub const COMIMAGE_FLAGS_TRACKDEBUGDATA: u32 = 0x00010000;
+pub const COMIMAGE_FLAGS_32BITPREFERRED: u32 = 0x00020000;
+
+pub const COR_VTABLE_32BIT: u16 = 0x01;
+pub const COR_VTABLE_64BIT: u16 = 0x02;
+pub const COR_VTABLE_FROM_UNMANAGED: u16 = 0x04;
+pub const COR_VTABLE_FROM_UNMANAGED_RETAIN_APPDOMAIN: u16 = 0x08;
+pub const COR_VTABLE_CALL_MOST_DERIVED: u16 = 0x10;
+
+/// IMAGE_COR20_HEADER
+#[derive(Debug, PartialEq, Copy, Clone, Default, Pread, Pwrite, IOread, IOwrite, SizeWith, Deserialize)]
+pub struct ClrHeader {
+    pub cb: u32,
+    pub major_runtime_version: u16,
+    pub minor_runtime_version: u16,
+    pub metadata: DataDirectory,
+    pub flags: u32,
+    /// Metadata token of the entry point method, or RVA with COMIMAGE_FLAGS_NATIVE_ENTRYPOINT
+    pub entry_point: u32,
+    pub resources: DataDirectory,
+    pub strong_name_signature: DataDirectory,
+    pub code_manager_table: DataDirectory,
+    pub vtable_fixups: DataDirectory,
+    pub export_address_table_jumps: DataDirectory,
+    pub managed_native_header: DataDirectory,
+}
+
+impl Serialize for ClrHeader {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("ClrHeader", 13)?;
+        state.serialize_field("cb", &self.cb)?;
+        state.serialize_field("major_runtime_version", &self.major_runtime_version)?;
+        state.serialize_field("minor_runtime_version", &self.minor_runtime_version)?;
+        state.serialize_field("metadata", &self.metadata)?;
+        state.serialize_field("flags", &format!("0x{:x}", &self.flags))?;
+        state.serialize_field("flag_names", &get_flag_names(self.flags))?;
+        state.serialize_field("entry_point", &format!("0x{:x}", &self.entry_point))?;
+        state.serialize_field("resources", &self.resources)?;
+        state.serialize_field("strong_name_signature", &self.strong_name_signature)?;
+        state.serialize_field("code_manager_table", &self.code_manager_table)?;
+        state.serialize_field("vtable_fixups", &self.vtable_fixups)?;
+        state.serialize_field("export_address_table_jumps", &self.export_address_table_jumps)?;
+        state.serialize_field("managed_native_header", &self.managed_native_header)?;
+        state.end()
+    }
+}
+
+impl ClrHeader {
+    pub fn parse(bytes: &[u8], offset: &mut usize) -> error::Result<Self> {
+        Ok(bytes.gread_with(offset, scroll::LE)?)
+    }
+    pub fn get_runtime_version(&self) -> String {
+        format!("{}.{}", self.major_runtime_version, self.minor_runtime_version)
+    }
+    /// Metadata token of the managed entry point, `None` for native entry points or libraries
+    pub fn get_entry_point_token(&self) -> Option<u32> {
+        match self.entry_point {
+            0 => None,
+            _ if self.flags & COMIMAGE_FLAGS_NATIVE_ENTRYPOINT != 0 => None,
+            token => Some(token)
+        }
+    }
+    /// RVA of the native entry point of mixed mode images
+    pub fn get_entry_point_rva(&self) -> Option<u32> {
+        match self.entry_point {
+            0 => None,
+            rva if self.flags & COMIMAGE_FLAGS_NATIVE_ENTRYPOINT != 0 => Some(rva),
+            _ => None
+        }
+    }
+    pub fn is_il_only(&self) -> bool {
+        self.flags & COMIMAGE_FLAGS_ILONLY != 0
+    }
+    pub fn is_strong_name_signed(&self) -> bool {
+        self.flags & COMIMAGE_FLAGS_STRONGNAMESIGNED != 0
+    }
+}
+
+/// Named bits of the CLR header flags, sorted by value
+pub fn get_flag_names(flags: u32) -> Vec<String> {
+    let mut names:Vec<(&u32, &&str)> = index::COMIMAGEFLAG.iter().filter(|(flag, _)| flags & **flag != 0).collect();
+    names.sort();
+    names.into_iter().map(|(_, name)| name.to_string()).collect()
+}
+
+/// IMAGE_COR_VTABLEFIXUP, a table of `count` slots patched with method addresses
+#[derive(Debug, PartialEq, Copy, Clone, Default, Pread, Pwrite, IOread, IOwrite, SizeWith, Deserialize)]
+pub struct VTableFixup {
+    pub rva: u32,
+    pub count: u16,
+    pub fixup_type: u16,
+}
+
+impl Serialize for VTableFixup {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("VTableFixup", 3)?;
+        state.serialize_field("rva", &format!("0x{:x}", &self.rva))?;
+        state.serialize_field("count", &self.count)?;
+        state.serialize_field("fixup_type", &format!("0x{:x}", &self.fixup_type))?;
+        state.end()
+    }
+}
+
+impl VTableFixup {
+    pub fn parse(bytes: &[u8], offset: &mut usize) -> error::Result<Self> {
+        Ok(bytes.gread_with(offset, scroll::LE)?)
+    }
+}
+
+/// CLR runtime header of .NET images
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct ClrDirectory {
+    pub header: ClrHeader,
+    pub vtable_fixups: Vec<VTableFixup>,
+}
+
+impl ClrDirectory {
+    pub fn parse(bytes: &[u8], headers: &Headers, data_directory: DataDirectory) -> error::Result<Self> {
+        let mut offset:usize = headers.rva_to_offset(data_directory.virtual_address).ok_or(error::Error::BadRva(data_directory.virtual_address))?;
+        let header:ClrHeader = ClrHeader::parse(bytes, &mut offset)?;
+        // The fixups are only kept as far as they can be read, the header stands on its own
+        let mut vtable_fixups:Vec<VTableFixup> = Vec::new();
+        if header.vtable_fixups.virtual_address != 0 {
+            if let Some(mut offset) = headers.rva_to_offset(header.vtable_fixups.virtual_address) {
+                for _ in 0..header.vtable_fixups.size / 8 {
+                    match VTableFixup::parse(bytes, &mut offset) {
+                        Ok(vtable_fixup) => vtable_fixups.push(vtable_fixup),
+                        Err(_) => break
+                    }
+                }
+            }
+        }
+        Ok(ClrDirectory { header, vtable_fixups })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use scroll::Pwrite;
+
+    use super::{ClrDirectory, ClrHeader, VTableFixup, COMIMAGE_FLAGS_ILONLY, COMIMAGE_FLAGS_STRONGNAMESIGNED, COR_VTABLE_32BIT};
+    use crate::pe::header::{DataDirectory, IMAGE_DIRECTORY_ENTRY_COM_DESCRIPTOR, test_headers, test_image};
+    use crate::pe::pe::PE;
+
+    #[test]
+    fn clr_directory() {
+        let headers = test_headers(0x2000, 0x200);
+        let mut bytes = vec![0u8; 0x200];
+        bytes.pwrite_with(ClrHeader {
+            cb: 72,
+            major_runtime_version: 2,
+            minor_runtime_version: 5,
+            metadata: DataDirectory { virtual_address: 0x2100, size: 0x80 },
+            flags: COMIMAGE_FLAGS_ILONLY | COMIMAGE_FLAGS_STRONGNAMESIGNED,
+            entry_point: 0x06000001,
+            vtable_fixups: DataDirectory { virtual_address: 0x2050, size: 8 },
+            ..Default::default()
+        }, 8, scroll::LE).unwrap();
+        bytes.pwrite_with(VTableFixup { rva: 0x2180, count: 2, fixup_type: COR_VTABLE_32BIT }, 0x50, scroll::LE).unwrap();
+
+        let clr_directory = ClrDirectory::parse(&bytes, &headers, DataDirectory { virtual_address: 0x2008, size: 72 }).unwrap();
+        assert_eq!(clr_directory.header.get_runtime_version(), "2.5");
+        assert!(clr_directory.header.is_il_only());
+        assert!(clr_directory.header.is_strong_name_signed());
+        assert_eq!(clr_directory.header.get_entry_point_token(), Some(0x06000001));
+        assert_eq!(clr_directory.header.get_entry_point_rva(), None);
+        assert_eq!(clr_directory.vtable_fixups[0].count, 2);
+        assert_eq!(super::get_flag_names(clr_directory.header.flags), vec!["COMIMAGE_FLAGS_ILONLY", "COMIMAGE_FLAGS_STRONGNAMESIGNED"]);
+    }
+
+    #[test]
+    fn clr_directory_malformed() {
+        let mut data = vec![0u8; 0x200];
+        let com_descriptor = DataDirectory { virtual_address: 0x1000, size: 72 };
+        // vtable fixups outside of the image
+        data.pwrite_with(ClrHeader { cb: 72, vtable_fixups: DataDirectory { virtual_address: 0x9000, size: 8 }, ..Default::default() }, 0, scroll::LE).unwrap();
+        let pe = PE::new(&test_image(&[(IMAGE_DIRECTORY_ENTRY_COM_DESCRIPTOR, com_descriptor)], &data)).unwrap();
+        assert!(pe.is_dotnet());
+        assert!(pe.clr_directory.unwrap().vtable_fixups.is_empty());
+        // vtable fixups running past the end of the file
+        data.pwrite_with(ClrHeader { cb: 72, vtable_fixups: DataDirectory { virtual_address: 0x11f8, size: 0x80 }, ..Default::default() }, 0, scroll::LE).unwrap();
+        data.pwrite_with(VTableFixup { rva: 0x1100, count: 1, fixup_type: COR_VTABLE_32BIT }, 0x1f8, scroll::LE).unwrap();
+        let pe = PE::new(&test_image(&[(IMAGE_DIRECTORY_ENTRY_COM_DESCRIPTOR, com_descriptor)], &data)).unwrap();
+        assert_eq!(pe.clr_directory.unwrap().vtable_fixups, vec![VTableFixup { rva: 0x1100, count: 1, fixup_type: COR_VTABLE_32BIT }]);
+        // header cut by the end of the file
+        let com_descriptor = DataDirectory { virtual_address: 0x11f0, size: 72 };
+        let pe = PE::new(&test_image(&[(IMAGE_DIRECTORY_ENTRY_COM_DESCRIPTOR, com_descriptor)], &data)).unwrap();
+        assert!(!pe.is_dotnet());
+    }
+}
@@ -0,0 +1,410 @@
+use std::convert::TryInto;
+
+use scroll::{IOread, IOwrite, Pread, Pwrite, SizeWith};
+use scroll::ctx::SizeWith as _;
+use serde::{Deserialize, Serialize, Serializer};
+use serde::ser::SerializeStruct;
+
+use crate::error;
+use crate::pe::header::{DataDirectory, Headers};
+use crate::pe::index;
+
+pub const IMAGE_DEBUG_TYPE_CODEVIEW: u32 = 2;
+pub const IMAGE_DEBUG_TYPE_VC_FEATURE: u32 = 12;
+pub const IMAGE_DEBUG_TYPE_POGO: u32 = 13;
+pub const IMAGE_DEBUG_TYPE_REPRO: u32 = 16;
+pub const IMAGE_DEBUG_TYPE_EX_DLLCHARACTERISTICS: u32 = 20;
+
+pub const IMAGE_DLLCHARACTERISTICS_EX_CET_COMPAT: u32 = 0x0001;
+pub const IMAGE_DLLCHARACTERISTICS_EX_FORWARD_CFI_COMPAT: u32 = 0x0040;
+
+/// `RSDS`, CodeView 7.0 record of PDB 7.0 files
+pub const CODEVIEW_RSDS_SIGNATURE: u32 = 0x53445352;
+/// `NB10`, CodeView 4.10 record of PDB 2.0 files
+pub const CODEVIEW_NB10_SIGNATURE: u32 = 0x3031424e;
+
+#[derive(Debug, PartialEq, Copy, Clone, Default, Pread, Pwrite, IOread, IOwrite, SizeWith, Deserialize)]
+pub struct ImageDebugDirectory {
+    pub characteristics: u32,
+    pub time_date_stamp: u32,
+    pub major_version: u16,
+    pub minor_version: u16,
+    pub debug_type: u32,
+    pub size_of_data: u32,
+    pub address_of_raw_data: u32,
+    pub pointer_to_raw_data: u32,
+}
+
+impl Serialize for ImageDebugDirectory {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("ImageDebugDirectory", 8)?;
+        state.serialize_field("characteristics", &format!("0x{:x}", &self.characteristics))?;
+        state.serialize_field("time_date_stamp", &format!("0x{:x}", &self.time_date_stamp))?;
+        state.serialize_field("major_version", &self.major_version)?;
+        state.serialize_field("minor_version", &self.minor_version)?;
+        state.serialize_field("debug_type", &index::DEBUGTYPE.get(&self.debug_type).map(|name| name.to_string()).unwrap_or_else(|| format!("0x{:x}", &self.debug_type)))?;
+        state.serialize_field("size_of_data", &self.size_of_data)?;
+        state.serialize_field("address_of_raw_data", &format!("0x{:x}", &self.address_of_raw_data))?;
+        state.serialize_field("pointer_to_raw_data", &format!("0x{:x}", &self.pointer_to_raw_data))?;
+        state.end()
+    }
+}
+
+impl ImageDebugDirectory {
+    pub fn parse(bytes: &[u8], offset: &mut usize) -> error::Result<Self> {
+        Ok(bytes.gread_with(offset, scroll::LE)?)
+    }
+    /// Raw data of the entry, located through the file pointer or, failing that, the RVA
+    pub fn get_data<'a>(&self, bytes: &'a [u8], headers: &Headers) -> error::Result<&'a [u8]> {
+        let offset:usize = if self.pointer_to_raw_data != 0 {
+            self.pointer_to_raw_data as usize
+        } else {
+            headers.rva_to_offset(self.address_of_raw_data).ok_or(error::Error::BadRva(self.address_of_raw_data))?
+        };
+        Ok(bytes.pread_with::<&[u8]>(offset, self.size_of_data as usize)?)
+    }
+}
+
+/// Formats a GUID stored as `Data1`, `Data2`, `Data3` little endian followed by 8 bytes
+pub fn format_guid(guid: &[u8; 16]) -> String {
+    format!("{{{:08X}-{:04X}-{:04X}-{:02X}{:02X}-{:02X}{:02X}{:02X}{:02X}{:02X}{:02X}}}",
+        u32::from_le_bytes([guid[0], guid[1], guid[2], guid[3]]),
+        u16::from_le_bytes([guid[4], guid[5]]),
+        u16::from_le_bytes([guid[6], guid[7]]),
+        guid[8], guid[9], guid[10], guid[11], guid[12], guid[13], guid[14], guid[15])
+}
+
+fn read_path(bytes: &[u8], offset: usize) -> String {
+    let path:&[u8] = bytes.get(offset..).unwrap_or(&[]);
+    let end:usize = path.iter().position(|&c| c == 0).unwrap_or(path.len());
+    String::from_utf8_lossy(&path[..end]).into_owned()
+}
+
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct CodeViewRsds {
+    pub guid: String,
+    pub age: u32,
+    pub path: String,
+}
+
+impl CodeViewRsds {
+    /// Key symbol servers store the PDB under, GUID digits followed by the age in hexadecimal
+    pub fn get_symbol_key(&self) -> String {
+        format!("{}{:X}", self.guid.replace(&['{', '}', '-'][..], ""), self.age)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct CodeViewNb10 {
+    pub offset: u32,
+    pub time_date_stamp: u32,
+    pub age: u32,
+    pub path: String,
+}
+
+impl CodeViewNb10 {
+    /// Key symbol servers store the PDB under, signature followed by the age in hexadecimal
+    pub fn get_symbol_key(&self) -> String {
+        format!("{:08X}{:X}", self.time_date_stamp, self.age)
+    }
+}
+
+/// Contribution of a section or COFF group, as recorded by profile guided optimization
+#[derive(Debug, PartialEq, Clone, Default, Deserialize)]
+pub struct PogoEntry {
+    pub rva: u32,
+    pub size: u32,
+    pub name: String,
+}
+
+impl Serialize for PogoEntry {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("PogoEntry", 3)?;
+        state.serialize_field("rva", &format!("0x{:x}", &self.rva))?;
+        state.serialize_field("size", &self.size)?;
+        state.serialize_field("name", &self.name)?;
+        state.end()
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct Pogo {
+    /// `LTCG`, `PGI`, `PGO` or `PGU` depending on the build stage, empty when the linker left it blank
+    pub signature: String,
+    pub entries: Vec<PogoEntry>,
+}
+
+impl Pogo {
+    pub fn parse(bytes: &[u8]) -> error::Result<Self> {
+        let mut offset:usize = 0;
+        let signature:u32 = bytes.gread_with(&mut offset, scroll::LE)?;
+        let mut entries:Vec<PogoEntry> = Vec::new();
+        while offset + 8 < bytes.len() {
+            let rva:u32 = bytes.gread_with(&mut offset, scroll::LE)?;
+            let size:u32 = bytes.gread_with(&mut offset, scroll::LE)?;
+            let name:&[u8] = bytes.get(offset..).unwrap_or(&[]);
+            let length:usize = name.iter().position(|&c| c == 0).unwrap_or(name.len());
+            let name:String = String::from_utf8_lossy(&name[..length]).into_owned();
+            // Names are NUL terminated and padded to a 4 byte boundary, the decoded name may be longer
+            offset = (offset + length + 1 + 3) & !3;
+            entries.push(PogoEntry { rva, size, name });
+        }
+        Ok(Pogo {
+            signature: String::from_utf8_lossy(&signature.to_be_bytes()).trim_end_matches(char::from(0)).to_string(),
+            entries
+        })
+    }
+}
+
+/// Number of object files built with each security relevant compiler feature
+#[derive(Debug, PartialEq, Copy, Clone, Default, Pread, Pwrite, IOread, IOwrite, SizeWith, Serialize, Deserialize)]
+pub struct VcFeature {
+    pub pre_vc11: u32,
+    pub c_cpp: u32,
+    pub gs: u32,
+    pub sdl: u32,
+    pub guard_n: u32,
+}
+
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct Repro {
+    /// Hex encoded build hash, `None` when the entry is empty and the time stamps hold the hash
+    pub hash: Option<String>,
+}
+
+impl Repro {
+    pub fn parse(bytes: &[u8]) -> error::Result<Self> {
+        if bytes.is_empty() {
+            return Ok(Repro { hash: None });
+        }
+        let length:u32 = bytes.pread_with(0, scroll::LE)?;
+        let hash:&[u8] = bytes.pread_with(4, length as usize)?;
+        Ok(Repro { hash: Some(hash.iter().map(|b| format!("{:02x}", b)).collect()) })
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct ExDllCharacteristics {
+    pub characteristics: u32,
+    pub names: Vec<String>,
+}
+
+impl ExDllCharacteristics {
+    pub fn new(characteristics: u32) -> Self {
+        let mut names:Vec<String> = index::EXDLLCHARACTERISTIC.iter()
+            .filter(|(flag, _)| characteristics & **flag != 0)
+            .map(|(_, name)| name.to_string())
+            .collect();
+        names.sort();
+        ExDllCharacteristics { characteristics, names }
+    }
+    pub fn is_cet_compatible(&self) -> bool {
+        self.characteristics & IMAGE_DLLCHARACTERISTICS_EX_CET_COMPAT != 0
+    }
+    pub fn is_forward_cfi_compatible(&self) -> bool {
+        self.characteristics & IMAGE_DLLCHARACTERISTICS_EX_FORWARD_CFI_COMPAT != 0
+    }
+}
+
+/// Decoded content of a debug directory entry
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub enum DebugInfo {
+    CodeViewRsds(CodeViewRsds),
+    CodeViewNb10(CodeViewNb10),
+    Pogo(Pogo),
+    VcFeature(VcFeature),
+    Repro(Repro),
+    ExDllCharacteristics(ExDllCharacteristics),
+}
+
+impl DebugInfo {
+    pub fn parse(bytes: &[u8], debug_type: u32) -> error::Result<Option<Self>> {
+        if bytes.is_empty() && debug_type != IMAGE_DEBUG_TYPE_REPRO {
+            return Ok(None);
+        }
+        match debug_type {
+            IMAGE_DEBUG_TYPE_CODEVIEW => {
+                let mut offset:usize = 0;
+                let signature:u32 = bytes.gread_with(&mut offset, scroll::LE)?;
+                match signature {
+                    CODEVIEW_RSDS_SIGNATURE => {
+                        let guid:[u8; 16] = bytes.gread_with::<&[u8]>(&mut offset, 16)?.try_into().unwrap_or_default();
+                        let age:u32 = bytes.gread_with(&mut offset, scroll::LE)?;
+                        Ok(Some(DebugInfo::CodeViewRsds(CodeViewRsds { guid: format_guid(&guid), age, path: read_path(bytes, offset) })))
+                    }
+                    CODEVIEW_NB10_SIGNATURE => {
+                        let cv_offset:u32 = bytes.gread_with(&mut offset, scroll::LE)?;
+                        let time_date_stamp:u32 = bytes.gread_with(&mut offset, scroll::LE)?;
+                        let age:u32 = bytes.gread_with(&mut offset, scroll::LE)?;
+                        Ok(Some(DebugInfo::CodeViewNb10(CodeViewNb10 { offset: cv_offset, time_date_stamp, age, path: read_path(bytes, offset) })))
+                    }
+                    _ => Ok(None)
+                }
+            }
+            IMAGE_DEBUG_TYPE_POGO => Ok(Some(DebugInfo::Pogo(Pogo::parse(bytes)?))),
+            IMAGE_DEBUG_TYPE_VC_FEATURE => Ok(Some(DebugInfo::VcFeature(bytes.pread_with(0, scroll::LE)?))),
+            IMAGE_DEBUG_TYPE_REPRO => Ok(Some(DebugInfo::Repro(Repro::parse(bytes)?))),
+            IMAGE_DEBUG_TYPE_EX_DLLCHARACTERISTICS => Ok(Some(DebugInfo::ExDllCharacteristics(ExDllCharacteristics::new(bytes.pread_with(0, scroll::LE)?)))),
+            _ => Ok(None)
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct DebugEntry {
+    pub directory: ImageDebugDirectory,
+    pub info: Option<DebugInfo>,
+}
+
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct DebugDirectory {
+    pub entries: Vec<DebugEntry>,
+}
+
+impl DebugDirectory {
+    pub fn parse(bytes: &[u8], headers: &Headers, data_directory: DataDirectory) -> error::Result<Self> {
+        let mut offset:usize = headers.rva_to_offset(data_directory.virtual_address).ok_or(error::Error::BadRva(data_directory.virtual_address))?;
+        let number_of_entries:usize = data_directory.size as usize / ImageDebugDirectory::size_with(&scroll::LE);
+        let mut entries:Vec<DebugEntry> = Vec::new();
+        for _ in 0..number_of_entries {
+            let directory:ImageDebugDirectory = ImageDebugDirectory::parse(bytes, &mut offset)?;
+            // Stripped or truncated files keep entries whose data is gone or cut short
+            let data:Option<&[u8]> = if directory.size_of_data > 0 { directory.get_data(bytes, headers).ok() } else { Some(&[]) };
+            let info:Option<DebugInfo> = data.and_then(|data| DebugInfo::parse(data, directory.debug_type).ok().flatten());
+            entries.push(DebugEntry { directory, info });
+        }
+        Ok(DebugDirectory { entries })
+    }
+    /// PDB 7.0 information, the one symbol servers and debuggers match on
+    pub fn get_codeview(&self) -> Option<&CodeViewRsds> {
+        self.entries.iter().find_map(|entry| match entry.info {
+            Some(DebugInfo::CodeViewRsds(ref codeview)) => Some(codeview),
+            _ => None
+        })
+    }
+    pub fn get_vc_feature(&self) -> Option<&VcFeature> {
+        self.entries.iter().find_map(|entry| match entry.info {
+            Some(DebugInfo::VcFeature(ref vc_feature)) => Some(vc_feature),
+            _ => None
+        })
+    }
+    pub fn get_ex_dll_characteristics(&self) -> Option<&ExDllCharacteristics> {
+        self.entries.iter().find_map(|entry| match entry.info {
+            Some(DebugInfo::ExDllCharacteristics(ref characteristics)) => Some(characteristics),
+            _ => None
+        })
+    }
+    /// True when the image was linked with `/Brepro`
+    pub fn is_reproducible(&self) -> bool {
+        self.entries.iter().any(|entry| entry.directory.debug_type == IMAGE_DEBUG_TYPE_REPRO)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use scroll::Pwrite;
+
+    use super::{DebugDirectory, DebugInfo, ImageDebugDirectory, Repro, VcFeature, CODEVIEW_NB10_SIGNATURE, CODEVIEW_RSDS_SIGNATURE, IMAGE_DEBUG_TYPE_CODEVIEW, IMAGE_DEBUG_TYPE_EX_DLLCHARACTERISTICS, IMAGE_DEBUG_TYPE_POGO, IMAGE_DEBUG_TYPE_REPRO, IMAGE_DEBUG_TYPE_VC_FEATURE};
+    use crate::pe::header::{DataDirectory, test_headers};
+
+    #[test]
+    fn debug_directory() {
+        let headers = test_headers(0x1000, 0x200);
+        let mut bytes = vec![0u8; 0x200];
+        bytes.pwrite_with(ImageDebugDirectory { debug_type: IMAGE_DEBUG_TYPE_CODEVIEW, size_of_data: 0x20, address_of_raw_data: 0x1080, ..Default::default() }, 0, scroll::LE).unwrap();
+        bytes.pwrite_with(ImageDebugDirectory { debug_type: IMAGE_DEBUG_TYPE_CODEVIEW, size_of_data: 0x20, pointer_to_raw_data: 0x100, ..Default::default() }, 28, scroll::LE).unwrap();
+        bytes.pwrite_with(CODEVIEW_RSDS_SIGNATURE, 0x80, scroll::LE).unwrap();
+        bytes[0x84..0x94].copy_from_slice(&[0x78, 0x56, 0x34, 0x12, 0xbc, 0x9a, 0xf0, 0xde, 0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef]);
+        bytes.pwrite_with(3u32, 0x94, scroll::LE).unwrap();
+        bytes[0x98..0x9f].copy_from_slice(b"app.pdb");
+        bytes.pwrite_with(CODEVIEW_NB10_SIGNATURE, 0x100, scroll::LE).unwrap();
+        bytes.pwrite_with(0x4a3b2c1du32, 0x108, scroll::LE).unwrap();
+        bytes.pwrite_with(2u32, 0x10c, scroll::LE).unwrap();
+        bytes[0x110..0x117].copy_from_slice(b"old.pdb");
+
+        let debug_directory = DebugDirectory::parse(&bytes, &headers, DataDirectory { virtual_address: 0x1000, size: 56 }).unwrap();
+        let codeview = debug_directory.get_codeview().unwrap();
+        assert_eq!(codeview.guid, "{12345678-9ABC-DEF0-0123-456789ABCDEF}");
+        assert_eq!(codeview.path, "app.pdb");
+        assert_eq!(codeview.get_symbol_key(), "123456789ABCDEF00123456789ABCDEF3");
+        match debug_directory.entries[1].info {
+            Some(DebugInfo::CodeViewNb10(ref nb10)) => {
+                assert_eq!(nb10.age, 2);
+                assert_eq!(nb10.path, "old.pdb");
+                assert_eq!(nb10.get_symbol_key(), "4A3B2C1D2");
+            }
+            ref other => panic!("unexpected debug info {:?}", other)
+        }
+    }
+
+    #[test]
+    fn debug_data_missing_or_truncated() {
+        let mut bytes = vec![0u8; 0x200];
+        bytes.pwrite_with(ImageDebugDirectory { debug_type: IMAGE_DEBUG_TYPE_CODEVIEW, size_of_data: 0x40, pointer_to_raw_data: 0x1e0, ..Default::default() }, 0, scroll::LE).unwrap();
+        bytes.pwrite_with(ImageDebugDirectory { debug_type: IMAGE_DEBUG_TYPE_REPRO, ..Default::default() }, 28, scroll::LE).unwrap();
+        // RSDS record cut after its signature
+        bytes.pwrite_with(ImageDebugDirectory { debug_type: IMAGE_DEBUG_TYPE_CODEVIEW, size_of_data: 8, pointer_to_raw_data: 0x100, ..Default::default() }, 56, scroll::LE).unwrap();
+        bytes.pwrite_with(CODEVIEW_RSDS_SIGNATURE, 0x100, scroll::LE).unwrap();
+        bytes.pwrite_with(ImageDebugDirectory { debug_type: IMAGE_DEBUG_TYPE_VC_FEATURE, size_of_data: 8, pointer_to_raw_data: 0x100, ..Default::default() }, 84, scroll::LE).unwrap();
+        let debug_directory = DebugDirectory::parse(&bytes, &test_headers(0x1000, 0x200), DataDirectory { virtual_address: 0x1000, size: 112 }).unwrap();
+        assert_eq!(debug_directory.entries.len(), 4);
+        assert_eq!(debug_directory.entries[0].info, None);
+        assert!(debug_directory.is_reproducible());
+        assert_eq!(debug_directory.entries[2].directory.debug_type, IMAGE_DEBUG_TYPE_CODEVIEW);
+        assert_eq!(debug_directory.entries[2].info, None);
+        assert_eq!(debug_directory.get_vc_feature(), None);
+    }
+
+    #[test]
+    fn secondary_debug_info() {
+        let mut pogo = 0x4c544347u32.to_le_bytes().to_vec();
+        pogo.extend(&0x1000u32.to_le_bytes());
+        pogo.extend(&0x20u32.to_le_bytes());
+        pogo.extend(b".text$mn\0\0\0\0");
+        pogo.extend(&0x2000u32.to_le_bytes());
+        pogo.extend(&0x8u32.to_le_bytes());
+        pogo.extend(b".rdata\0\0");
+        // invalid UTF-8 is decoded to wider replacement characters
+        pogo.extend(&0x3000u32.to_le_bytes());
+        pogo.extend(&0x4u32.to_le_bytes());
+        pogo.extend(b".\xff\xfe\0");
+        pogo.extend(&0x4000u32.to_le_bytes());
+        pogo.extend(&0x4u32.to_le_bytes());
+        pogo.extend(b".bss\0\0\0\0");
+        match DebugInfo::parse(&pogo, IMAGE_DEBUG_TYPE_POGO).unwrap() {
+            Some(DebugInfo::Pogo(pogo)) => {
+                assert_eq!(pogo.signature, "LTCG");
+                assert_eq!(pogo.entries.len(), 4);
+                assert_eq!(pogo.entries[0].name, ".text$mn");
+                assert_eq!(pogo.entries[1].rva, 0x2000);
+                assert_eq!(pogo.entries[1].name, ".rdata");
+                assert_eq!(pogo.entries[2].name, ".\u{fffd}\u{fffd}");
+                assert_eq!(pogo.entries[3].rva, 0x4000);
+                assert_eq!(pogo.entries[3].name, ".bss");
+            }
+            other => panic!("unexpected debug info {:?}", other)
+        }
+
+        let vc_feature: Vec<u8> = [0u32, 12, 12, 3, 1].iter().flat_map(|v| v.to_le_bytes().to_vec()).collect();
+        assert_eq!(DebugInfo::parse(&vc_feature, IMAGE_DEBUG_TYPE_VC_FEATURE).unwrap(), Some(DebugInfo::VcFeature(VcFeature { pre_vc11: 0, c_cpp: 12, gs: 12, sdl: 3, guard_n: 1 })));
+
+        let repro = [4u8, 0, 0, 0, 0xde, 0xad, 0xbe, 0xef];
+        assert_eq!(DebugInfo::parse(&repro, IMAGE_DEBUG_TYPE_REPRO).unwrap(), Some(DebugInfo::Repro(Repro { hash: Some("deadbeef".to_string()) })));
+        assert_eq!(DebugInfo::parse(&[], IMAGE_DEBUG_TYPE_REPRO).unwrap(), Some(DebugInfo::Repro(Repro { hash: None })));
+
+        match DebugInfo::parse(&0x41u32.to_le_bytes(), IMAGE_DEBUG_TYPE_EX_DLLCHARACTERISTICS).unwrap() {
+            Some(DebugInfo::ExDllCharacteristics(characteristics)) => {
+                assert!(characteristics.is_cet_compatible());
+                assert!(characteristics.is_forward_cfi_compatible());
+                assert_eq!(characteristics.names.len(), 2);
+            }
+            other => panic!("unexpected debug info {:?}", other)
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use scroll::{IOread, IOwrite, Pread, Pwrite, SizeWith};
+use serde::{Deserialize, Serialize, Serializer};
+use serde::ser::SerializeStruct;
+
+use crate::error;
+use crate::pe::header::{DataDirectory, Headers};
+use crate::pe::section::ImportFunction;
+
+/// Set in `attributes` when the descriptor fields are RVAs, legacy descriptors hold virtual addresses
+pub const DELAY_IMPORT_ATTRIBUTE_RVA: u32 = 0x1;
+
+#[derive(Debug, PartialEq, Copy, Clone, Default, Pread, Pwrite, IOread, IOwrite, SizeWith, Deserialize)]
+pub struct DelayImportDescriptor {
+    pub attributes: u32,
+    pub name: u32,
+    pub module_handle: u32,
+    pub delay_import_address_table: u32,
+    pub delay_import_name_table: u32,
+    pub bound_delay_import_table: u32,
+    pub unload_delay_import_table: u32,
+    pub time_date_stamp: u32,
+}
+
+impl Serialize for DelayImportDescriptor {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("DelayImportDescriptor", 8)?;
+        state.serialize_field("attributes", &format!("0x{:x}", &self.attributes))?;
+        state.serialize_field("name", &format!("0x{:x}", &self.name))?;
+        state.serialize_field("module_handle", &format!("0x{:x}", &self.module_handle))?;
+        state.serialize_field("delay_import_address_table", &format!("0x{:x}", &self.delay_import_address_table))?;
+        state.serialize_field("delay_import_name_table", &format!("0x{:x}", &self.delay_import_name_table))?;
+        state.serialize_field("bound_delay_import_table", &format!("0x{:x}", &self.bound_delay_import_table))?;
+        state.serialize_field("unload_delay_import_table", &format!("0x{:x}", &self.unload_delay_import_table))?;
+        state.serialize_field("time_date_stamp", &format!("0x{:x}", &self.time_date_stamp))?;
+        state.end()
+    }
+}
+
+impl DelayImportDescriptor {
+    pub fn parse(bytes: &[u8], offset: &mut usize) -> error::Result<Self> {
+        Ok(bytes.gread_with(offset, scroll::LE)?)
+    }
+    pub fn is_rva_based(&self) -> bool {
+        self.attributes & DELAY_IMPORT_ATTRIBUTE_RVA != 0
+    }
+    /// Converts a descriptor field into an RVA, legacy descriptors are relative to the image base
+    pub fn to_rva(&self, address: u32, image_base: u64) -> u32 {
+        if self.is_rva_based() || address == 0 {
+            return address
+        }
+        (u64::from(address).wrapping_sub(image_base)) as u32
+    }
+    /// Returns a copy of the descriptor where every address is an RVA
+    pub fn normalize(&self, image_base: u64) -> Self {
+        DelayImportDescriptor {
+            attributes: self.attributes,
+            name: self.to_rva(self.name, image_base),
+            module_handle: self.to_rva(self.module_handle, image_base),
+            delay_import_address_table: self.to_rva(self.delay_import_address_table, image_base),
+            delay_import_name_table: self.to_rva(self.delay_import_name_table, image_base),
+            bound_delay_import_table: self.to_rva(self.bound_delay_import_table, image_base),
+            unload_delay_import_table: self.to_rva(self.unload_delay_import_table, image_base),
+            time_date_stamp: self.time_date_stamp,
+        }
+    }
+}
+
+/// Delay-load descriptor resolved to its DLL name and imported symbols
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct DelayImport {
+    /// Descriptor with all its addresses converted to RVAs
+    pub descriptor: DelayImportDescriptor,
+    pub name: String,
+    pub functions: Vec<ImportFunction>,
+}
+
+impl DelayImport {
+    pub fn parse(bytes: &[u8], headers: &Headers, descriptor: &DelayImportDescriptor) -> error::Result<Self> {
+        let image_base:u64 = headers.optional.specific_fields.image_base;
+        let normalized:DelayImportDescriptor = descriptor.normalize(image_base);
+        let thunk_base:u64 = if descriptor.is_rva_based() { 0 } else { image_base };
+        // The delay IAT points to the loader helper thunks, only the name table describes the imports
+        let functions:Vec<ImportFunction> = match normalized.delay_import_name_table {
+            0 => Vec::new(),
+            name_table => ImportFunction::parse_thunks_with_base(bytes, headers, name_table, normalized.delay_import_address_table, thunk_base)?
+        };
+        Ok(DelayImport {
+            name: headers.get_string(bytes, normalized.name)?,
+            functions,
+            descriptor: normalized
+        })
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct DelayImportDirectory {
+    pub imports: Vec<DelayImport>
+}
+
+impl DelayImportDirectory {
+    pub fn parse(bytes: &[u8], headers: &Headers, data_directory: DataDirectory) -> error::Result<Self> {
+        let mut offset:usize = headers.rva_to_offset(data_directory.virtual_address).ok_or(error::Error::BadRva(data_directory.virtual_address))?;
+        let mut imports:Vec<DelayImport> = Vec::new();
+        loop {
+            let descriptor:DelayImportDescriptor = DelayImportDescriptor::parse(bytes, &mut offset)?;
+            if descriptor.name == 0 {
+                break;
+            }
+            imports.push(DelayImport::parse(bytes, headers, &descriptor)?);
+        }
+        Ok(DelayImportDirectory { imports })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use scroll::Pwrite;
+
+    use super::{DelayImportDescriptor, DelayImportDirectory, DELAY_IMPORT_ATTRIBUTE_RVA};
+    use crate::pe::header::{DataDirectory, Headers, OPTIONAL_HEADER_SIGNATURE_32, test_headers};
+
+    fn headers() -> Headers {
+        let mut headers = test_headers(0x1000, 0x200);
+        headers.optional.standard_fields.signature = OPTIONAL_HEADER_SIGNATURE_32;
+        headers.optional.specific_fields.image_base = 0x400000;
+        headers
+    }
+
+    fn image(attributes: u32, base: u32) -> Vec<u8> {
+        let mut bytes = vec![0u8; 0x200];
+        let descriptor = DelayImportDescriptor {
+            attributes,
+            name: base + 0x1100,
+            module_handle: base + 0x1080,
+            delay_import_address_table: base + 0x1040,
+            delay_import_name_table: base + 0x1060,
+            ..Default::default()
+        };
+        bytes.pwrite_with(descriptor, 0, scroll::LE).unwrap();
+        bytes.pwrite_with(base + 0x1110, 0x60, scroll::LE).unwrap();
+        bytes.pwrite_with(0x8000_0007u32, 0x64, scroll::LE).unwrap();
+        bytes[0x100..0x10a].copy_from_slice(b"USER32.dll");
+        bytes.pwrite_with(0x1234u16, 0x110, scroll::LE).unwrap();
+        bytes[0x112..0x11d].copy_from_slice(b"MessageBoxW");
+        bytes
+    }
+
+    #[test]
+    fn delay_imports() {
+        let headers = headers();
+        let data_directory = DataDirectory { virtual_address: 0x1000, size: 0x40 };
+        for (attributes, base) in [(DELAY_IMPORT_ATTRIBUTE_RVA, 0), (0, 0x400000)].iter() {
+            let bytes = image(*attributes, *base);
+            let directory = DelayImportDirectory::parse(&bytes, &headers, data_directory).unwrap();
+            assert_eq!(directory.imports.len(), 1);
+            let import = &directory.imports[0];
+            assert_eq!(import.name, "USER32.dll");
+            assert_eq!(import.descriptor.module_handle, 0x1080);
+            assert_eq!(import.functions.len(), 2);
+            assert_eq!(import.functions[0].name.as_deref(), Some("MessageBoxW"));
+            assert_eq!(import.functions[0].hint, 0x1234);
+            assert_eq!(import.functions[0].iat_rva, 0x1040);
+            assert_eq!(import.functions[1].ordinal, Some(7));
+            assert_eq!(import.functions[1].iat_rva, 0x1044);
+        }
+    }
+
+    #[test]
+    fn delay_imports_without_name_table() {
+        let mut bytes = image(DELAY_IMPORT_ATTRIBUTE_RVA, 0);
+        bytes.pwrite_with(0u32, 16, scroll::LE).unwrap();
+        // the IAT holds a helper thunk VA, not a hint/name RVA
+        bytes.pwrite_with(0x401234u32, 0x40, scroll::LE).unwrap();
+        let data_directory = DataDirectory { virtual_address: 0x1000, size: 0x40 };
+        let directory = DelayImportDirectory::parse(&bytes, &headers(), data_directory).unwrap();
+        assert_eq!(directory.imports[0].name, "USER32.dll");
+        assert!(directory.imports[0].functions.is_empty());
+    }
+}
@@ -0,0 +1,320 @@
+use scroll::{IOread, IOwrite, Pread, Pwrite, SizeWith};
+use serde::{Deserialize, Serialize};
+
+use crate::error;
+use crate::pe::header::Headers;
+use crate::pe::index;
+use crate::pe::resource::{ResourceDirectory, ResourceId, RT_DIALOG};
+use crate::pe::version::{align_4, read_utf16};
+
+/// Dialog style bit announcing the font fields of the template
+pub const DS_SETFONT: u32 = 0x40;
+/// Signature of DLGTEMPLATEEX, stored right after its version word
+pub const DIALOG_EX_SIGNATURE: u16 = 0xffff;
+
+#[derive(Debug, PartialEq, Copy, Clone, Default, Pread, Pwrite, IOread, IOwrite, SizeWith, Deserialize, Serialize)]
+pub struct DialogTemplate {
+    pub style: u32,
+    pub extended_style: u32,
+    pub number_of_items: u16,
+    pub x: i16,
+    pub y: i16,
+    pub cx: i16,
+    pub cy: i16,
+}
+
+#[derive(Debug, PartialEq, Copy, Clone, Default, Pread, Pwrite, IOread, IOwrite, SizeWith, Deserialize, Serialize)]
+pub struct DialogTemplateEx {
+    pub version: u16,
+    pub signature: u16,
+    pub help_id: u32,
+    pub extended_style: u32,
+    pub style: u32,
+    pub number_of_items: u16,
+    pub x: i16,
+    pub y: i16,
+    pub cx: i16,
+    pub cy: i16,
+}
+
+#[derive(Debug, PartialEq, Copy, Clone, Default, Pread, Pwrite, IOread, IOwrite, SizeWith, Deserialize, Serialize)]
+pub struct DialogItemTemplate {
+    pub style: u32,
+    pub extended_style: u32,
+    pub x: i16,
+    pub y: i16,
+    pub cx: i16,
+    pub cy: i16,
+    pub id: u16,
+}
+
+#[derive(Debug, PartialEq, Copy, Clone, Default, Pread, Pwrite, IOread, IOwrite, SizeWith, Deserialize, Serialize)]
+pub struct DialogItemTemplateEx {
+    pub help_id: u32,
+    pub extended_style: u32,
+    pub style: u32,
+    pub x: i16,
+    pub y: i16,
+    pub cx: i16,
+    pub cy: i16,
+    pub id: u32,
+}
+
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct DialogFont {
+    pub point_size: u16,
+    /// Only present in extended templates
+    pub weight: Option<u16>,
+    pub italic: Option<bool>,
+    pub charset: Option<u8>,
+    pub typeface: String,
+}
+
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct DialogControl {
+    pub help_id: Option<u32>,
+    pub style: u32,
+    pub extended_style: u32,
+    pub x: i16,
+    pub y: i16,
+    pub cx: i16,
+    pub cy: i16,
+    pub id: u32,
+    /// Window class, either a predefined atom or a registered class name
+    pub class: Option<ResourceId>,
+    /// Name of the predefined class when `class` is an atom
+    pub class_name: Option<String>,
+    /// Caption text or ordinal of an icon/bitmap resource
+    pub title: Option<ResourceId>,
+    pub creation_data: Vec<u8>,
+}
+
+/// RT_DIALOG resource, decoded from either DLGTEMPLATE or DLGTEMPLATEEX
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct Dialog {
+    pub name: ResourceId,
+    pub language: ResourceId,
+    pub extended: bool,
+    pub help_id: Option<u32>,
+    pub style: u32,
+    pub extended_style: u32,
+    pub x: i16,
+    pub y: i16,
+    pub cx: i16,
+    pub cy: i16,
+    pub menu: Option<ResourceId>,
+    pub class: Option<ResourceId>,
+    pub title: String,
+    pub font: Option<DialogFont>,
+    pub controls: Vec<DialogControl>,
+}
+
+/// Reads a `sz_Or_Ord` field: nothing, `0xffff` followed by an ordinal, or a UTF-16 string
+pub(crate) fn read_name_or_ordinal(bytes: &[u8], offset: &mut usize) -> error::Result<Option<ResourceId>> {
+    let first:u16 = bytes.pread_with(*offset, scroll::LE)?;
+    match first {
+        0 => {
+            *offset += 2;
+            Ok(None)
+        }
+        0xffff => {
+            *offset += 2;
+            Ok(Some(ResourceId::Id(bytes.gread_with(offset, scroll::LE)?)))
+        }
+        _ => Ok(Some(ResourceId::Name(read_utf16(bytes, offset, bytes.len())?)))
+    }
+}
+
+impl DialogControl {
+    fn parse(bytes: &[u8], offset: &mut usize, extended: bool) -> error::Result<Self> {
+        *offset = align_4(*offset);
+        let mut control:DialogControl = if extended {
+            let item:DialogItemTemplateEx = bytes.gread_with(offset, scroll::LE)?;
+            DialogControl {
+                help_id: Some(item.help_id),
+                style: item.style,
+                extended_style: item.extended_style,
+                x: item.x,
+                y: item.y,
+                cx: item.cx,
+                cy: item.cy,
+                id: item.id,
+                ..Default::default()
+            }
+        } else {
+            let item:DialogItemTemplate = bytes.gread_with(offset, scroll::LE)?;
+            DialogControl {
+                style: item.style,
+                extended_style: item.extended_style,
+                x: item.x,
+                y: item.y,
+                cx: item.cx,
+                cy: item.cy,
+                id: u32::from(item.id),
+                ..Default::default()
+            }
+        };
+        control.class = read_name_or_ordinal(bytes, offset)?;
+        control.class_name = match control.class {
+            Some(ResourceId::Id(atom)) => index::CONTROLCLASS.get(&atom).map(|name| name.to_string()),
+            _ => None
+        };
+        control.title = read_name_or_ordinal(bytes, offset)?;
+        let creation_data_size:u16 = bytes.gread_with(offset, scroll::LE)?;
+        if creation_data_size > 0 {
+            let creation_data:&[u8] = bytes.gread_with(offset, creation_data_size as usize)?;
+            control.creation_data = creation_data.to_vec();
+        }
+        Ok(control)
+    }
+}
+
+impl Dialog {
+    pub fn parse(bytes: &[u8]) -> error::Result<Self> {
+        let mut offset:usize = 0;
+        let signature:u16 = bytes.pread_with(2, scroll::LE)?;
+        let extended:bool = signature == DIALOG_EX_SIGNATURE;
+        let (mut dialog, number_of_items):(Dialog, u16) = if extended {
+            let template:DialogTemplateEx = bytes.gread_with(&mut offset, scroll::LE)?;
+            (Dialog {
+                extended,
+                help_id: Some(template.help_id),
+                style: template.style,
+                extended_style: template.extended_style,
+                x: template.x,
+                y: template.y,
+                cx: template.cx,
+                cy: template.cy,
+                ..Default::default()
+            }, template.number_of_items)
+        } else {
+            let template:DialogTemplate = bytes.gread_with(&mut offset, scroll::LE)?;
+            (Dialog {
+                style: template.style,
+                extended_style: template.extended_style,
+                x: template.x,
+                y: template.y,
+                cx: template.cx,
+                cy: template.cy,
+                ..Default::default()
+            }, template.number_of_items)
+        };
+        dialog.menu = read_name_or_ordinal(bytes, &mut offset)?;
+        dialog.class = read_name_or_ordinal(bytes, &mut offset)?;
+        dialog.title = read_utf16(bytes, &mut offset, bytes.len())?;
+        if dialog.style & DS_SETFONT != 0 {
+            let point_size:u16 = bytes.gread_with(&mut offset, scroll::LE)?;
+            dialog.font = Some(if extended {
+                let weight:u16 = bytes.gread_with(&mut offset, scroll::LE)?;
+                let italic:u8 = bytes.gread_with(&mut offset, scroll::LE)?;
+                let charset:u8 = bytes.gread_with(&mut offset, scroll::LE)?;
+                DialogFont {
+                    point_size,
+                    weight: Some(weight),
+                    italic: Some(italic != 0),
+                    charset: Some(charset),
+                    typeface: read_utf16(bytes, &mut offset, bytes.len())?
+                }
+            } else {
+                DialogFont {
+                    point_size,
+                    typeface: read_utf16(bytes, &mut offset, bytes.len())?,
+                    ..Default::default()
+                }
+            });
+        }
+        for _ in 0..number_of_items {
+            dialog.controls.push(DialogControl::parse(bytes, &mut offset, extended)?);
+        }
+        Ok(dialog)
+    }
+    /// Decodes every RT_DIALOG resource of the tree
+    pub fn from_resources(bytes: &[u8], headers: &Headers, resource_directory: &ResourceDirectory) -> error::Result<Vec<Self>> {
+        let mut dialogs:Vec<Dialog> = Vec::new();
+        for resource in resource_directory.get_resources(RT_DIALOG) {
+            dialogs.push(Dialog {
+                name: resource.name.clone(),
+                language: resource.language.clone(),
+                ..Dialog::parse(resource.get_data(bytes, headers)?)?
+            });
+        }
+        Ok(dialogs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use scroll::Pwrite;
+
+    use super::{Dialog, DialogItemTemplate, DialogItemTemplateEx, DialogTemplate, DialogTemplateEx, DIALOG_EX_SIGNATURE, DS_SETFONT};
+    use crate::pe::resource::ResourceId;
+
+    fn utf16(text: &str) -> Vec<u8> {
+        text.encode_utf16().chain(Some(0)).flat_map(|c| c.to_le_bytes().to_vec()).collect()
+    }
+
+    fn align(bytes: &mut Vec<u8>) {
+        bytes.resize((bytes.len() + 3) & !3, 0);
+    }
+
+    #[test]
+    fn dialog() {
+        let mut bytes = vec![0u8; 18];
+        bytes.pwrite_with(DialogTemplate { style: DS_SETFONT, number_of_items: 2, cx: 200, cy: 100, ..Default::default() }, 0, scroll::LE).unwrap();
+        bytes.extend(&[0, 0, 0, 0]);
+        bytes.extend(utf16("About"));
+        bytes.extend(&8u16.to_le_bytes());
+        bytes.extend(utf16("MS Shell Dlg"));
+        for (id, title) in [(1u16, "OK"), (2, "Cancel")].iter() {
+            align(&mut bytes);
+            let mut item = vec![0u8; 18];
+            item.pwrite_with(DialogItemTemplate { id: *id, cx: 50, cy: 14, ..Default::default() }, 0, scroll::LE).unwrap();
+            bytes.extend(item);
+            bytes.extend(&[0xff, 0xff, 0x80, 0x00]);
+            bytes.extend(utf16(title));
+            bytes.extend(&[0, 0]);
+        }
+        let dialog = Dialog::parse(&bytes).unwrap();
+        assert!(!dialog.extended);
+        assert_eq!(dialog.title, "About");
+        assert_eq!(dialog.font.unwrap().typeface, "MS Shell Dlg");
+        assert_eq!(dialog.controls.len(), 2);
+        assert_eq!(dialog.controls[1].id, 2);
+        assert_eq!(dialog.controls[1].class_name.as_deref(), Some("Button"));
+        assert_eq!(dialog.controls[1].title, Some(ResourceId::Name("Cancel".to_string())));
+    }
+
+    #[test]
+    fn dialog_ex() {
+        let mut bytes = vec![0u8; 26];
+        bytes.pwrite_with(DialogTemplateEx { version: 1, signature: DIALOG_EX_SIGNATURE, style: DS_SETFONT, number_of_items: 1, ..Default::default() }, 0, scroll::LE).unwrap();
+        bytes.extend(&[0xff, 0xff, 0x65, 0x00]);
+        bytes.extend(utf16("MyClass"));
+        bytes.extend(utf16("Settings"));
+        bytes.extend(&[9, 0, 0xbc, 0x02, 1, 0]);
+        bytes.extend(utf16("Segoe UI"));
+        align(&mut bytes);
+        let mut item = vec![0u8; 24];
+        item.pwrite_with(DialogItemTemplateEx { help_id: 7, id: 1001, ..Default::default() }, 0, scroll::LE).unwrap();
+        bytes.extend(item);
+        bytes.extend(utf16("SysListView32"));
+        bytes.extend(&[0xff, 0xff, 0x03, 0x00]);
+        bytes.extend(&[2, 0, 0xaa, 0xbb]);
+        let dialog = Dialog::parse(&bytes).unwrap();
+        assert!(dialog.extended);
+        assert_eq!(dialog.menu, Some(ResourceId::Id(0x65)));
+        assert_eq!(dialog.class, Some(ResourceId::Name("MyClass".to_string())));
+        assert_eq!(dialog.title, "Settings");
+        let font = dialog.font.unwrap();
+        assert_eq!(font.weight, Some(700));
+        assert_eq!(font.italic, Some(true));
+        assert_eq!(font.typeface, "Segoe UI");
+        let control = &dialog.controls[0];
+        assert_eq!(control.help_id, Some(7));
+        assert_eq!(control.id, 1001);
+        assert_eq!(control.class, Some(ResourceId::Name("SysListView32".to_string())));
+        assert_eq!(control.class_name, None);
+        assert_eq!(control.title, Some(ResourceId::Id(3)));
+        assert_eq!(control.creation_data, vec![0xaa, 0xbb]);
+    }
+}
@@ -0,0 +1,488 @@
+use scroll::{IOread, IOwrite, Pread, Pwrite, SizeWith};
+use scroll::ctx::SizeWith as _;
+use serde::{Deserialize, Serialize, Serializer};
+use serde::ser::SerializeStruct;
+
+use crate::error;
+use crate::pe::header::{Headers, OPTIONAL_HEADER_SIGNATURE_64};
+use crate::pe::load_config::LoadConfigDirectory;
+use crate::pe::relocation::{BaseRelocation, BaseRelocationBlock, IMAGE_REL_BASED_ABSOLUTE};
+
+pub const IMAGE_DYNAMIC_RELOCATION_GUARD_RF_PROLOGUE: u64 = 1;
+pub const IMAGE_DYNAMIC_RELOCATION_GUARD_RF_EPILOGUE: u64 = 2;
+pub const IMAGE_DYNAMIC_RELOCATION_GUARD_IMPORT_CONTROL_TRANSFER: u64 = 3;
+pub const IMAGE_DYNAMIC_RELOCATION_GUARD_INDIR_CONTROL_TRANSFER: u64 = 4;
+pub const IMAGE_DYNAMIC_RELOCATION_GUARD_SWITCHTABLE_BRANCH: u64 = 5;
+pub const IMAGE_DYNAMIC_RELOCATION_ARM64X: u64 = 6;
+pub const IMAGE_DYNAMIC_RELOCATION_FUNCTION_OVERRIDE: u64 = 7;
+pub const IMAGE_DYNAMIC_RELOCATION_ARM64_KERNEL_IMPORT_CALL_TRANSFER: u64 = 8;
+
+pub const IMAGE_DVRT_ARM64X_FIXUP_TYPE_ZEROFILL: u8 = 0;
+pub const IMAGE_DVRT_ARM64X_FIXUP_TYPE_VALUE: u8 = 1;
+pub const IMAGE_DVRT_ARM64X_FIXUP_TYPE_DELTA: u8 = 2;
+
+#[derive(Debug, PartialEq, Copy, Clone, Default, Pread, Pwrite, IOread, IOwrite, SizeWith, Serialize, Deserialize)]
+pub struct DynamicRelocationTableHeader {
+    pub version: u32,
+    pub size: u32,
+}
+
+impl DynamicRelocationTableHeader {
+    pub fn parse(bytes: &[u8], offset: &mut usize) -> error::Result<Self> {
+        Ok(bytes.gread_with(offset, scroll::LE)?)
+    }
+}
+
+/// Name of a well-known dynamic relocation symbol, other symbols are VAs of patched variables
+pub fn get_symbol_name(symbol: u64) -> &'static str {
+    match symbol {
+        IMAGE_DYNAMIC_RELOCATION_GUARD_RF_PROLOGUE => "IMAGE_DYNAMIC_RELOCATION_GUARD_RF_PROLOGUE",
+        IMAGE_DYNAMIC_RELOCATION_GUARD_RF_EPILOGUE => "IMAGE_DYNAMIC_RELOCATION_GUARD_RF_EPILOGUE",
+        IMAGE_DYNAMIC_RELOCATION_GUARD_IMPORT_CONTROL_TRANSFER => "IMAGE_DYNAMIC_RELOCATION_GUARD_IMPORT_CONTROL_TRANSFER",
+        IMAGE_DYNAMIC_RELOCATION_GUARD_INDIR_CONTROL_TRANSFER => "IMAGE_DYNAMIC_RELOCATION_GUARD_INDIR_CONTROL_TRANSFER",
+        IMAGE_DYNAMIC_RELOCATION_GUARD_SWITCHTABLE_BRANCH => "IMAGE_DYNAMIC_RELOCATION_GUARD_SWITCHTABLE_BRANCH",
+        IMAGE_DYNAMIC_RELOCATION_ARM64X => "IMAGE_DYNAMIC_RELOCATION_ARM64X",
+        IMAGE_DYNAMIC_RELOCATION_FUNCTION_OVERRIDE => "IMAGE_DYNAMIC_RELOCATION_FUNCTION_OVERRIDE",
+        IMAGE_DYNAMIC_RELOCATION_ARM64_KERNEL_IMPORT_CALL_TRANSFER => "IMAGE_DYNAMIC_RELOCATION_ARM64_KERNEL_IMPORT_CALL_TRANSFER",
+        _ => "UNKNOWN"
+    }
+}
+
+/// Decoded fixup record, the layout depends on the symbol of the relocation
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub enum DynamicRelocationFixup {
+    /// Import call or jump through the IAT
+    ImportControlTransfer { rva: u32, indirect_call: bool, iat_index: u32 },
+    /// Indirect call or jump guarded by CFG
+    IndirectControlTransfer { rva: u32, indirect_call: bool, rex_w_prefix: bool, cfg_check: bool },
+    /// Switch table jump through a register
+    SwitchableBranch { rva: u32, register_number: u8 },
+    /// Zero fill of `size` bytes applied when loaded as ARM64X native
+    Arm64XZeroFill { rva: u32, size: u8 },
+    /// Little endian value of `size` bytes written when loaded as ARM64X native
+    Arm64XValue { rva: u32, size: u8, value: u64 },
+    /// Signed delta added to the pointer when loaded as ARM64X native
+    Arm64XDelta { rva: u32, delta: i64 },
+    /// ARM64 kernel import call, `rva` is the 4-byte aligned instruction
+    Arm64KernelImportCallTransfer { rva: u32, indirect_call: bool, register_index: u8, import_type: u8, iat_index: u16 },
+}
+
+impl DynamicRelocationFixup {
+    pub fn get_rva(&self) -> u32 {
+        match *self {
+            DynamicRelocationFixup::ImportControlTransfer { rva, .. } => rva,
+            DynamicRelocationFixup::IndirectControlTransfer { rva, .. } => rva,
+            DynamicRelocationFixup::SwitchableBranch { rva, .. } => rva,
+            DynamicRelocationFixup::Arm64XZeroFill { rva, .. } => rva,
+            DynamicRelocationFixup::Arm64XValue { rva, .. } => rva,
+            DynamicRelocationFixup::Arm64XDelta { rva, .. } => rva,
+            DynamicRelocationFixup::Arm64KernelImportCallTransfer { rva, .. } => rva,
+        }
+    }
+}
+
+/// Relocation block of a well-known symbol, sharing the base relocation block header
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct DynamicRelocationBlock {
+    pub block: BaseRelocationBlock,
+    pub fixups: Vec<DynamicRelocationFixup>,
+}
+
+impl DynamicRelocationBlock {
+    pub fn parse(bytes: &[u8], offset: &mut usize, symbol: u64) -> error::Result<Self> {
+        let start:usize = *offset;
+        let block:BaseRelocationBlock = BaseRelocationBlock::parse(bytes, offset)?;
+        if (block.block_size as usize) < BaseRelocationBlock::size_with(&scroll::LE) {
+            return Err(error::Error::Malformed(format!("Dynamic relocation block at offset 0x{:x} too small", start)));
+        }
+        let end:usize = (start + block.block_size as usize).min(bytes.len());
+        let mut fixups:Vec<DynamicRelocationFixup> = Vec::new();
+        while *offset + 2 <= end {
+            let fixup:DynamicRelocationFixup = match symbol {
+                IMAGE_DYNAMIC_RELOCATION_GUARD_IMPORT_CONTROL_TRANSFER => {
+                    let record:u32 = bytes.gread_with(offset, scroll::LE)?;
+                    if record == 0 && *offset >= end {
+                        break;
+                    }
+                    DynamicRelocationFixup::ImportControlTransfer {
+                        rva: block.page_rva.wrapping_add(record & 0xfff),
+                        indirect_call: record & 0x1000 != 0,
+                        iat_index: record >> 13
+                    }
+                }
+                IMAGE_DYNAMIC_RELOCATION_ARM64_KERNEL_IMPORT_CALL_TRANSFER => {
+                    let record:u32 = bytes.gread_with(offset, scroll::LE)?;
+                    if record == 0 && *offset >= end {
+                        break;
+                    }
+                    DynamicRelocationFixup::Arm64KernelImportCallTransfer {
+                        rva: block.page_rva.wrapping_add((record & 0x3ff) << 2),
+                        indirect_call: record & 0x400 != 0,
+                        register_index: ((record >> 11) & 0x1f) as u8,
+                        import_type: ((record >> 16) & 1) as u8,
+                        iat_index: (record >> 17) as u16
+                    }
+                }
+                _ => {
+                    let record:u16 = bytes.gread_with(offset, scroll::LE)?;
+                    // Blocks are padded to 4 bytes with an empty record
+                    if record == 0 && *offset >= end {
+                        break;
+                    }
+                    let rva:u32 = block.page_rva.wrapping_add(u32::from(record & 0xfff));
+                    match symbol {
+                        IMAGE_DYNAMIC_RELOCATION_GUARD_INDIR_CONTROL_TRANSFER => DynamicRelocationFixup::IndirectControlTransfer {
+                            rva,
+                            indirect_call: record & 0x1000 != 0,
+                            rex_w_prefix: record & 0x2000 != 0,
+                            cfg_check: record & 0x4000 != 0
+                        },
+                        IMAGE_DYNAMIC_RELOCATION_GUARD_SWITCHTABLE_BRANCH => DynamicRelocationFixup::SwitchableBranch {
+                            rva,
+                            register_number: (record >> 12) as u8
+                        },
+                        _ => {
+                            let size:u8 = ((record >> 14) & 3) as u8;
+                            match ((record >> 12) & 3) as u8 {
+                                IMAGE_DVRT_ARM64X_FIXUP_TYPE_ZEROFILL => DynamicRelocationFixup::Arm64XZeroFill { rva, size: 1 << size },
+                                IMAGE_DVRT_ARM64X_FIXUP_TYPE_VALUE => {
+                                    let value:u64 = match size {
+                                        0 => u64::from(bytes.gread_with::<u8>(offset, scroll::LE)?),
+                                        1 => u64::from(bytes.gread_with::<u16>(offset, scroll::LE)?),
+                                        2 => u64::from(bytes.gread_with::<u32>(offset, scroll::LE)?),
+                                        _ => bytes.gread_with::<u64>(offset, scroll::LE)?
+                                    };
+                                    DynamicRelocationFixup::Arm64XValue { rva, size: 1 << size, value }
+                                }
+                                IMAGE_DVRT_ARM64X_FIXUP_TYPE_DELTA => {
+                                    let value:u16 = bytes.gread_with(offset, scroll::LE)?;
+                                    let scale:i64 = if size & 2 != 0 { 8 } else { 4 };
+                                    let delta:i64 = i64::from(value) * scale;
+                                    DynamicRelocationFixup::Arm64XDelta { rva, delta: if size & 1 != 0 { -delta } else { delta } }
+                                }
+                                // The size of an unknown fixup is unknown as well, the rest of the block is skipped
+                                _ => break
+                            }
+                        }
+                    }
+                }
+            };
+            fixups.push(fixup);
+        }
+        *offset = end;
+        Ok(DynamicRelocationBlock { block, fixups })
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Default, Deserialize)]
+pub struct DynamicRelocation {
+    pub symbol: u64,
+    pub symbol_name: String,
+    /// Version 2 only
+    pub symbol_group: Option<u32>,
+    /// Version 2 only
+    pub flags: Option<u32>,
+    pub fixup_info_size: u32,
+    /// Blocks of the well-known block based symbols
+    pub blocks: Vec<DynamicRelocationBlock>,
+    /// Blocks of a variable symbol, decoded as base relocations
+    pub base_relocations: Vec<BaseRelocation>,
+}
+
+impl Serialize for DynamicRelocation {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("DynamicRelocation", 7)?;
+        state.serialize_field("symbol", &format!("0x{:x}", &self.symbol))?;
+        state.serialize_field("symbol_name", &self.symbol_name)?;
+        state.serialize_field("symbol_group", &self.symbol_group)?;
+        state.serialize_field("flags", &self.flags.map(|flags| format!("0x{:x}", flags)))?;
+        state.serialize_field("fixup_info_size", &self.fixup_info_size)?;
+        state.serialize_field("blocks", &self.blocks)?;
+        state.serialize_field("base_relocations", &self.base_relocations)?;
+        state.end()
+    }
+}
+
+impl DynamicRelocation {
+    /// Parses one relocation of a version 1 or 2 table
+    pub fn parse(bytes: &[u8], offset: &mut usize, version: u32, is_64: bool, machine: u16) -> error::Result<Self> {
+        let start:usize = *offset;
+        let read_symbol = |offset: &mut usize| -> error::Result<u64> {
+            Ok(if is_64 { bytes.gread_with(offset, scroll::LE)? } else { u64::from(bytes.gread_with::<u32>(offset, scroll::LE)?) })
+        };
+        let mut relocation:DynamicRelocation = if version == 1 {
+            let symbol:u64 = read_symbol(offset)?;
+            DynamicRelocation {
+                symbol,
+                fixup_info_size: bytes.gread_with(offset, scroll::LE)?,
+                ..Default::default()
+            }
+        } else {
+            let header_size:u32 = bytes.gread_with(offset, scroll::LE)?;
+            let fixup_info_size:u32 = bytes.gread_with(offset, scroll::LE)?;
+            let symbol:u64 = read_symbol(offset)?;
+            let symbol_group:u32 = bytes.gread_with(offset, scroll::LE)?;
+            let flags:u32 = bytes.gread_with(offset, scroll::LE)?;
+            // The header may grow in later versions but never shrink below its known fields
+            if (header_size as usize) < *offset - start {
+                return Err(error::Error::Malformed(format!("Dynamic relocation header at offset 0x{:x} too small", start)));
+            }
+            *offset = start + header_size as usize;
+            DynamicRelocation {
+                symbol,
+                symbol_group: Some(symbol_group),
+                flags: Some(flags),
+                fixup_info_size,
+                ..Default::default()
+            }
+        };
+        relocation.symbol_name = get_symbol_name(relocation.symbol).to_string();
+
+        let end:usize = *offset + relocation.fixup_info_size as usize;
+        // Blocks are bounded by the fixup info, an oversized one must not decode the next relocation
+        let bytes:&[u8] = &bytes[..end.min(bytes.len())];
+        let header_size:usize = BaseRelocationBlock::size_with(&scroll::LE);
+        match relocation.symbol {
+            // Prologue, epilogue and function override fixups are not block based
+            IMAGE_DYNAMIC_RELOCATION_GUARD_RF_PROLOGUE | IMAGE_DYNAMIC_RELOCATION_GUARD_RF_EPILOGUE | IMAGE_DYNAMIC_RELOCATION_FUNCTION_OVERRIDE => (),
+            IMAGE_DYNAMIC_RELOCATION_GUARD_IMPORT_CONTROL_TRANSFER..=IMAGE_DYNAMIC_RELOCATION_ARM64X | IMAGE_DYNAMIC_RELOCATION_ARM64_KERNEL_IMPORT_CALL_TRANSFER => {
+                while *offset + header_size <= end {
+                    match DynamicRelocationBlock::parse(bytes, offset, relocation.symbol) {
+                        Ok(block) => relocation.blocks.push(block),
+                        Err(_) => break
+                    }
+                }
+            }
+            _ => {
+                while *offset + header_size <= end {
+                    match BaseRelocation::parse(bytes, offset, machine) {
+                        Ok(block) => relocation.base_relocations.push(block),
+                        Err(_) => break
+                    }
+                }
+            }
+        }
+        *offset = end;
+        Ok(relocation)
+    }
+}
+
+/// Dynamic value relocation table referenced by the load configuration directory
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct DynamicRelocationTable {
+    pub header: DynamicRelocationTableHeader,
+    pub relocations: Vec<DynamicRelocation>,
+}
+
+impl DynamicRelocationTable {
+    /// Locates the table from its section and offset, or from the older VA field
+    pub fn parse(bytes: &[u8], headers: &Headers, load_config: &LoadConfigDirectory) -> error::Result<Option<Self>> {
+        let start:usize = if load_config.dynamic_value_reloc_table_section != 0 {
+            let index:usize = load_config.dynamic_value_reloc_table_section as usize - 1;
+            match headers.sections.items.get(index) {
+                Some(section) => section.pointer_to_raw_data as usize + load_config.dynamic_value_reloc_table_offset as usize,
+                None => return Ok(None)
+            }
+        } else if load_config.dynamic_value_reloc_table != 0 {
+            let rva:u32 = load_config.dynamic_value_reloc_table.wrapping_sub(headers.optional.specific_fields.image_base) as u32;
+            match headers.rva_to_offset(rva) {
+                Some(offset) => offset,
+                None => return Ok(None)
+            }
+        } else {
+            return Ok(None);
+        };
+
+        let mut offset:usize = start;
+        let header:DynamicRelocationTableHeader = match DynamicRelocationTableHeader::parse(bytes, &mut offset) {
+            Ok(header) => header,
+            Err(_) => return Ok(None)
+        };
+        if header.version != 1 && header.version != 2 {
+            return Ok(None);
+        }
+        let is_64:bool = headers.optional.standard_fields.signature == OPTIONAL_HEADER_SIGNATURE_64;
+        let end:usize = offset + header.size as usize;
+        let mut relocations:Vec<DynamicRelocation> = Vec::new();
+        while offset < end {
+            let relocation_offset:usize = offset;
+            // A malformed relocation has no reliable size, the relocations after it cannot be located
+            match DynamicRelocation::parse(bytes, &mut offset, header.version, is_64, headers.coff.machine) {
+                Ok(relocation) => relocations.push(relocation),
+                Err(_) => break
+            }
+            if offset <= relocation_offset {
+                break;
+            }
+        }
+        Ok(Some(DynamicRelocationTable { header, relocations }))
+    }
+    /// RVAs of every location patched through the table, as `BaseRelocationDirectory::fixups`
+    pub fn fixups(&self) -> impl Iterator<Item = u32> + '_ {
+        self.relocations.iter().flat_map(|relocation| {
+            relocation.blocks.iter()
+                .flat_map(|block| block.fixups.iter().map(|fixup| fixup.get_rva()))
+                .chain(relocation.base_relocations.iter()
+                    .flat_map(|block| block.entries.iter())
+                    .filter(|entry| entry.relocation_type != IMAGE_REL_BASED_ABSOLUTE)
+                    .map(|entry| entry.rva))
+        })
+    }
+    pub fn get_relocation(&self, symbol: u64) -> Option<&DynamicRelocation> {
+        self.relocations.iter().find(|relocation| relocation.symbol == symbol)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use scroll::Pwrite;
+
+    use super::{DynamicRelocationFixup, DynamicRelocationTable, DynamicRelocationTableHeader, IMAGE_DYNAMIC_RELOCATION_ARM64X, IMAGE_DYNAMIC_RELOCATION_GUARD_IMPORT_CONTROL_TRANSFER};
+    use crate::pe::header::{Headers, OPTIONAL_HEADER_SIGNATURE_64, test_headers};
+    use crate::pe::load_config::LoadConfigDirectory;
+    use crate::pe::relocation::BaseRelocationBlock;
+
+    fn headers() -> Headers {
+        let mut headers = test_headers(0x1000, 0x200);
+        headers.optional.standard_fields.signature = OPTIONAL_HEADER_SIGNATURE_64;
+        headers
+    }
+
+    #[test]
+    fn dynamic_relocations_v1() {
+        let headers = headers();
+        let mut bytes = vec![0u8; 0x200];
+        bytes.pwrite_with(DynamicRelocationTableHeader { version: 1, size: 60 }, 0x10, scroll::LE).unwrap();
+        // Import control transfer
+        bytes.pwrite_with(IMAGE_DYNAMIC_RELOCATION_GUARD_IMPORT_CONTROL_TRANSFER, 0x18, scroll::LE).unwrap();
+        bytes.pwrite_with(16u32, 0x20, scroll::LE).unwrap();
+        bytes.pwrite_with(BaseRelocationBlock { page_rva: 0x2000, block_size: 16 }, 0x24, scroll::LE).unwrap();
+        bytes.pwrite_with(0x3123u32, 0x2c, scroll::LE).unwrap();
+        bytes.pwrite_with(0x0002_0456u32, 0x30, scroll::LE).unwrap();
+        // ARM64X
+        bytes.pwrite_with(IMAGE_DYNAMIC_RELOCATION_ARM64X, 0x34, scroll::LE).unwrap();
+        bytes.pwrite_with(20u32, 0x3c, scroll::LE).unwrap();
+        bytes.pwrite_with(BaseRelocationBlock { page_rva: 0x3000, block_size: 20 }, 0x40, scroll::LE).unwrap();
+        bytes.pwrite_with(0x8010u16, 0x48, scroll::LE).unwrap();
+        bytes.pwrite_with(0x9020u16, 0x4a, scroll::LE).unwrap();
+        bytes.pwrite_with(0xaa64_8664u32, 0x4c, scroll::LE).unwrap();
+        bytes.pwrite_with(0x6030u16, 0x50, scroll::LE).unwrap();
+        bytes.pwrite_with(0x0010u16, 0x52, scroll::LE).unwrap();
+
+        let load_config = LoadConfigDirectory {
+            dynamic_value_reloc_table_offset: 0x10,
+            dynamic_value_reloc_table_section: 1,
+            ..Default::default()
+        };
+        let table = DynamicRelocationTable::parse(&bytes, &headers, &load_config).unwrap().unwrap();
+        assert_eq!(table.relocations.len(), 2);
+        let import = table.get_relocation(IMAGE_DYNAMIC_RELOCATION_GUARD_IMPORT_CONTROL_TRANSFER).unwrap();
+        assert_eq!(import.blocks[0].fixups, vec![
+            DynamicRelocationFixup::ImportControlTransfer { rva: 0x2123, indirect_call: true, iat_index: 1 },
+            DynamicRelocationFixup::ImportControlTransfer { rva: 0x2456, indirect_call: false, iat_index: 16 },
+        ]);
+        let arm64x = table.get_relocation(IMAGE_DYNAMIC_RELOCATION_ARM64X).unwrap();
+        assert_eq!(arm64x.blocks[0].fixups, vec![
+            DynamicRelocationFixup::Arm64XZeroFill { rva: 0x3010, size: 4 },
+            DynamicRelocationFixup::Arm64XValue { rva: 0x3020, size: 4, value: 0xaa64_8664 },
+            DynamicRelocationFixup::Arm64XDelta { rva: 0x3030, delta: -0x40 },
+        ]);
+        assert_eq!(table.fixups().collect::<Vec<u32>>(), vec![0x2123, 0x2456, 0x3010, 0x3020, 0x3030]);
+    }
+
+    #[test]
+    fn dynamic_relocations_v2() {
+        let headers = headers();
+        let mut bytes = vec![0u8; 0x200];
+        bytes.pwrite_with(DynamicRelocationTableHeader { version: 2, size: 36 }, 0, scroll::LE).unwrap();
+        // Variable symbol with base relocations
+        bytes.pwrite_with(24u32, 0x08, scroll::LE).unwrap();
+        bytes.pwrite_with(12u32, 0x0c, scroll::LE).unwrap();
+        bytes.pwrite_with(0x1_4000_5000u64, 0x10, scroll::LE).unwrap();
+        bytes.pwrite_with(7u32, 0x18, scroll::LE).unwrap();
+        bytes.pwrite_with(BaseRelocationBlock { page_rva: 0x4000, block_size: 12 }, 0x20, scroll::LE).unwrap();
+        bytes.pwrite_with(0xa008u16, 0x28, scroll::LE).unwrap();
+
+        let load_config = LoadConfigDirectory {
+            dynamic_value_reloc_table: 0x1000,
+            ..Default::default()
+        };
+        let table = DynamicRelocationTable::parse(&bytes, &headers, &load_config).unwrap().unwrap();
+        assert_eq!(table.relocations.len(), 1);
+        assert_eq!(table.relocations[0].symbol_name, "UNKNOWN");
+        assert_eq!(table.relocations[0].symbol_group, Some(7));
+        assert_eq!(table.relocations[0].base_relocations[0].entries[0].rva, 0x4008);
+        assert_eq!(table.fixups().collect::<Vec<u32>>(), vec![0x4008]);
+        assert!(DynamicRelocationTable::parse(&bytes, &headers, &LoadConfigDirectory::default()).unwrap().is_none());
+    }
+
+    #[test]
+    fn dynamic_relocations_v2_bad_header_size() {
+        let headers = headers();
+        let mut bytes = vec![0u8; 0x200];
+        bytes.pwrite_with(DynamicRelocationTableHeader { version: 2, size: 0x100 }, 0, scroll::LE).unwrap();
+        // Valid relocation without fixups
+        bytes.pwrite_with(24u32, 0x08, scroll::LE).unwrap();
+        bytes.pwrite_with(0x1_4000_5000u64, 0x10, scroll::LE).unwrap();
+        // Header size of zero would move the cursor back to the start of the record
+        bytes.pwrite_with(0u32, 0x20, scroll::LE).unwrap();
+        bytes.pwrite_with(0u32, 0x24, scroll::LE).unwrap();
+
+        let load_config = LoadConfigDirectory { dynamic_value_reloc_table: 0x1000, ..Default::default() };
+        let table = DynamicRelocationTable::parse(&bytes, &headers, &load_config).unwrap().unwrap();
+        assert_eq!(table.relocations.len(), 1);
+        assert_eq!(table.relocations[0].symbol, 0x1_4000_5000);
+    }
+
+    #[test]
+    fn dynamic_relocations_malformed() {
+        let headers = headers();
+        let mut bytes = vec![0u8; 0x200];
+        bytes.pwrite_with(DynamicRelocationTableHeader { version: 1, size: 24 }, 0, scroll::LE).unwrap();
+        // ARM64X block with a zero fill followed by the undefined fixup type 3
+        bytes.pwrite_with(IMAGE_DYNAMIC_RELOCATION_ARM64X, 0x08, scroll::LE).unwrap();
+        bytes.pwrite_with(12u32, 0x10, scroll::LE).unwrap();
+        bytes.pwrite_with(BaseRelocationBlock { page_rva: 0x3000, block_size: 12 }, 0x14, scroll::LE).unwrap();
+        bytes.pwrite_with(0x8010u16, 0x1c, scroll::LE).unwrap();
+        bytes.pwrite_with(0x3020u16, 0x1e, scroll::LE).unwrap();
+
+        let load_config = LoadConfigDirectory { dynamic_value_reloc_table: 0x1000, ..Default::default() };
+        let table = DynamicRelocationTable::parse(&bytes, &headers, &load_config).unwrap().unwrap();
+        assert_eq!(table.relocations[0].blocks[0].fixups, vec![DynamicRelocationFixup::Arm64XZeroFill { rva: 0x3010, size: 4 }]);
+
+        let load_config = LoadConfigDirectory { dynamic_value_reloc_table_section: 5, ..Default::default() };
+        assert!(DynamicRelocationTable::parse(&bytes, &headers, &load_config).unwrap().is_none());
+        let load_config = LoadConfigDirectory { dynamic_value_reloc_table: 0x5000, ..Default::default() };
+        assert!(DynamicRelocationTable::parse(&bytes, &headers, &load_config).unwrap().is_none());
+        let load_config = LoadConfigDirectory { dynamic_value_reloc_table_offset: 0x1fc, dynamic_value_reloc_table_section: 1, ..Default::default() };
+        assert!(DynamicRelocationTable::parse(&bytes, &headers, &load_config).unwrap().is_none());
+        bytes.pwrite_with(DynamicRelocationTableHeader { version: 3, size: 24 }, 0, scroll::LE).unwrap();
+        let load_config = LoadConfigDirectory { dynamic_value_reloc_table: 0x1000, ..Default::default() };
+        assert!(DynamicRelocationTable::parse(&bytes, &headers, &load_config).unwrap().is_none());
+    }
+
+    #[test]
+    fn dynamic_relocations_oversized_block() {
+        let headers = headers();
+        let mut bytes = vec![0u8; 0x200];
+        bytes.pwrite_with(DynamicRelocationTableHeader { version: 1, size: 48 }, 0, scroll::LE).unwrap();
+        // ARM64X block claiming more than the fixup info of its relocation
+        bytes.pwrite_with(IMAGE_DYNAMIC_RELOCATION_ARM64X, 0x08, scroll::LE).unwrap();
+        bytes.pwrite_with(12u32, 0x10, scroll::LE).unwrap();
+        bytes.pwrite_with(BaseRelocationBlock { page_rva: 0x3000, block_size: 0x100 }, 0x14, scroll::LE).unwrap();
+        bytes.pwrite_with(0x8010u16, 0x1c, scroll::LE).unwrap();
+        // Import control transfer
+        bytes.pwrite_with(IMAGE_DYNAMIC_RELOCATION_GUARD_IMPORT_CONTROL_TRANSFER, 0x20, scroll::LE).unwrap();
+        bytes.pwrite_with(12u32, 0x28, scroll::LE).unwrap();
+        bytes.pwrite_with(BaseRelocationBlock { page_rva: 0x2000, block_size: 12 }, 0x2c, scroll::LE).unwrap();
+        bytes.pwrite_with(0x3123u32, 0x34, scroll::LE).unwrap();
+
+        let load_config = LoadConfigDirectory { dynamic_value_reloc_table: 0x1000, ..Default::default() };
+        let table = DynamicRelocationTable::parse(&bytes, &headers, &load_config).unwrap().unwrap();
+        assert_eq!(table.relocations.len(), 2);
+        assert_eq!(table.fixups().collect::<Vec<u32>>(), vec![0x3010, 0x2123]);
+    }
+}
@@ -0,0 +1,340 @@
+use scroll::{IOread, IOwrite, Pread, Pwrite, SizeWith};
+use scroll::ctx::SizeWith as _;
+use serde::{Deserialize, Serialize, Serializer};
+use serde::ser::SerializeStruct;
+
+use crate::error;
+use crate::pe::exception_arm::{ArmExceptionFunction, ArmRuntimeFunction};
+use crate::pe::header::{DataDirectory, Headers, IMAGE_FILE_MACHINE_AMD64, IMAGE_FILE_MACHINE_ARM64, IMAGE_FILE_MACHINE_ARMNT};
+use crate::pe::index;
+
+pub const UNW_FLAG_EHANDLER: u8 = 0x1;
+pub const UNW_FLAG_UHANDLER: u8 = 0x2;
+pub const UNW_FLAG_CHAININFO: u8 = 0x4;
+
+pub const UWOP_PUSH_NONVOL: u8 = 0;
+pub const UWOP_ALLOC_LARGE: u8 = 1;
+pub const UWOP_ALLOC_SMALL: u8 = 2;
+pub const UWOP_SET_FPREG: u8 = 3;
+pub const UWOP_SAVE_NONVOL: u8 = 4;
+pub const UWOP_SAVE_NONVOL_FAR: u8 = 5;
+pub const UWOP_EPILOG: u8 = 6;
+pub const UWOP_SPARE_CODE: u8 = 7;
+pub const UWOP_SAVE_XMM128: u8 = 8;
+pub const UWOP_SAVE_XMM128_FAR: u8 = 9;
+pub const UWOP_PUSH_MACHFRAME: u8 = 10;
+
+/// Set in `unwind_info_address` when it holds the RVA of another .pdata entry sharing its unwind data
+pub const RUNTIME_FUNCTION_INDIRECT: u32 = 0x1;
+
+/// Chains are normally one or two links long, anything longer is treated as a loop
+const UNWIND_CHAIN_MAX_DEPTH: usize = 32;
+
+/// x64 .pdata entry
+#[derive(Debug, PartialEq, Copy, Clone, Default, Pread, Pwrite, IOread, IOwrite, SizeWith, Deserialize)]
+pub struct RuntimeFunction {
+    pub begin_address: u32,
+    pub end_address: u32,
+    pub unwind_info_address: u32,
+}
+
+impl Serialize for RuntimeFunction {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("RuntimeFunction", 3)?;
+        state.serialize_field("begin_address", &format!("0x{:x}", &self.begin_address))?;
+        state.serialize_field("end_address", &format!("0x{:x}", &self.end_address))?;
+        state.serialize_field("unwind_info_address", &format!("0x{:x}", &self.unwind_info_address))?;
+        state.end()
+    }
+}
+
+impl RuntimeFunction {
+    pub fn parse(bytes: &[u8], offset: &mut usize) -> error::Result<Self> {
+        Ok(bytes.gread_with(offset, scroll::LE)?)
+    }
+}
+
+/// Decoded unwind operation, along with the extra slots it consumed
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct UnwindCode {
+    /// Offset of the end of the prolog instruction
+    pub code_offset: u8,
+    pub operation: u8,
+    pub operation_info: u8,
+    pub operation_name: Option<String>,
+    pub register: Option<String>,
+    /// Allocation size or stack offset the register is saved at
+    pub offset: Option<u32>,
+}
+
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct UnwindInfo {
+    pub version: u8,
+    pub flags: u8,
+    pub size_of_prolog: u8,
+    pub count_of_codes: u8,
+    pub frame_register: u8,
+    /// Scaled offset of the frame register from RSP, in units of 16 bytes
+    pub frame_offset: u8,
+    pub unwind_codes: Vec<UnwindCode>,
+    /// RVA of the language specific handler, when UNW_FLAG_EHANDLER or UNW_FLAG_UHANDLER is set
+    pub handler: Option<u32>,
+    /// Primary function entry when UNW_FLAG_CHAININFO is set
+    pub chained_function: Option<RuntimeFunction>,
+    pub chained_unwind_info: Option<Box<UnwindInfo>>,
+}
+
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct ExceptionFunction {
+    pub runtime_function: RuntimeFunction,
+    /// Entry the unwind data was read from, when `runtime_function` is an indirect entry
+    pub indirect_function: Option<RuntimeFunction>,
+    pub unwind_info: UnwindInfo,
+}
+
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct ExceptionDirectory {
+    pub functions: Vec<ExceptionFunction>,
+    /// ARM64 and ARMv7 entries, which use their own .pdata layout
+    pub arm_functions: Vec<ArmExceptionFunction>,
+}
+
+impl UnwindCode {
+    /// Decodes the operation starting at `codes[0]`, returns it with the number of slots used
+    fn parse(codes: &[u16], frame_register: u8) -> error::Result<(Self, usize)> {
+        let slot:u16 = codes[0];
+        let operation:u8 = ((slot >> 8) & 0xf) as u8;
+        let operation_info:u8 = (slot >> 12) as u8;
+        let mut code:UnwindCode = UnwindCode {
+            code_offset: slot as u8,
+            operation,
+            operation_info,
+            operation_name: index::UNWINDOPERATION.get(&operation).map(|name| name.to_string()),
+            ..Default::default()
+        };
+        let general_register:Option<String> = index::X64REGISTER.get(&operation_info).map(|name| name.to_string());
+        let slot_at = |index: usize| -> error::Result<u32> {
+            codes.get(index).map(|&slot| u32::from(slot)).ok_or_else(|| error::Error::Malformed(format!("Unwind code {} truncated", operation)))
+        };
+        let slots:usize = match operation {
+            UWOP_PUSH_NONVOL => {
+                code.register = general_register;
+                1
+            }
+            UWOP_ALLOC_LARGE if operation_info == 0 => {
+                code.offset = Some(slot_at(1)? * 8);
+                2
+            }
+            UWOP_ALLOC_LARGE => {
+                code.offset = Some(slot_at(1)? | slot_at(2)? << 16);
+                3
+            }
+            UWOP_ALLOC_SMALL => {
+                code.offset = Some(u32::from(operation_info) * 8 + 8);
+                1
+            }
+            UWOP_SET_FPREG => {
+                code.register = index::X64REGISTER.get(&frame_register).map(|name| name.to_string());
+                1
+            }
+            UWOP_SAVE_NONVOL => {
+                code.register = general_register;
+                code.offset = Some(slot_at(1)? * 8);
+                2
+            }
+            UWOP_SAVE_NONVOL_FAR => {
+                code.register = general_register;
+                code.offset = Some(slot_at(1)? | slot_at(2)? << 16);
+                3
+            }
+            UWOP_EPILOG => 2,
+            UWOP_SPARE_CODE => 3,
+            UWOP_SAVE_XMM128 => {
+                code.register = Some(format!("XMM{}", operation_info));
+                code.offset = Some(slot_at(1)? * 16);
+                2
+            }
+            UWOP_SAVE_XMM128_FAR => {
+                code.register = Some(format!("XMM{}", operation_info));
+                code.offset = Some(slot_at(1)? | slot_at(2)? << 16);
+                3
+            }
+            UWOP_PUSH_MACHFRAME => 1,
+            // The size of an unknown operation is unknown as well, the remaining codes are skipped
+            _ => codes.len()
+        };
+        Ok((code, slots))
+    }
+}
+
+impl UnwindInfo {
+    pub fn parse(bytes: &[u8], headers: &Headers, rva: u32) -> error::Result<Self> {
+        UnwindInfo::parse_chain(bytes, headers, rva, 0)
+    }
+    fn parse_chain(bytes: &[u8], headers: &Headers, rva: u32, depth: usize) -> error::Result<Self> {
+        if depth > UNWIND_CHAIN_MAX_DEPTH {
+            return Err(error::Error::Malformed(format!("Unwind info chain too long at 0x{:x}", rva)));
+        }
+        let mut offset:usize = headers.rva_to_offset(rva).ok_or(error::Error::BadRva(rva))?;
+        let version_flags:u8 = bytes.gread_with(&mut offset, scroll::LE)?;
+        let size_of_prolog:u8 = bytes.gread_with(&mut offset, scroll::LE)?;
+        let count_of_codes:u8 = bytes.gread_with(&mut offset, scroll::LE)?;
+        let frame:u8 = bytes.gread_with(&mut offset, scroll::LE)?;
+        let mut unwind_info:UnwindInfo = UnwindInfo {
+            version: version_flags & 0x7,
+            flags: version_flags >> 3,
+            size_of_prolog,
+            count_of_codes,
+            frame_register: frame & 0xf,
+            frame_offset: frame >> 4,
+            ..Default::default()
+        };
+
+        let mut codes:Vec<u16> = Vec::with_capacity(count_of_codes as usize);
+        for _ in 0..count_of_codes {
+            codes.push(bytes.gread_with(&mut offset, scroll::LE)?);
+        }
+        let mut index:usize = 0;
+        while index < codes.len() {
+            let (code, slots) = UnwindCode::parse(&codes[index..], unwind_info.frame_register)?;
+            unwind_info.unwind_codes.push(code);
+            index += slots;
+        }
+        // The code array is padded to an even number of slots
+        if count_of_codes % 2 == 1 {
+            offset += 2;
+        }
+
+        if unwind_info.flags & UNW_FLAG_CHAININFO != 0 {
+            let chained_function:RuntimeFunction = RuntimeFunction::parse(bytes, &mut offset)?;
+            unwind_info.chained_unwind_info = Some(Box::new(UnwindInfo::parse_chain(bytes, headers, chained_function.unwind_info_address, depth + 1)?));
+            unwind_info.chained_function = Some(chained_function);
+        } else if unwind_info.flags & (UNW_FLAG_EHANDLER | UNW_FLAG_UHANDLER) != 0 {
+            unwind_info.handler = Some(bytes.pread_with(offset, scroll::LE)?);
+        }
+        Ok(unwind_info)
+    }
+}
+
+impl ExceptionDirectory {
+    /// Parses the .pdata table, with the x64 or ARM layout depending on `COFF.machine`
+    pub fn parse(bytes: &[u8], headers: &Headers, data_directory: DataDirectory) -> error::Result<Self> {
+        let mut exception_directory:ExceptionDirectory = ExceptionDirectory::default();
+        let mut offset:usize = headers.rva_to_offset(data_directory.virtual_address).ok_or(error::Error::BadRva(data_directory.virtual_address))?;
+        match headers.coff.machine {
+            IMAGE_FILE_MACHINE_AMD64 => {
+                let number_of_functions:usize = data_directory.size as usize / RuntimeFunction::size_with(&scroll::LE);
+                for _ in 0..number_of_functions {
+                    let runtime_function:RuntimeFunction = RuntimeFunction::parse(bytes, &mut offset)?;
+                    if runtime_function.begin_address == 0 && runtime_function.end_address == 0 {
+                        continue;
+                    }
+                    let indirect_function:Option<RuntimeFunction> = if runtime_function.unwind_info_address & RUNTIME_FUNCTION_INDIRECT != 0 {
+                        // An unreadable target, or one that is indirect as well, only drops this entry
+                        let rva:u32 = runtime_function.unwind_info_address & !RUNTIME_FUNCTION_INDIRECT;
+                        match headers.rva_to_offset(rva).and_then(|mut indirect_offset| RuntimeFunction::parse(bytes, &mut indirect_offset).ok()) {
+                            Some(indirect_function) if indirect_function.unwind_info_address & RUNTIME_FUNCTION_INDIRECT == 0 => Some(indirect_function),
+                            _ => continue
+                        }
+                    } else {
+                        None
+                    };
+                    let unwind_info_address:u32 = indirect_function.unwrap_or(runtime_function).unwind_info_address;
+                    exception_directory.functions.push(ExceptionFunction {
+                        runtime_function,
+                        indirect_function,
+                        unwind_info: UnwindInfo::parse(bytes, headers, unwind_info_address)?
+                    });
+                }
+            }
+            IMAGE_FILE_MACHINE_ARM64 | IMAGE_FILE_MACHINE_ARMNT => {
+                let number_of_functions:usize = data_directory.size as usize / ArmRuntimeFunction::size_with(&scroll::LE);
+                for _ in 0..number_of_functions {
+                    let runtime_function:ArmRuntimeFunction = ArmRuntimeFunction::parse(bytes, &mut offset)?;
+                    if runtime_function.begin_address == 0 && runtime_function.unwind_data == 0 {
+                        continue;
+                    }
+                    exception_directory.arm_functions.push(ArmExceptionFunction::parse(bytes, headers, runtime_function)?);
+                }
+            }
+            _ => {}
+        }
+        Ok(exception_directory)
+    }
+    /// Finds the function whose range contains `rva`, the table being sorted by address
+    pub fn find_function(&self, rva: u32) -> Option<&ExceptionFunction> {
+        let index:usize = match self.functions.binary_search_by_key(&rva, |function| function.runtime_function.begin_address) {
+            Ok(index) => index,
+            Err(0) => return None,
+            Err(index) => index - 1
+        };
+        self.functions.get(index).filter(|function| rva < function.runtime_function.end_address)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use scroll::Pwrite;
+
+    use crate::pe::header::{DataDirectory, Headers, IMAGE_DIRECTORY_ENTRY_EXCEPTION, IMAGE_FILE_MACHINE_AMD64, test_headers};
+
+    use super::{ExceptionDirectory, RuntimeFunction, UnwindInfo, UWOP_ALLOC_SMALL, UWOP_PUSH_NONVOL};
+
+    #[test]
+    fn exception_directory() {
+        let bytes = include_bytes!("../../samples/pe.exe");
+        let headers = Headers::parse(bytes).unwrap();
+        let data_directory = headers.get_data_directory(IMAGE_DIRECTORY_ENTRY_EXCEPTION).unwrap();
+        let exception_directory = ExceptionDirectory::parse(bytes, &headers, data_directory).unwrap();
+        assert_eq!(exception_directory.functions.len(), 52);
+        assert!(exception_directory.functions.iter().all(|function| function.unwind_info.version == 1));
+        let first = &exception_directory.functions[0];
+        assert_eq!(exception_directory.find_function(first.runtime_function.begin_address), Some(first));
+        assert_eq!(exception_directory.find_function(0), None);
+    }
+
+    #[test]
+    fn unknown_unwind_operation() {
+        let mut bytes = vec![0u8; 0x20];
+        // version 1, three codes: PUSH_NONVOL RBX, unknown operation 11, ALLOC_SMALL
+        bytes[..4].copy_from_slice(&[0x01, 0x08, 0x03, 0x00]);
+        bytes[4..6].copy_from_slice(&[0x02, 0x30 | UWOP_PUSH_NONVOL]);
+        bytes[6..8].copy_from_slice(&[0x04, 0x0b]);
+        bytes[8..10].copy_from_slice(&[0x06, 0x10 | UWOP_ALLOC_SMALL]);
+        let unwind_info = UnwindInfo::parse(&bytes, &test_headers(0x1000, 0x20), 0x1000).unwrap();
+        assert_eq!(unwind_info.unwind_codes.len(), 2);
+        assert_eq!(unwind_info.unwind_codes[0].register.as_deref(), Some("RBX"));
+        assert_eq!(unwind_info.unwind_codes[1].operation, 11);
+        assert_eq!(unwind_info.unwind_codes[1].operation_name, None);
+    }
+
+    #[test]
+    fn indirect_runtime_function() {
+        let mut headers = test_headers(0x1000, 0x40);
+        headers.coff.machine = IMAGE_FILE_MACHINE_AMD64;
+        let mut bytes = vec![0u8; 0x40];
+        bytes.pwrite_with(RuntimeFunction { begin_address: 0x2000, end_address: 0x2010, unwind_info_address: 0x1020 }, 0, scroll::LE).unwrap();
+        // a function split in two parts, the second one reuses the unwind data of the first
+        bytes.pwrite_with(RuntimeFunction { begin_address: 0x2010, end_address: 0x2020, unwind_info_address: 0x1001 }, 12, scroll::LE).unwrap();
+        // version 1, one code: PUSH_NONVOL RBX
+        bytes[0x20..0x24].copy_from_slice(&[0x01, 0x02, 0x01, 0x00]);
+        bytes[0x24..0x26].copy_from_slice(&[0x02, 0x30 | UWOP_PUSH_NONVOL]);
+        let data_directory = DataDirectory { virtual_address: 0x1000, size: 24 };
+        let exception_directory = ExceptionDirectory::parse(&bytes, &headers, data_directory).unwrap();
+        assert_eq!(exception_directory.functions.len(), 2);
+        assert_eq!(exception_directory.functions[0].indirect_function, None);
+        let indirect = &exception_directory.functions[1];
+        assert_eq!(indirect.indirect_function.unwrap().begin_address, 0x2000);
+        assert_eq!(indirect.unwind_info, exception_directory.functions[0].unwind_info);
+        assert_eq!(indirect.unwind_info.unwind_codes[0].register.as_deref(), Some("RBX"));
+
+        // an indirect entry pointing at itself is dropped
+        bytes.pwrite_with(0x100du32, 20, scroll::LE).unwrap();
+        let exception_directory = ExceptionDirectory::parse(&bytes, &headers, data_directory).unwrap();
+        assert_eq!(exception_directory.functions.len(), 1);
+        assert_eq!(exception_directory.functions[0].runtime_function.begin_address, 0x2000);
+    }
+}
@@ -0,0 +1,322 @@
+use scroll::{IOread, IOwrite, Pread, Pwrite, SizeWith};
+use serde::{Deserialize, Serialize, Serializer};
+use serde::ser::SerializeStruct;
+
+use crate::error;
+use crate::pe::header::{Headers, IMAGE_FILE_MACHINE_ARMNT};
+
+/// `.pdata` flag: the second word is the RVA of an `.xdata` record
+pub const PDATA_FLAG_XDATA: u8 = 0;
+/// `.pdata` flag: packed unwind data of a function with a canonical prolog and epilog
+pub const PDATA_FLAG_PACKED: u8 = 1;
+/// `.pdata` flag: packed unwind data of a fragment without prolog
+pub const PDATA_FLAG_PACKED_FRAGMENT: u8 = 2;
+
+/// ARM64 and ARMv7 .pdata entry
+#[derive(Debug, PartialEq, Copy, Clone, Default, Pread, Pwrite, IOread, IOwrite, SizeWith, Deserialize)]
+pub struct ArmRuntimeFunction {
+    pub begin_address: u32,
+    pub unwind_data: u32,
+}
+
+impl Serialize for ArmRuntimeFunction {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("ArmRuntimeFunction", 2)?;
+        state.serialize_field("begin_address", &format!("0x{:x}", &self.begin_address))?;
+        state.serialize_field("unwind_data", &format!("0x{:x}", &self.unwind_data))?;
+        state.end()
+    }
+}
+
+impl ArmRuntimeFunction {
+    pub fn parse(bytes: &[u8], offset: &mut usize) -> error::Result<Self> {
+        Ok(bytes.gread_with(offset, scroll::LE)?)
+    }
+    pub fn get_flag(&self) -> u8 {
+        (self.unwind_data & 0x3) as u8
+    }
+}
+
+/// Packed ARM64 unwind data, sizes already scaled to bytes
+#[derive(Debug, PartialEq, Copy, Clone, Default, Serialize, Deserialize)]
+pub struct Arm64PackedUnwindData {
+    pub flag: u8,
+    pub function_length: u32,
+    /// Number of non-volatile FP registers saved (d8-d15)
+    pub reg_f: u8,
+    /// Number of non-volatile integer registers saved (x19-x28)
+    pub reg_i: u8,
+    /// Whether the integer parameter registers x0-x7 are homed
+    pub h: bool,
+    /// 0: unchained, 1: unchained with lr saved, 2: chained with pac, 3: chained
+    pub cr: u8,
+    pub frame_size: u32,
+}
+
+impl Arm64PackedUnwindData {
+    pub fn parse(unwind_data: u32) -> Self {
+        Arm64PackedUnwindData {
+            flag: (unwind_data & 0x3) as u8,
+            function_length: ((unwind_data >> 2) & 0x7ff) * 4,
+            reg_f: ((unwind_data >> 13) & 0x7) as u8,
+            reg_i: ((unwind_data >> 16) & 0xf) as u8,
+            h: (unwind_data >> 20) & 0x1 != 0,
+            cr: ((unwind_data >> 21) & 0x3) as u8,
+            frame_size: (unwind_data >> 23) * 16,
+        }
+    }
+}
+
+/// Packed ARMv7 unwind data, sizes already scaled to bytes
+#[derive(Debug, PartialEq, Copy, Clone, Default, Serialize, Deserialize)]
+pub struct ArmPackedUnwindData {
+    pub flag: u8,
+    pub function_length: u32,
+    /// 0: pop {pc}, 1: 16-bit branch, 2: 32-bit branch, 3: no epilog
+    pub ret: u8,
+    /// Whether the integer parameter registers r0-r3 are homed
+    pub h: bool,
+    /// Index of the last saved non-volatile register
+    pub reg: u8,
+    /// Whether floating point registers are saved instead of integer ones
+    pub r: bool,
+    /// Whether lr is saved/restored
+    pub l: bool,
+    /// Whether r11 is set up as frame pointer
+    pub c: bool,
+    pub stack_adjust: u32,
+}
+
+impl ArmPackedUnwindData {
+    pub fn parse(unwind_data: u32) -> Self {
+        ArmPackedUnwindData {
+            flag: (unwind_data & 0x3) as u8,
+            function_length: ((unwind_data >> 2) & 0x7ff) * 2,
+            ret: ((unwind_data >> 13) & 0x3) as u8,
+            h: (unwind_data >> 15) & 0x1 != 0,
+            reg: ((unwind_data >> 16) & 0x7) as u8,
+            r: (unwind_data >> 19) & 0x1 != 0,
+            l: (unwind_data >> 20) & 0x1 != 0,
+            c: (unwind_data >> 21) & 0x1 != 0,
+            stack_adjust: (unwind_data >> 22) * 4,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Copy, Clone, Default, Serialize, Deserialize)]
+pub struct EpilogScope {
+    /// Offset of the epilog from the start of the function, in bytes
+    pub start_offset: u32,
+    /// Index of the first unwind code byte describing the epilog
+    pub start_index: u16,
+    /// Condition the epilog is executed under, ARMv7 only
+    pub condition: Option<u8>,
+}
+
+/// `.xdata` record, shared by ARM64 and ARMv7 with slightly different bit layouts
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct ArmUnwindRecord {
+    pub function_length: u32,
+    pub version: u8,
+    /// Whether exception data (handler RVA) follows the unwind codes
+    pub x: bool,
+    /// Whether a single epilog is described by the header instead of a scope list
+    pub e: bool,
+    /// Whether the record describes a fragment without prolog, ARMv7 only
+    pub f: Option<bool>,
+    /// Number of epilog scopes, or unwind code index of the single epilog when `e` is set
+    pub epilog_count: u16,
+    pub code_words: u8,
+    pub epilog_scopes: Vec<EpilogScope>,
+    pub unwind_codes: Vec<u8>,
+    pub handler: Option<u32>,
+}
+
+impl ArmUnwindRecord {
+    pub fn parse(bytes: &[u8], headers: &Headers, rva: u32) -> error::Result<Self> {
+        let arm:bool = headers.coff.machine == IMAGE_FILE_MACHINE_ARMNT;
+        let mut offset:usize = headers.rva_to_offset(rva).ok_or(error::Error::BadRva(rva))?;
+        let header:u32 = bytes.gread_with(&mut offset, scroll::LE)?;
+        let mut record:ArmUnwindRecord = if arm {
+            ArmUnwindRecord {
+                function_length: (header & 0x3ffff) * 2,
+                version: ((header >> 18) & 0x3) as u8,
+                x: (header >> 20) & 0x1 != 0,
+                e: (header >> 21) & 0x1 != 0,
+                f: Some((header >> 22) & 0x1 != 0),
+                epilog_count: ((header >> 23) & 0x1f) as u16,
+                code_words: (header >> 28) as u8,
+                ..Default::default()
+            }
+        } else {
+            ArmUnwindRecord {
+                function_length: (header & 0x3ffff) * 4,
+                version: ((header >> 18) & 0x3) as u8,
+                x: (header >> 20) & 0x1 != 0,
+                e: (header >> 21) & 0x1 != 0,
+                epilog_count: ((header >> 22) & 0x1f) as u16,
+                code_words: (header >> 27) as u8,
+                ..Default::default()
+            }
+        };
+        // Both counts being zero announces an extension word holding larger ones
+        if record.epilog_count == 0 && record.code_words == 0 {
+            let extension:u32 = bytes.gread_with(&mut offset, scroll::LE)?;
+            record.epilog_count = extension as u16;
+            record.code_words = (extension >> 16) as u8;
+        }
+        if !record.e {
+            for _ in 0..record.epilog_count {
+                let scope:u32 = bytes.gread_with(&mut offset, scroll::LE)?;
+                record.epilog_scopes.push(if arm {
+                    EpilogScope {
+                        start_offset: (scope & 0x3ffff) * 2,
+                        start_index: (scope >> 24) as u16,
+                        condition: Some(((scope >> 20) & 0xf) as u8),
+                    }
+                } else {
+                    EpilogScope {
+                        start_offset: (scope & 0x3ffff) * 4,
+                        start_index: (scope >> 22) as u16,
+                        condition: None,
+                    }
+                });
+            }
+        }
+        if record.code_words > 0 {
+            let unwind_codes:&[u8] = bytes.gread_with(&mut offset, record.code_words as usize * 4)?;
+            record.unwind_codes = unwind_codes.to_vec();
+        }
+        if record.x {
+            record.handler = Some(bytes.pread_with(offset, scroll::LE)?);
+        }
+        Ok(record)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub enum ArmUnwindData {
+    Arm64Packed(Arm64PackedUnwindData),
+    ArmPacked(ArmPackedUnwindData),
+    Record(ArmUnwindRecord),
+    /// Reserved `.pdata` flag, the unwind data cannot be interpreted
+    Unknown,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct ArmExceptionFunction {
+    pub runtime_function: ArmRuntimeFunction,
+    pub unwind_data: ArmUnwindData,
+}
+
+impl ArmExceptionFunction {
+    /// Decodes the unwind data of a .pdata entry, packed or through its .xdata record
+    pub fn parse(bytes: &[u8], headers: &Headers, runtime_function: ArmRuntimeFunction) -> error::Result<Self> {
+        let arm:bool = headers.coff.machine == IMAGE_FILE_MACHINE_ARMNT;
+        let unwind_data:ArmUnwindData = match runtime_function.get_flag() {
+            PDATA_FLAG_XDATA => ArmUnwindData::Record(ArmUnwindRecord::parse(bytes, headers, runtime_function.unwind_data)?),
+            PDATA_FLAG_PACKED | PDATA_FLAG_PACKED_FRAGMENT if arm => ArmUnwindData::ArmPacked(ArmPackedUnwindData::parse(runtime_function.unwind_data)),
+            PDATA_FLAG_PACKED | PDATA_FLAG_PACKED_FRAGMENT => ArmUnwindData::Arm64Packed(Arm64PackedUnwindData::parse(runtime_function.unwind_data)),
+            _ => ArmUnwindData::Unknown
+        };
+        Ok(ArmExceptionFunction { runtime_function, unwind_data })
+    }
+    /// Start of the function, without the Thumb bit ARMv7 addresses carry
+    pub fn get_begin_address(&self) -> u32 {
+        self.runtime_function.begin_address & !1
+    }
+    pub fn get_function_length(&self) -> u32 {
+        match self.unwind_data {
+            ArmUnwindData::Arm64Packed(ref packed) => packed.function_length,
+            ArmUnwindData::ArmPacked(ref packed) => packed.function_length,
+            ArmUnwindData::Record(ref record) => record.function_length,
+            ArmUnwindData::Unknown => 0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use scroll::Pwrite;
+
+    use super::{ArmExceptionFunction, ArmRuntimeFunction, ArmUnwindData};
+    use crate::pe::header::{Headers, IMAGE_FILE_MACHINE_ARM64, IMAGE_FILE_MACHINE_ARMNT, test_headers};
+
+    fn headers(machine: u16) -> Headers {
+        let mut headers = test_headers(0x1000, 0x200);
+        headers.coff.machine = machine;
+        headers
+    }
+
+    #[test]
+    fn arm64_packed() {
+        let headers = headers(IMAGE_FILE_MACHINE_ARM64);
+        // Flag 1, 0x40 bytes long, RegF 1, RegI 2, H, CR 3, 0x30 bytes of stack
+        let unwind_data = 1 | 0x10 << 2 | 1 << 13 | 2 << 16 | 1 << 20 | 3 << 21 | 3 << 23;
+        let function = ArmExceptionFunction::parse(&[], &headers, ArmRuntimeFunction { begin_address: 0x1000, unwind_data }).unwrap();
+        match function.unwind_data {
+            ArmUnwindData::Arm64Packed(packed) => {
+                assert_eq!(packed.function_length, 0x40);
+                assert_eq!(packed.reg_f, 1);
+                assert_eq!(packed.reg_i, 2);
+                assert!(packed.h);
+                assert_eq!(packed.cr, 3);
+                assert_eq!(packed.frame_size, 0x30);
+            }
+            ref other => panic!("unexpected unwind data {:?}", other)
+        }
+    }
+
+    #[test]
+    fn arm64_xdata() {
+        let headers = headers(IMAGE_FILE_MACHINE_ARM64);
+        let mut bytes = vec![0u8; 0x200];
+        // 0x100 bytes long, X, two epilog scopes, one code word
+        bytes.pwrite_with(0x40u32 | 1 << 20 | 2 << 22 | 1 << 27, 0x80, scroll::LE).unwrap();
+        bytes.pwrite_with(0x30u32 | 1 << 22, 0x84, scroll::LE).unwrap();
+        bytes.pwrite_with(0x3cu32 | 2 << 22, 0x88, scroll::LE).unwrap();
+        bytes[0x8c..0x90].copy_from_slice(&[0xe1, 0xe4, 0xe4, 0xe3]);
+        bytes.pwrite_with(0x1180u32, 0x90, scroll::LE).unwrap();
+        let function = ArmExceptionFunction::parse(&bytes, &headers, ArmRuntimeFunction { begin_address: 0x1000, unwind_data: 0x1080 }).unwrap();
+        assert_eq!(function.get_function_length(), 0x100);
+        match function.unwind_data {
+            ArmUnwindData::Record(record) => {
+                assert_eq!(record.epilog_scopes.len(), 2);
+                assert_eq!(record.epilog_scopes[1].start_offset, 0xf0);
+                assert_eq!(record.epilog_scopes[1].start_index, 2);
+                assert_eq!(record.unwind_codes, vec![0xe1, 0xe4, 0xe4, 0xe3]);
+                assert_eq!(record.handler, Some(0x1180));
+            }
+            ref other => panic!("unexpected unwind data {:?}", other)
+        }
+    }
+
+    #[test]
+    fn arm_packed() {
+        let headers = headers(IMAGE_FILE_MACHINE_ARMNT);
+        // Flag 1, 0x20 bytes long, Ret 0, Reg 3, L, C, 0x10 bytes of stack
+        let unwind_data = 1 | 0x10 << 2 | 3 << 16 | 1 << 20 | 1 << 21 | 4 << 22;
+        let function = ArmExceptionFunction::parse(&[], &headers, ArmRuntimeFunction { begin_address: 0x1001, unwind_data }).unwrap();
+        assert_eq!(function.get_begin_address(), 0x1000);
+        match function.unwind_data {
+            ArmUnwindData::ArmPacked(packed) => {
+                assert_eq!(packed.function_length, 0x20);
+                assert_eq!(packed.reg, 3);
+                assert!(packed.l && packed.c && !packed.r && !packed.h);
+                assert_eq!(packed.stack_adjust, 0x10);
+            }
+            ref other => panic!("unexpected unwind data {:?}", other)
+        }
+    }
+
+    #[test]
+    fn reserved_flag() {
+        let headers = headers(IMAGE_FILE_MACHINE_ARM64);
+        let function = ArmExceptionFunction::parse(&[], &headers, ArmRuntimeFunction { begin_address: 0x1000, unwind_data: 0x1083 }).unwrap();
+        assert_eq!(function.unwind_data, ArmUnwindData::Unknown);
+        assert_eq!(function.get_function_length(), 0);
+    }
+}
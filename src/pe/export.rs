@@ -0,0 +1,182 @@
+use scroll::{IOread, IOwrite, Pread, Pwrite, SizeWith};
+use serde::{Deserialize, Serialize, Serializer};
+use serde::ser::SerializeStruct;
+
+use crate::error;
+use crate::pe::header::{DataDirectory, Headers};
+
+/// Export directory table located at the beginning of the export data directory
+#[derive(Debug, PartialEq, Copy, Clone, Default, Pread, Pwrite, IOread, IOwrite, SizeWith, Deserialize)]
+pub struct ExportDirectoryTable {
+    pub export_flags: u32,
+    pub time_date_stamp: u32,
+    pub major_version: u16,
+    pub minor_version: u16,
+    pub name_rva: u32,
+    pub ordinal_base: u32,
+    pub address_table_entries: u32,
+    pub number_of_name_pointers: u32,
+    pub export_address_table_rva: u32,
+    pub name_pointer_rva: u32,
+    pub ordinal_table_rva: u32,
+}
+
+impl Serialize for ExportDirectoryTable {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("ExportDirectoryTable", 11)?;
+        state.serialize_field("export_flags", &format!("0x{:x}", &self.export_flags))?;
+        state.serialize_field("time_date_stamp", &format!("0x{:x}", &self.time_date_stamp))?;
+        state.serialize_field("major_version", &self.major_version)?;
+        state.serialize_field("minor_version", &self.minor_version)?;
+        state.serialize_field("name_rva", &format!("0x{:x}", &self.name_rva))?;
+        state.serialize_field("ordinal_base", &self.ordinal_base)?;
+        state.serialize_field("address_table_entries", &self.address_table_entries)?;
+        state.serialize_field("number_of_name_pointers", &self.number_of_name_pointers)?;
+        state.serialize_field("export_address_table_rva", &format!("0x{:x}", &self.export_address_table_rva))?;
+        state.serialize_field("name_pointer_rva", &format!("0x{:x}", &self.name_pointer_rva))?;
+        state.serialize_field("ordinal_table_rva", &format!("0x{:x}", &self.ordinal_table_rva))?;
+        state.end()
+    }
+}
+
+impl ExportDirectoryTable {
+    pub fn parse(bytes: &[u8], offset: &mut usize) -> error::Result<Self> {
+        Ok(bytes.gread_with(offset, scroll::LE)?)
+    }
+}
+
+/// A single exported symbol
+#[derive(Debug, PartialEq, Clone, Default, Deserialize)]
+pub struct Export {
+    pub name: Option<String>,
+    pub ordinal: u32,
+    pub rva: u32,
+    /// Forwarder string (e.g. `NTDLL.RtlAllocateHeap`) when the RVA points inside the export directory
+    pub forwarder: Option<String>,
+}
+
+impl Serialize for Export {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("Export", 4)?;
+        state.serialize_field("name", &self.name)?;
+        state.serialize_field("ordinal", &self.ordinal)?;
+        state.serialize_field("rva", &format!("0x{:x}", &self.rva))?;
+        state.serialize_field("forwarder", &self.forwarder)?;
+        state.end()
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct ExportDirectory {
+    pub export_directory_table: ExportDirectoryTable,
+    pub name: String,
+    pub exports: Vec<Export>,
+}
+
+impl ExportDirectory {
+    pub fn parse(bytes: &[u8], headers: &Headers, data_directory: DataDirectory) -> error::Result<Self> {
+        let mut offset:usize = headers.rva_to_offset(data_directory.virtual_address).ok_or(error::Error::BadRva(data_directory.virtual_address))?;
+        let export_directory_table:ExportDirectoryTable = ExportDirectoryTable::parse(bytes, &mut offset)?;
+        let name:String = headers.get_string(bytes, export_directory_table.name_rva)?;
+
+        let mut exports:Vec<Export> = Vec::new();
+        let address_table_offset:usize = headers.rva_to_offset(export_directory_table.export_address_table_rva).ok_or(error::Error::BadRva(export_directory_table.export_address_table_rva))?;
+        for i in 0..export_directory_table.address_table_entries {
+            let rva:u32 = bytes.pread_with(address_table_offset + i as usize * 4, scroll::LE)?;
+            let forwarder:Option<String> = if data_directory.virtual_address <= rva && rva - data_directory.virtual_address < data_directory.size {
+                Some(headers.get_string(bytes, rva)?)
+            } else {
+                None
+            };
+            exports.push(Export {
+                name: None,
+                ordinal: export_directory_table.ordinal_base.wrapping_add(i),
+                rva,
+                forwarder
+            });
+        }
+
+        // Names are optional and reference an entry of the address table through the ordinal table
+        if export_directory_table.number_of_name_pointers != 0 {
+            let name_pointer_offset:usize = headers.rva_to_offset(export_directory_table.name_pointer_rva).ok_or(error::Error::BadRva(export_directory_table.name_pointer_rva))?;
+            let ordinal_table_offset:usize = headers.rva_to_offset(export_directory_table.ordinal_table_rva).ok_or(error::Error::BadRva(export_directory_table.ordinal_table_rva))?;
+            for i in 0..export_directory_table.number_of_name_pointers as usize {
+                let name_rva:u32 = bytes.pread_with(name_pointer_offset + i * 4, scroll::LE)?;
+                let ordinal:u16 = bytes.pread_with(ordinal_table_offset + i * 2, scroll::LE)?;
+                if let Some(export) = exports.get_mut(ordinal as usize) {
+                    export.name = Some(headers.get_string(bytes, name_rva)?);
+                }
+            }
+        }
+        // Unused slots of the address table are left to zero
+        exports.retain(|export| export.rva != 0);
+
+        Ok(ExportDirectory {
+            export_directory_table,
+            name,
+            exports
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use scroll::Pwrite;
+
+    use super::{ExportDirectory, ExportDirectoryTable};
+    use crate::pe::header::{DataDirectory, test_headers};
+
+    #[test]
+    fn exports() {
+        // Single section mapped at RVA 0x1000 and file offset 0
+        let mut bytes = vec![0u8; 0x200];
+        let table = ExportDirectoryTable {
+            name_rva: 0x1100,
+            ordinal_base: 5,
+            address_table_entries: 3,
+            number_of_name_pointers: 2,
+            export_address_table_rva: 0x1040,
+            name_pointer_rva: 0x1050,
+            ordinal_table_rva: 0x1060,
+            ..Default::default()
+        };
+        bytes.pwrite_with(table, 0, scroll::LE).unwrap();
+        for (i, rva) in [0x2000u32, 0, 0x1120].iter().enumerate() {
+            bytes.pwrite_with(*rva, 0x40 + i * 4, scroll::LE).unwrap();
+        }
+        bytes.pwrite_with(0x1110u32, 0x50, scroll::LE).unwrap();
+        bytes.pwrite_with(0x1118u32, 0x54, scroll::LE).unwrap();
+        bytes.pwrite_with(2u16, 0x60, scroll::LE).unwrap();
+        bytes.pwrite_with(0u16, 0x62, scroll::LE).unwrap();
+        bytes[0x100..0x108].copy_from_slice(b"test.dll");
+        bytes[0x110..0x115].copy_from_slice(b"Alloc");
+        bytes[0x118..0x11c].copy_from_slice(b"Main");
+        bytes[0x120..0x135].copy_from_slice(b"NTDLL.RtlAllocateHeap");
+
+        let headers = test_headers(0x1000, 0x200);
+        let data_directory = DataDirectory { virtual_address: 0x1000, size: 0x140 };
+        let export_directory = ExportDirectory::parse(&bytes, &headers, data_directory).unwrap();
+        assert_eq!(export_directory.name, "test.dll");
+        assert_eq!(export_directory.exports.len(), 2);
+        assert_eq!(export_directory.exports[0].name.as_deref(), Some("Main"));
+        assert_eq!(export_directory.exports[0].ordinal, 5);
+        assert_eq!(export_directory.exports[0].forwarder, None);
+        assert_eq!(export_directory.exports[1].name.as_deref(), Some("Alloc"));
+        assert_eq!(export_directory.exports[1].ordinal, 7);
+        assert_eq!(export_directory.exports[1].forwarder.as_deref(), Some("NTDLL.RtlAllocateHeap"));
+    }
+
+    #[test]
+    fn exports_bad_name_rva() {
+        let mut bytes = vec![0u8; 0x200];
+        bytes.pwrite_with(ExportDirectoryTable { name_rva: 0x5000, ..Default::default() }, 0, scroll::LE).unwrap();
+        let data_directory = DataDirectory { virtual_address: 0x1000, size: 0x28 };
+        assert!(ExportDirectory::parse(&bytes, &test_headers(0x1000, 0x200), data_directory).is_err());
+    }
+}
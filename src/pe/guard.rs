@@ -0,0 +1,197 @@
+use scroll::Pread;
+use serde::{Deserialize, Serialize, Serializer};
+use serde::ser::SerializeStruct;
+
+use crate::error;
+use crate::pe::header::Headers;
+use crate::pe::index;
+use crate::pe::load_config::LoadConfigDirectory;
+
+pub const IMAGE_GUARD_CF_INSTRUMENTED: u32 = 0x00000100;
+pub const IMAGE_GUARD_CF_FUNCTION_TABLE_PRESENT: u32 = 0x00000400;
+pub const IMAGE_GUARD_XFG_ENABLED: u32 = 0x00800000;
+pub const IMAGE_GUARD_CF_FUNCTION_TABLE_SIZE_MASK: u32 = 0xf0000000;
+pub const IMAGE_GUARD_CF_FUNCTION_TABLE_SIZE_SHIFT: u32 = 28;
+
+/// Metadata bits following the RVA of a guard table entry
+pub const IMAGE_GUARD_FLAG_FID_SUPPRESSED: u8 = 0x01;
+pub const IMAGE_GUARD_FLAG_EXPORT_SUPPRESSED: u8 = 0x02;
+pub const IMAGE_GUARD_FLAG_FID_LANGEXCPTHANDLER: u8 = 0x04;
+pub const IMAGE_GUARD_FLAG_FID_XFG: u8 = 0x08;
+
+/// Entry of one of the guard tables: a target RVA and its optional metadata
+#[derive(Debug, PartialEq, Copy, Clone, Default, Deserialize)]
+pub struct GuardTarget {
+    pub rva: u32,
+    pub flags: Option<u8>,
+    /// Type hash stored in the 8 bytes preceding an XFG target
+    pub xfg_hash: Option<u64>,
+}
+
+impl Serialize for GuardTarget {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("GuardTarget", 3)?;
+        state.serialize_field("rva", &format!("0x{:x}", &self.rva))?;
+        state.serialize_field("flags", &self.flags)?;
+        state.serialize_field("xfg_hash", &self.xfg_hash.map(|xfg_hash| format!("0x{:x}", xfg_hash)))?;
+        state.end()
+    }
+}
+
+/// Control Flow Guard metadata referenced by the load configuration directory
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct ControlFlowGuard {
+    pub guard_flags: u32,
+    pub guard_flag_names: Vec<String>,
+    /// Size of each table entry, a 4-byte RVA followed by the metadata bytes
+    pub stride: u32,
+    pub functions: Vec<GuardTarget>,
+    pub address_taken_iat_entries: Vec<GuardTarget>,
+    pub long_jump_targets: Vec<GuardTarget>,
+    pub eh_continuation_targets: Vec<GuardTarget>,
+    pub xfg_check_function_pointer: u64,
+    pub xfg_dispatch_function_pointer: u64,
+    pub xfg_table_dispatch_function_pointer: u64,
+}
+
+/// Named bits of a GuardFlags value, sorted by value
+pub fn get_guard_flag_names(guard_flags: u32) -> Vec<String> {
+    let mut flags:Vec<(&u32, &&str)> = index::GUARDFLAG.iter().filter(|(flag, _)| guard_flags & **flag != 0).collect();
+    flags.sort();
+    flags.into_iter().map(|(_, name)| name.to_string()).collect()
+}
+
+/// Reads a guard table, truncated at the end of the file and empty when it lies outside of the image
+fn parse_table(bytes: &[u8], headers: &Headers, table: u64, count: u64, stride: u32, xfg: bool) -> Vec<GuardTarget> {
+    let mut targets:Vec<GuardTarget> = Vec::new();
+    if table == 0 || count == 0 {
+        return targets;
+    }
+    let table_rva:u32 = table.wrapping_sub(headers.optional.specific_fields.image_base) as u32;
+    let offset:usize = match headers.rva_to_offset(table_rva) {
+        Some(offset) => offset,
+        None => return targets
+    };
+    for index in 0..count as usize {
+        let entry:usize = offset + index * stride as usize;
+        if entry + stride as usize > bytes.len() {
+            break;
+        }
+        let rva:u32 = bytes.pread_with(entry, scroll::LE).unwrap_or_default();
+        let flags:Option<u8> = if stride > 4 { bytes.pread_with(entry + 4, scroll::LE).ok() } else { None };
+        let xfg_hash:Option<u64> = match flags {
+            Some(flags) if xfg && flags & IMAGE_GUARD_FLAG_FID_XFG != 0 => {
+                headers.rva_to_offset(rva.wrapping_sub(8)).and_then(|hash_offset| bytes.pread_with(hash_offset, scroll::LE).ok())
+            }
+            _ => None
+        };
+        targets.push(GuardTarget { rva, flags, xfg_hash });
+    }
+    targets
+}
+
+impl ControlFlowGuard {
+    /// Extracts the guard tables, `None` when the image carries no CFG metadata.
+    /// Each table is truncated on its own when it runs out of the file
+    pub fn parse(bytes: &[u8], headers: &Headers, load_config: &LoadConfigDirectory) -> error::Result<Option<Self>> {
+        let guard_flags:u32 = load_config.guard_flags;
+        if guard_flags == 0 && load_config.guard_cf_function_table == 0 {
+            return Ok(None);
+        }
+        let stride:u32 = 4 + ((guard_flags & IMAGE_GUARD_CF_FUNCTION_TABLE_SIZE_MASK) >> IMAGE_GUARD_CF_FUNCTION_TABLE_SIZE_SHIFT);
+        let xfg:bool = guard_flags & IMAGE_GUARD_XFG_ENABLED != 0;
+        Ok(Some(ControlFlowGuard {
+            guard_flags,
+            guard_flag_names: get_guard_flag_names(guard_flags),
+            stride,
+            functions: parse_table(bytes, headers, load_config.guard_cf_function_table, load_config.guard_cf_function_count, stride, xfg),
+            address_taken_iat_entries: parse_table(bytes, headers, load_config.guard_address_taken_iat_entry_table, load_config.guard_address_taken_iat_entry_count, stride, false),
+            long_jump_targets: parse_table(bytes, headers, load_config.guard_long_jump_target_table, load_config.guard_long_jump_target_count, stride, false),
+            eh_continuation_targets: parse_table(bytes, headers, load_config.guard_eh_continuation_table, load_config.guard_eh_continuation_count, stride, false),
+            xfg_check_function_pointer: load_config.guard_xfg_check_function_pointer,
+            xfg_dispatch_function_pointer: load_config.guard_xfg_dispatch_function_pointer,
+            xfg_table_dispatch_function_pointer: load_config.guard_xfg_table_dispatch_function_pointer,
+        }))
+    }
+    /// True when the image was built with `/guard:cf` and ships its target table
+    pub fn is_instrumented(&self) -> bool {
+        self.guard_flags & IMAGE_GUARD_CF_INSTRUMENTED != 0 && self.guard_flags & IMAGE_GUARD_CF_FUNCTION_TABLE_PRESENT != 0
+    }
+    /// Valid indirect call targets, suppressed functions excluded
+    pub fn call_targets(&self) -> impl Iterator<Item = u32> + '_ {
+        self.functions.iter()
+            .filter(|function| function.flags.unwrap_or(0) & IMAGE_GUARD_FLAG_FID_SUPPRESSED == 0)
+            .map(|function| function.rva)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use scroll::Pwrite;
+
+    use super::{ControlFlowGuard, IMAGE_GUARD_FLAG_FID_SUPPRESSED, IMAGE_GUARD_FLAG_FID_XFG};
+    use crate::pe::header::{DataDirectory, IMAGE_DIRECTORY_ENTRY_LOAD_CONFIG, test_headers, test_image};
+    use crate::pe::load_config::LoadConfigDirectory;
+    use crate::pe::pe::PE;
+
+    #[test]
+    fn control_flow_guard() {
+        let mut headers = test_headers(0x1000, 0x200);
+        headers.optional.specific_fields.image_base = 0x1_4000_0000;
+        let mut bytes = vec![0u8; 0x200];
+        // Function table with one metadata byte per entry
+        bytes.pwrite_with(0x1100u32, 0x00, scroll::LE).unwrap();
+        bytes[0x04] = IMAGE_GUARD_FLAG_FID_XFG;
+        bytes.pwrite_with(0x1120u32, 0x05, scroll::LE).unwrap();
+        bytes[0x09] = IMAGE_GUARD_FLAG_FID_SUPPRESSED;
+        bytes.pwrite_with(0x1130u32, 0x0a, scroll::LE).unwrap();
+        bytes.pwrite_with(0xfeed_beefu64, 0xf8, scroll::LE).unwrap();
+        // Long jump table
+        bytes.pwrite_with(0x1140u32, 0x20, scroll::LE).unwrap();
+
+        let load_config = LoadConfigDirectory {
+            guard_flags: 0x1080_0500 | 0x10000,
+            guard_cf_function_table: 0x1_4000_1000,
+            guard_cf_function_count: 3,
+            guard_long_jump_target_table: 0x1_4000_1020,
+            guard_long_jump_target_count: 1,
+            ..Default::default()
+        };
+        let guard = ControlFlowGuard::parse(&bytes, &headers, &load_config).unwrap().unwrap();
+        assert!(guard.is_instrumented());
+        assert_eq!(guard.stride, 5);
+        assert_eq!(guard.guard_flag_names, vec!["IMAGE_GUARD_CF_INSTRUMENTED", "IMAGE_GUARD_CF_FUNCTION_TABLE_PRESENT", "IMAGE_GUARD_CF_LONGJUMP_TABLE_PRESENT", "IMAGE_GUARD_XFG_ENABLED"]);
+        assert_eq!(guard.functions.len(), 3);
+        assert_eq!(guard.functions[0].xfg_hash, Some(0xfeed_beef));
+        assert_eq!(guard.call_targets().collect::<Vec<u32>>(), vec![0x1100, 0x1130]);
+        assert_eq!(guard.long_jump_targets[0].rva, 0x1140);
+        assert!(ControlFlowGuard::parse(&bytes, &headers, &LoadConfigDirectory::default()).unwrap().is_none());
+    }
+
+    #[test]
+    fn control_flow_guard_truncated_table() {
+        let mut data = vec![0u8; 0x200];
+        // 64-bit load configuration up to GuardFlags, the function count runs the table past the end of the file
+        data.pwrite_with(0x94u32, 0, scroll::LE).unwrap();
+        data.pwrite_with(0x4011f8u64, 128, scroll::LE).unwrap();
+        data.pwrite_with(0x1_0000_0000u64, 136, scroll::LE).unwrap();
+        data.pwrite_with(0x500u32, 144, scroll::LE).unwrap();
+        data.pwrite_with(0x1100u32, 0x1f8, scroll::LE).unwrap();
+        data.pwrite_with(0x1120u32, 0x1fc, scroll::LE).unwrap();
+        let load_config_directory = DataDirectory { virtual_address: 0x1000, size: 0x94 };
+        let pe = PE::new(&test_image(&[(IMAGE_DIRECTORY_ENTRY_LOAD_CONFIG, load_config_directory)], &data)).unwrap();
+        assert_eq!(pe.load_config_directory.unwrap().guard_flags, 0x500);
+        let guard = pe.control_flow_guard.unwrap();
+        assert_eq!(guard.call_targets().collect::<Vec<u32>>(), vec![0x1100, 0x1120]);
+
+        // function table outside of the image
+        data.pwrite_with(0x409000u64, 128, scroll::LE).unwrap();
+        let pe = PE::new(&test_image(&[(IMAGE_DIRECTORY_ENTRY_LOAD_CONFIG, load_config_directory)], &data)).unwrap();
+        let guard = pe.control_flow_guard.unwrap();
+        assert!(guard.functions.is_empty());
+        assert!(guard.is_instrumented());
+    }
+}
@@ -94,6 +94,16 @@ impl PE {
     }
 }
 
+pub const IMAGE_FILE_MACHINE_I386: u16 = 0x14c;
+pub const IMAGE_FILE_MACHINE_ARMNT: u16 = 0x1c4;
+pub const IMAGE_FILE_MACHINE_RISCV32: u16 = 0x5032;
+pub const IMAGE_FILE_MACHINE_RISCV64: u16 = 0x5064;
+pub const IMAGE_FILE_MACHINE_RISCV128: u16 = 0x5128;
+pub const IMAGE_FILE_MACHINE_AMD64: u16 = 0x8664;
+pub const IMAGE_FILE_MACHINE_ARM64EC: u16 = 0xa641;
+pub const IMAGE_FILE_MACHINE_ARM64X: u16 = 0xa64e;
+pub const IMAGE_FILE_MACHINE_ARM64: u16 = 0xaa64;
+
 #[derive(Debug, PartialEq, Copy, Clone, Default, Pread, Pwrite, IOread, IOwrite, SizeWith, Deserialize)]
 pub struct COFF {
     pub machine: u16,
@@ -361,6 +371,19 @@ impl DataDirectory {
 
 pub const MAX_NUMBER_OF_RVA: usize = 16;
 
+pub const IMAGE_DIRECTORY_ENTRY_EXPORT: usize = 0;
+pub const IMAGE_DIRECTORY_ENTRY_IMPORT: usize = 1;
+pub const IMAGE_DIRECTORY_ENTRY_RESOURCE: usize = 2;
+pub const IMAGE_DIRECTORY_ENTRY_EXCEPTION: usize = 3;
+pub const IMAGE_DIRECTORY_ENTRY_BASERELOC: usize = 5;
+pub const IMAGE_DIRECTORY_ENTRY_DEBUG: usize = 6;
+pub const IMAGE_DIRECTORY_ENTRY_TLS: usize = 9;
+pub const IMAGE_DIRECTORY_ENTRY_LOAD_CONFIG: usize = 10;
+pub const IMAGE_DIRECTORY_ENTRY_BOUND_IMPORT: usize = 11;
+pub const IMAGE_DIRECTORY_ENTRY_IAT: usize = 12;
+pub const IMAGE_DIRECTORY_ENTRY_DELAY_IMPORT: usize = 13;
+pub const IMAGE_DIRECTORY_ENTRY_COM_DESCRIPTOR: usize = 14;
+
 #[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
 pub struct DataDirectories {
     pub items: Vec<DataDirectory>,
@@ -382,6 +405,8 @@ impl<'a> scroll::ctx::TryFromCtx<'a, (usize, scroll::Endian)> for DataDirectorie
 pub const OPTIONAL_HEADER_SIGNATURE_32: u16 = 0x10b;
 pub const OPTIONAL_HEADER_SIGNATURE_64: u16 = 0x20b;
 
+pub const IMAGE_DLLCHARACTERISTICS_NO_SEH: u16 = 0x0400;
+
 #[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
 pub struct Optional {
     pub standard_fields: StandardFields,
@@ -520,11 +545,133 @@ impl Headers {
             }
         }
     }
+    /// Returns the data directory at `index`, or `None` if it is absent or empty
+    pub fn get_data_directory(&self, index: usize) -> Option<DataDirectory> {
+        let data_directory:DataDirectory = *self.optional.data_directories.items.get(index)?;
+        if data_directory.virtual_address == 0 {
+            return None
+        }
+        Some(data_directory)
+    }
+    /// Returns the section containing the given RVA
+    pub fn get_section(&self, rva: u32) -> Option<Section> {
+        for section in &self.sections.items {
+            let size = section.virtual_size.max(section.size_of_raw_data);
+            if section.virtual_address <= rva && rva - section.virtual_address < size {
+                return Some(*section)
+            }
+        }
+        None
+    }
+    /// Converts an RVA into a file offset, headers are mapped at the same offset.
+    /// RVAs in the zero filled tail of a section have no file offset
+    pub fn rva_to_offset(&self, rva: u32) -> Option<usize> {
+        match self.get_section(rva) {
+            Some(section) if rva - section.virtual_address >= section.size_of_raw_data => None,
+            Some(section) => section.pointer_to_raw_data.checked_add(rva - section.virtual_address).map(|offset| offset as usize),
+            None if rva < self.optional.specific_fields.size_of_headers => Some(rva as usize),
+            None => None
+        }
+    }
+    /// File offset where the raw data of the last section ends, anything past it is overlay
+    pub fn get_overlay_offset(&self) -> usize {
+        let mut overlay_offset:usize = self.optional.specific_fields.size_of_headers as usize;
+        for section in &self.sections.items {
+            if section.size_of_raw_data != 0 {
+                overlay_offset = overlay_offset.max(section.pointer_to_raw_data as usize + section.size_of_raw_data as usize);
+            }
+        }
+        overlay_offset
+    }
+    /// Reads the null terminated ASCII string located at the given RVA
+    pub fn get_string(&self, bytes: &[u8], rva: u32) -> error::Result<String> {
+        let offset:usize = self.rva_to_offset(rva).ok_or(error::Error::BadRva(rva))?;
+        Ok(bytes.pread::<&str>(offset)?.to_string())
+    }
+}
+
+/// Headers of an image with a single section mapped at file offset 0
+#[cfg(test)]
+pub(crate) fn test_headers(virtual_address: u32, size: u32) -> Headers {
+    Headers {
+        sections: Sections {
+            items: vec![Section {
+                virtual_size: size,
+                virtual_address,
+                size_of_raw_data: size,
+                ..Default::default()
+            }]
+        },
+        ..Default::default()
+    }
+}
+
+/// PE32+ AMD64 image based at 0x400000, with `data` as its single section at RVA 0x1000
+#[cfg(test)]
+pub(crate) fn test_image(data_directories: &[(usize, DataDirectory)], data: &[u8]) -> Vec<u8> {
+    build_test_image(true, data_directories, data)
+}
+
+/// PE32 i386 counterpart of `test_image`
+#[cfg(test)]
+pub(crate) fn test_image_32(data_directories: &[(usize, DataDirectory)], data: &[u8]) -> Vec<u8> {
+    build_test_image(false, data_directories, data)
+}
+
+#[cfg(test)]
+fn build_test_image(is_64: bool, data_directories: &[(usize, DataDirectory)], data: &[u8]) -> Vec<u8> {
+    use scroll::ctx::SizeWith as _;
+
+    let mut bytes:Vec<u8> = vec![0; 0x200];
+    let (machine, signature, specific_fields_size):(u16, u16, usize) = if is_64 {
+        (IMAGE_FILE_MACHINE_AMD64, OPTIONAL_HEADER_SIGNATURE_64, SpecificFields64::size_with(&scroll::LE))
+    } else {
+        (IMAGE_FILE_MACHINE_I386, OPTIONAL_HEADER_SIGNATURE_32, SpecificFields32::size_with(&scroll::LE))
+    };
+    let data_directories_offset:usize = 0x70 + specific_fields_size;
+    bytes.pwrite_with(Dos { signature: DOS_HEADER_SIGNATURE, pe_header_offset: 0x40, ..Default::default() }, 0, scroll::LE).unwrap();
+    bytes.pwrite_with(PE_HEADER_SIGNATURE, 0x40, scroll::LE).unwrap();
+    bytes.pwrite_with(COFF { machine, number_of_section: 1, size_of_optional_header: (data_directories_offset + 16 * 8 - 0x58) as u16, ..Default::default() }, 0x44, scroll::LE).unwrap();
+    bytes.pwrite_with(StandardFields { signature, ..Default::default() }, 0x58, scroll::LE).unwrap();
+    if is_64 {
+        bytes.pwrite_with(SpecificFields64 {
+            image_base: 0x400000,
+            section_alignment: 0x1000,
+            file_alignment: 0x200,
+            size_of_image: 0x1000 + data.len() as u32,
+            size_of_headers: 0x200,
+            number_of_rva_and_sizes: 16,
+            ..Default::default()
+        }, 0x70, scroll::LE).unwrap();
+    } else {
+        bytes.pwrite_with(SpecificFields32 {
+            image_base: 0x400000,
+            section_alignment: 0x1000,
+            file_alignment: 0x200,
+            size_of_image: 0x1000 + data.len() as u32,
+            size_of_headers: 0x200,
+            number_of_rva_and_sizes: 16,
+            ..Default::default()
+        }, 0x70, scroll::LE).unwrap();
+    }
+    for (index, data_directory) in data_directories {
+        bytes.pwrite_with(*data_directory, data_directories_offset + index * 8, scroll::LE).unwrap();
+    }
+    bytes.pwrite_with(Section {
+        virtual_size: data.len() as u32,
+        virtual_address: 0x1000,
+        size_of_raw_data: data.len() as u32,
+        pointer_to_raw_data: 0x200,
+        ..Default::default()
+    }, data_directories_offset + 16 * 8, scroll::LE).unwrap();
+    bytes.extend_from_slice(data);
+    bytes
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{Headers, PE_HEADER_SIGNATURE, DOS_HEADER_SIGNATURE, DOS_HEADER_FILE_ADD_OF_RELOC_TABLE, OPTIONAL_HEADER_SIGNATURE_64};
+    use super::{DataDirectory, Headers, PE_HEADER_SIGNATURE, DOS_HEADER_SIGNATURE, DOS_HEADER_FILE_ADD_OF_RELOC_TABLE, OPTIONAL_HEADER_SIGNATURE_64, IMAGE_DIRECTORY_ENTRY_TLS};
+    use super::{test_headers, test_image, test_image_32, OPTIONAL_HEADER_SIGNATURE_32};
 
     const PE: [u8; 1008] = [
         0x4D, 0x5A, 0x90, 0x00, 0x03, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0xFF, 0xFF, 0x00, 0x00,
@@ -600,4 +747,32 @@ mod tests {
         assert!(header.pe.signature == PE_HEADER_SIGNATURE);
         assert!(header.optional.standard_fields.signature == OPTIONAL_HEADER_SIGNATURE_64);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn image() {
+        let bytes = test_image(&[(IMAGE_DIRECTORY_ENTRY_TLS, DataDirectory { virtual_address: 0x1010, size: 0x28 })], &[0u8; 0x100]);
+        let headers = Headers::parse(&bytes).unwrap();
+        assert_eq!(headers.optional.specific_fields.image_base, 0x400000);
+        assert_eq!(headers.get_data_directory(IMAGE_DIRECTORY_ENTRY_TLS).unwrap().size, 0x28);
+        assert_eq!(headers.rva_to_offset(0x1010), Some(0x210));
+        assert_eq!(headers.get_overlay_offset(), 0x300);
+        let headers = Headers::parse(&test_image_32(&[(IMAGE_DIRECTORY_ENTRY_TLS, DataDirectory { virtual_address: 0x1010, size: 0x18 })], &[0u8; 0x100])).unwrap();
+        assert_eq!(headers.optional.standard_fields.signature, OPTIONAL_HEADER_SIGNATURE_32);
+        assert_eq!(headers.optional.specific_fields.image_base, 0x400000);
+        assert_eq!(headers.get_data_directory(IMAGE_DIRECTORY_ENTRY_TLS).unwrap().size, 0x18);
+        assert_eq!(headers.rva_to_offset(0x1010), Some(0x210));
+    }
+
+    #[test]
+    fn rva_to_offset() {
+        let mut headers = test_headers(0x1000, 0x200);
+        headers.sections.items[0].pointer_to_raw_data = 0x400;
+        headers.sections.items[0].virtual_size = 0x1000;
+        assert_eq!(headers.rva_to_offset(0x1010), Some(0x410));
+        // zero filled tail, mapped in memory but absent from the file
+        assert_eq!(headers.rva_to_offset(0x1200), None);
+        assert_eq!(headers.rva_to_offset(0x3000), None);
+        headers.sections.items[0].pointer_to_raw_data = u32::MAX - 0x10;
+        assert_eq!(headers.rva_to_offset(0x1020), None);
+    }
+}
@@ -0,0 +1,244 @@
+use scroll::{IOread, IOwrite, Pread, Pwrite, SizeWith};
+use serde::{Deserialize, Serialize};
+
+use crate::error;
+use crate::pe::header::Headers;
+use crate::pe::resource::{ResourceDirectory, ResourceId, RT_BITMAP, RT_CURSOR, RT_GROUP_CURSOR, RT_GROUP_ICON, RT_ICON};
+
+pub const ICON_TYPE: u16 = 1;
+pub const CURSOR_TYPE: u16 = 2;
+
+const ICON_DIRECTORY_SIZE: usize = 6;
+const ICON_DIRECTORY_ENTRY_SIZE: usize = 16;
+const BITMAP_FILE_HEADER_SIZE: usize = 14;
+const BITMAP_CORE_HEADER_SIZE: u32 = 12;
+const BITMAP_INFO_HEADER_SIZE: u32 = 40;
+const BI_BITFIELDS: u32 = 3;
+const BI_ALPHABITFIELDS: u32 = 6;
+
+/// GRPICONDIR header shared by RT_GROUP_ICON and RT_GROUP_CURSOR
+#[derive(Debug, PartialEq, Copy, Clone, Default, Pread, Pwrite, IOread, IOwrite, SizeWith, Serialize, Deserialize)]
+pub struct GroupIconDirectory {
+    pub reserved: u16,
+    pub icon_type: u16,
+    pub count: u16,
+}
+
+/// GRPICONDIRENTRY, cursor groups store the width and the doubled height as two u16
+#[derive(Debug, PartialEq, Copy, Clone, Default, Pread, Pwrite, IOread, IOwrite, SizeWith, Serialize, Deserialize)]
+pub struct GroupIconDirectoryEntry {
+    pub width: u8,
+    pub height: u8,
+    pub color_count: u8,
+    pub reserved: u8,
+    pub planes: u16,
+    pub bit_count: u16,
+    pub bytes_in_res: u32,
+    pub id: u16,
+}
+
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct IconGroup {
+    pub directory: GroupIconDirectory,
+    pub entries: Vec<GroupIconDirectoryEntry>,
+}
+
+impl IconGroup {
+    pub fn parse(bytes: &[u8]) -> error::Result<Self> {
+        let mut offset:usize = 0;
+        let directory:GroupIconDirectory = bytes.gread_with(&mut offset, scroll::LE)?;
+        let mut entries:Vec<GroupIconDirectoryEntry> = Vec::new();
+        for _ in 0..directory.count {
+            entries.push(bytes.gread_with(&mut offset, scroll::LE)?);
+        }
+        Ok(IconGroup { directory, entries })
+    }
+    pub fn is_cursor(&self) -> bool {
+        self.directory.icon_type == CURSOR_TYPE
+    }
+    /// Reassembles the group and its RT_ICON/RT_CURSOR images into an .ico or .cur file
+    pub fn build(&self, bytes: &[u8], headers: &Headers, resource_directory: &ResourceDirectory, language: &ResourceId) -> error::Result<Vec<u8>> {
+        let image_type:u16 = if self.is_cursor() { RT_CURSOR } else { RT_ICON };
+        let mut images:Vec<(GroupIconDirectoryEntry, &[u8])> = Vec::new();
+        for entry in &self.entries {
+            let resource = resource_directory.find_resource(image_type, &ResourceId::Id(entry.id), language)
+                .ok_or_else(|| error::Error::Malformed(format!("Missing image {} of icon group", entry.id)))?;
+            images.push((*entry, resource.get_data(bytes, headers)?));
+        }
+
+        let mut file:Vec<u8> = vec![0; ICON_DIRECTORY_SIZE + images.len() * ICON_DIRECTORY_ENTRY_SIZE];
+        let mut offset:usize = 0;
+        file.gwrite_with(0u16, &mut offset, scroll::LE)?;
+        file.gwrite_with(self.directory.icon_type, &mut offset, scroll::LE)?;
+        file.gwrite_with(images.len() as u16, &mut offset, scroll::LE)?;
+        for (entry, image) in images {
+            let image_offset:u32 = file.len() as u32;
+            if self.is_cursor() {
+                // Cursor images start with the hotspot, the group entry holds 16-bit width and doubled height
+                let hotspot_x:u16 = image.pread_with(0, scroll::LE)?;
+                let hotspot_y:u16 = image.pread_with(2, scroll::LE)?;
+                let width:u16 = u16::from(entry.width) | (u16::from(entry.height) << 8);
+                let height:u16 = (u16::from(entry.color_count) | (u16::from(entry.reserved) << 8)) / 2;
+                let image:&[u8] = image.get(4..).unwrap_or(&[]);
+                file.gwrite_with(width as u8, &mut offset, scroll::LE)?;
+                file.gwrite_with(height as u8, &mut offset, scroll::LE)?;
+                file.gwrite_with(0u16, &mut offset, scroll::LE)?;
+                file.gwrite_with(hotspot_x, &mut offset, scroll::LE)?;
+                file.gwrite_with(hotspot_y, &mut offset, scroll::LE)?;
+                file.gwrite_with(image.len() as u32, &mut offset, scroll::LE)?;
+                file.gwrite_with(image_offset, &mut offset, scroll::LE)?;
+                file.extend_from_slice(image);
+            } else {
+                file.gwrite_with(entry.width, &mut offset, scroll::LE)?;
+                file.gwrite_with(entry.height, &mut offset, scroll::LE)?;
+                file.gwrite_with(entry.color_count, &mut offset, scroll::LE)?;
+                file.gwrite_with(0u8, &mut offset, scroll::LE)?;
+                file.gwrite_with(entry.planes, &mut offset, scroll::LE)?;
+                file.gwrite_with(entry.bit_count, &mut offset, scroll::LE)?;
+                file.gwrite_with(image.len() as u32, &mut offset, scroll::LE)?;
+                file.gwrite_with(image_offset, &mut offset, scroll::LE)?;
+                file.extend_from_slice(image);
+            }
+        }
+        Ok(file)
+    }
+}
+
+/// Prepends a BITMAPFILEHEADER to the DIB stored in an RT_BITMAP resource
+pub fn build_bitmap(dib: &[u8]) -> error::Result<Vec<u8>> {
+    let header_size:u32 = dib.pread_with(0, scroll::LE)?;
+    let color_table_size:u32 = if header_size == BITMAP_CORE_HEADER_SIZE {
+        let bit_count:u16 = dib.pread_with(10, scroll::LE)?;
+        if bit_count <= 8 { 3 << bit_count } else { 0 }
+    } else {
+        let bit_count:u16 = dib.pread_with(14, scroll::LE)?;
+        let compression:u32 = dib.pread_with(16, scroll::LE)?;
+        let colors_used:u32 = dib.pread_with(32, scroll::LE)?;
+        let colors:u32 = match colors_used {
+            0 if bit_count <= 8 => 1 << bit_count,
+            _ => colors_used
+        };
+        // Color masks follow a BITMAPINFOHEADER, later headers include them
+        let masks:u32 = match compression {
+            BI_BITFIELDS if header_size == BITMAP_INFO_HEADER_SIZE => 12,
+            BI_ALPHABITFIELDS if header_size == BITMAP_INFO_HEADER_SIZE => 16,
+            _ => 0
+        };
+        colors.saturating_mul(4).saturating_add(masks)
+    };
+    let bits_offset:u32 = (BITMAP_FILE_HEADER_SIZE as u32).checked_add(header_size)
+        .and_then(|offset| offset.checked_add(color_table_size))
+        .ok_or_else(|| error::Error::Malformed(format!("Bitmap color table of {} bytes too large", color_table_size)))?;
+    let mut file:Vec<u8> = vec![0; BITMAP_FILE_HEADER_SIZE];
+    let mut offset:usize = 0;
+    file.gwrite_with(b'B', &mut offset, scroll::LE)?;
+    file.gwrite_with(b'M', &mut offset, scroll::LE)?;
+    file.gwrite_with((BITMAP_FILE_HEADER_SIZE + dib.len()) as u32, &mut offset, scroll::LE)?;
+    file.gwrite_with(0u32, &mut offset, scroll::LE)?;
+    file.gwrite_with(bits_offset, &mut offset, scroll::LE)?;
+    file.extend_from_slice(dib);
+    Ok(file)
+}
+
+/// Standalone file rebuilt from the resource tree
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct ResourceFile {
+    pub name: ResourceId,
+    pub language: ResourceId,
+    /// `ico`, `cur` or `bmp`
+    pub extension: String,
+    pub data: Vec<u8>,
+}
+
+impl ResourceFile {
+    /// Rebuilds every icon group, cursor group and bitmap of the resource tree
+    pub fn extract_all(bytes: &[u8], headers: &Headers, resource_directory: &ResourceDirectory) -> error::Result<Vec<Self>> {
+        let mut files:Vec<ResourceFile> = Vec::new();
+        for (group_type, extension) in [(RT_GROUP_ICON, "ico"), (RT_GROUP_CURSOR, "cur")].iter() {
+            for resource in resource_directory.get_resources(*group_type) {
+                let group:IconGroup = IconGroup::parse(resource.get_data(bytes, headers)?)?;
+                files.push(ResourceFile {
+                    data: group.build(bytes, headers, resource_directory, &resource.language)?,
+                    name: resource.name,
+                    language: resource.language,
+                    extension: extension.to_string()
+                });
+            }
+        }
+        for resource in resource_directory.get_resources(RT_BITMAP) {
+            files.push(ResourceFile {
+                data: build_bitmap(resource.get_data(bytes, headers)?)?,
+                name: resource.name,
+                language: resource.language,
+                extension: "bmp".to_string()
+            });
+        }
+        Ok(files)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use scroll::{Pread, Pwrite};
+
+    use super::{build_bitmap, GroupIconDirectory, GroupIconDirectoryEntry, IconGroup, CURSOR_TYPE};
+    use crate::pe::header::test_headers;
+    use crate::pe::resource::{ResourceContent, ResourceDataEntry, ResourceDirectory, ResourceEntry, ResourceId, RT_CURSOR};
+
+    fn directory(entries: Vec<ResourceEntry>) -> ResourceDirectory {
+        ResourceDirectory { entries, ..Default::default() }
+    }
+
+    #[test]
+    fn cursor() {
+        // Cursor image at RVA 0x1000: hotspot (3, 5) followed by 8 bytes of bitmap
+        let mut bytes = vec![0u8; 0x100];
+        bytes.pwrite_with(3u16, 0, scroll::LE).unwrap();
+        bytes.pwrite_with(5u16, 2, scroll::LE).unwrap();
+        bytes[4..12].copy_from_slice(&[0x28, 0, 0, 0, 0x20, 0, 0, 0]);
+        let headers = test_headers(0x1000, 0x100);
+        let data_entry = ResourceDataEntry { data_rva: 0x1000, size: 12, ..Default::default() };
+        let language = directory(vec![ResourceEntry { id: ResourceId::Id(1033), content: ResourceContent::Data(data_entry) }]);
+        let name = directory(vec![ResourceEntry { id: ResourceId::Id(7), content: ResourceContent::Directory(language) }]);
+        let resource_directory = directory(vec![ResourceEntry { id: ResourceId::Id(RT_CURSOR), content: ResourceContent::Directory(name) }]);
+
+        // 32x32 cursor, the group stores a doubled height of 64
+        let group = IconGroup {
+            directory: GroupIconDirectory { reserved: 0, icon_type: CURSOR_TYPE, count: 1 },
+            entries: vec![GroupIconDirectoryEntry { width: 32, height: 0, color_count: 64, reserved: 0, planes: 1, bit_count: 1, bytes_in_res: 12, id: 7 }]
+        };
+        let file = group.build(&bytes, &headers, &resource_directory, &ResourceId::Id(1033)).unwrap();
+        assert_eq!(file.pread_with::<u16>(2, scroll::LE).unwrap(), CURSOR_TYPE);
+        assert_eq!(file.pread_with::<u16>(4, scroll::LE).unwrap(), 1);
+        assert_eq!(file[6], 32);
+        assert_eq!(file[7], 32);
+        assert_eq!(file.pread_with::<u16>(10, scroll::LE).unwrap(), 3);
+        assert_eq!(file.pread_with::<u16>(12, scroll::LE).unwrap(), 5);
+        assert_eq!(file.pread_with::<u32>(14, scroll::LE).unwrap(), 8);
+        assert_eq!(file.pread_with::<u32>(18, scroll::LE).unwrap(), 22);
+        assert_eq!(&file[22..], &bytes[4..12]);
+    }
+
+    #[test]
+    fn bitmap() {
+        // 8 bits per pixel BITMAPINFOHEADER, 256 color palette
+        let mut dib = vec![0u8; 40 + 256 * 4 + 4];
+        dib.pwrite_with(40u32, 0, scroll::LE).unwrap();
+        dib.pwrite_with(8u16, 14, scroll::LE).unwrap();
+        let file = build_bitmap(&dib).unwrap();
+        assert_eq!(&file[0..2], b"BM");
+        assert_eq!(file.pread_with::<u32>(2, scroll::LE).unwrap() as usize, file.len());
+        assert_eq!(file.pread_with::<u32>(10, scroll::LE).unwrap(), 14 + 40 + 1024);
+    }
+
+    #[test]
+    fn bitmap_too_many_colors() {
+        let mut dib = vec![0u8; 40];
+        dib.pwrite_with(40u32, 0, scroll::LE).unwrap();
+        dib.pwrite_with(0xffff_ffffu32, 32, scroll::LE).unwrap();
+        assert!(build_bitmap(&dib).is_err());
+        dib.pwrite_with(0xffff_fff0u32, 0, scroll::LE).unwrap();
+        dib.pwrite_with(0u32, 32, scroll::LE).unwrap();
+        assert!(build_bitmap(&dib).is_err());
+    }
+}
@@ -31,6 +31,8 @@ lazy_static!{
     0xebc 	EFI Byte Code
     0x8664 	AMD AMD64
     0x9041 	Mitsubishi M32R little endian
+    0xa641 	ARM64EC
+    0xa64e 	ARM64X
     0xaa64 	ARM64 little endian
     0xc0ee 	clr pure MSIL
     */
@@ -61,6 +63,8 @@ lazy_static!{
         (0xebc, "EFI Byte Code"),
         (0x8664, "AMD AMD64"),
         (0x9041, "Mitsubishi M32R little endian"),
+        (0xa641, "ARM64EC"),
+        (0xa64e, "ARM64X"),
         (0xaa64, "ARM64 little endian"),
         (0xc0ee, "clr pure MSIL"),
     ].into_iter().collect();
@@ -319,3 +323,370 @@ lazy_static! {
         (0x80000000, "IMAGE_SCN_MEM_WRITE"),
     ].into_iter().collect();
 }
+
+lazy_static! {
+/** Resource types
+    Constant Name 	        Value 	Description
+    RT_CURSOR 	            1 	    Hardware-dependent cursor resource
+    RT_BITMAP 	            2 	    Bitmap resource
+    RT_ICON 	            3 	    Hardware-dependent icon resource
+    RT_MENU 	            4 	    Menu resource
+    RT_DIALOG 	            5 	    Dialog box
+    RT_STRING 	            6 	    String-table entry
+    RT_FONTDIR 	            7 	    Font directory resource
+    RT_FONT 	            8 	    Font resource
+    RT_ACCELERATOR 	        9 	    Accelerator table
+    RT_RCDATA 	            10 	    Application-defined resource (raw data)
+    RT_MESSAGETABLE 	    11 	    Message-table entry
+    RT_GROUP_CURSOR 	    12 	    Hardware-independent cursor resource
+    RT_GROUP_ICON 	        14 	    Hardware-independent icon resource
+    RT_VERSION 	            16 	    Version resource
+    RT_DLGINCLUDE 	        17 	    Allows a resource editing tool to associate a string with an .rc file
+    RT_PLUGPLAY 	        19 	    Plug and Play resource
+    RT_VXD 	                20 	    VXD
+    RT_ANICURSOR 	        21 	    Animated cursor
+    RT_ANIICON 	            22 	    Animated icon
+    RT_HTML 	            23 	    HTML resource
+    RT_MANIFEST 	        24 	    Side-by-Side Assembly Manifest
+*/
+    pub static ref RESOURCETYPE: HashMap<u16, &'static str> = vec![
+        (1, "RT_CURSOR"),
+        (2, "RT_BITMAP"),
+        (3, "RT_ICON"),
+        (4, "RT_MENU"),
+        (5, "RT_DIALOG"),
+        (6, "RT_STRING"),
+        (7, "RT_FONTDIR"),
+        (8, "RT_FONT"),
+        (9, "RT_ACCELERATOR"),
+        (10, "RT_RCDATA"),
+        (11, "RT_MESSAGETABLE"),
+        (12, "RT_GROUP_CURSOR"),
+        (14, "RT_GROUP_ICON"),
+        (16, "RT_VERSION"),
+        (17, "RT_DLGINCLUDE"),
+        (19, "RT_PLUGPLAY"),
+        (20, "RT_VXD"),
+        (21, "RT_ANICURSOR"),
+        (22, "RT_ANIICON"),
+        (23, "RT_HTML"),
+        (24, "RT_MANIFEST"),
+    ].into_iter().collect();
+}
+
+lazy_static! {
+/** Manifest supportedOS identifiers
+    Id 	                                        Operating system
+    {e2011457-1546-43c5-a5fe-008deee3d3f0} 	    Windows Vista
+    {35138b9a-5d96-4fbd-8e2d-a2440225f93a} 	    Windows 7
+    {4a2f28e3-53b9-4441-ba9c-d69d4a4a6e38} 	    Windows 8
+    {1f676c76-80e1-4239-95bb-83d0f6d0da78} 	    Windows 8.1
+    {8e0f7a12-bfb3-4fe8-b9a5-48fd50a15a9a} 	    Windows 10 and Windows 11
+*/
+    pub static ref SUPPORTEDOS: HashMap<&'static str, &'static str> = vec![
+        ("{e2011457-1546-43c5-a5fe-008deee3d3f0}", "Windows Vista"),
+        ("{35138b9a-5d96-4fbd-8e2d-a2440225f93a}", "Windows 7"),
+        ("{4a2f28e3-53b9-4441-ba9c-d69d4a4a6e38}", "Windows 8"),
+        ("{1f676c76-80e1-4239-95bb-83d0f6d0da78}", "Windows 8.1"),
+        ("{8e0f7a12-bfb3-4fe8-b9a5-48fd50a15a9a}", "Windows 10 and Windows 11"),
+    ].into_iter().collect();
+}
+
+lazy_static! {
+/** Predefined dialog control classes, referenced by atom
+    Atom 	Class
+    0x80 	Button
+    0x81 	Edit
+    0x82 	Static
+    0x83 	ListBox
+    0x84 	ScrollBar
+    0x85 	ComboBox
+*/
+    pub static ref CONTROLCLASS: HashMap<u16, &'static str> = vec![
+        (0x80, "Button"),
+        (0x81, "Edit"),
+        (0x82, "Static"),
+        (0x83, "ListBox"),
+        (0x84, "ScrollBar"),
+        (0x85, "ComboBox"),
+    ].into_iter().collect();
+}
+
+lazy_static! {
+/** x64 general purpose registers, as encoded in unwind codes
+    Value 	Register
+    0 	    RAX
+    1 	    RCX
+    2 	    RDX
+    3 	    RBX
+    4 	    RSP
+    5 	    RBP
+    6 	    RSI
+    7 	    RDI
+    8-15 	R8-R15
+*/
+    pub static ref X64REGISTER: HashMap<u8, &'static str> = vec![
+        (0, "RAX"),
+        (1, "RCX"),
+        (2, "RDX"),
+        (3, "RBX"),
+        (4, "RSP"),
+        (5, "RBP"),
+        (6, "RSI"),
+        (7, "RDI"),
+        (8, "R8"),
+        (9, "R9"),
+        (10, "R10"),
+        (11, "R11"),
+        (12, "R12"),
+        (13, "R13"),
+        (14, "R14"),
+        (15, "R15"),
+    ].into_iter().collect();
+}
+
+lazy_static! {
+/** x64 unwind operations
+    Value 	Operation               Slots
+    0 	    UWOP_PUSH_NONVOL        1
+    1 	    UWOP_ALLOC_LARGE        2 or 3
+    2 	    UWOP_ALLOC_SMALL        1
+    3 	    UWOP_SET_FPREG          1
+    4 	    UWOP_SAVE_NONVOL        2
+    5 	    UWOP_SAVE_NONVOL_FAR    3
+    6 	    UWOP_EPILOG             2 (UWOP_SAVE_XMM in version 1)
+    7 	    UWOP_SPARE_CODE         3 (UWOP_SAVE_XMM_FAR in version 1)
+    8 	    UWOP_SAVE_XMM128        2
+    9 	    UWOP_SAVE_XMM128_FAR    3
+    10 	    UWOP_PUSH_MACHFRAME     1
+*/
+    pub static ref UNWINDOPERATION: HashMap<u8, &'static str> = vec![
+        (0, "UWOP_PUSH_NONVOL"),
+        (1, "UWOP_ALLOC_LARGE"),
+        (2, "UWOP_ALLOC_SMALL"),
+        (3, "UWOP_SET_FPREG"),
+        (4, "UWOP_SAVE_NONVOL"),
+        (5, "UWOP_SAVE_NONVOL_FAR"),
+        (6, "UWOP_EPILOG"),
+        (7, "UWOP_SPARE_CODE"),
+        (8, "UWOP_SAVE_XMM128"),
+        (9, "UWOP_SAVE_XMM128_FAR"),
+        (10, "UWOP_PUSH_MACHFRAME"),
+    ].into_iter().collect();
+}
+
+lazy_static! {
+/** Debug directory types
+    Value 	Type
+    0 	    IMAGE_DEBUG_TYPE_UNKNOWN
+    1 	    IMAGE_DEBUG_TYPE_COFF
+    2 	    IMAGE_DEBUG_TYPE_CODEVIEW
+    3 	    IMAGE_DEBUG_TYPE_FPO
+    4 	    IMAGE_DEBUG_TYPE_MISC
+    5 	    IMAGE_DEBUG_TYPE_EXCEPTION
+    6 	    IMAGE_DEBUG_TYPE_FIXUP
+    7 	    IMAGE_DEBUG_TYPE_OMAP_TO_SRC
+    8 	    IMAGE_DEBUG_TYPE_OMAP_FROM_SRC
+    9 	    IMAGE_DEBUG_TYPE_BORLAND
+    10 	    IMAGE_DEBUG_TYPE_RESERVED10
+    11 	    IMAGE_DEBUG_TYPE_CLSID
+    12 	    IMAGE_DEBUG_TYPE_VC_FEATURE
+    13 	    IMAGE_DEBUG_TYPE_POGO
+    14 	    IMAGE_DEBUG_TYPE_ILTCG
+    15 	    IMAGE_DEBUG_TYPE_MPX
+    16 	    IMAGE_DEBUG_TYPE_REPRO
+    17 	    IMAGE_DEBUG_TYPE_EMBEDDED_PORTABLE_PDB
+    19 	    IMAGE_DEBUG_TYPE_PDBCHECKSUM
+    20 	    IMAGE_DEBUG_TYPE_EX_DLLCHARACTERISTICS
+*/
+    pub static ref DEBUGTYPE: HashMap<u32, &'static str> = vec![
+        (0, "IMAGE_DEBUG_TYPE_UNKNOWN"),
+        (1, "IMAGE_DEBUG_TYPE_COFF"),
+        (2, "IMAGE_DEBUG_TYPE_CODEVIEW"),
+        (3, "IMAGE_DEBUG_TYPE_FPO"),
+        (4, "IMAGE_DEBUG_TYPE_MISC"),
+        (5, "IMAGE_DEBUG_TYPE_EXCEPTION"),
+        (6, "IMAGE_DEBUG_TYPE_FIXUP"),
+        (7, "IMAGE_DEBUG_TYPE_OMAP_TO_SRC"),
+        (8, "IMAGE_DEBUG_TYPE_OMAP_FROM_SRC"),
+        (9, "IMAGE_DEBUG_TYPE_BORLAND"),
+        (10, "IMAGE_DEBUG_TYPE_RESERVED10"),
+        (11, "IMAGE_DEBUG_TYPE_CLSID"),
+        (12, "IMAGE_DEBUG_TYPE_VC_FEATURE"),
+        (13, "IMAGE_DEBUG_TYPE_POGO"),
+        (14, "IMAGE_DEBUG_TYPE_ILTCG"),
+        (15, "IMAGE_DEBUG_TYPE_MPX"),
+        (16, "IMAGE_DEBUG_TYPE_REPRO"),
+        (17, "IMAGE_DEBUG_TYPE_EMBEDDED_PORTABLE_PDB"),
+        (19, "IMAGE_DEBUG_TYPE_PDBCHECKSUM"),
+        (20, "IMAGE_DEBUG_TYPE_EX_DLLCHARACTERISTICS"),
+    ].into_iter().collect();
+}
+
+lazy_static! {
+/** Extended DLL characteristics, stored in an IMAGE_DEBUG_TYPE_EX_DLLCHARACTERISTICS debug entry
+    Constant Name 	                                                    Value 	Description
+    IMAGE_DLLCHARACTERISTICS_EX_CET_COMPAT 	                            0x0001 	The image is compatible with CET shadow stacks
+    IMAGE_DLLCHARACTERISTICS_EX_CET_COMPAT_STRICT_MODE 	                0x0002 	Shadow stacks are enforced in strict mode
+    IMAGE_DLLCHARACTERISTICS_EX_CET_SET_CONTEXT_IP_VALIDATION_RELAXED_MODE 0x0004 	Relaxed context IP validation
+    IMAGE_DLLCHARACTERISTICS_EX_CET_DYNAMIC_APIS_ALLOW_IN_PROC 	        0x0008 	Dynamic CET APIs may be called in process
+    IMAGE_DLLCHARACTERISTICS_EX_CET_RESERVED_1 	                        0x0010 	Reserved
+    IMAGE_DLLCHARACTERISTICS_EX_CET_RESERVED_2 	                        0x0020 	Reserved
+    IMAGE_DLLCHARACTERISTICS_EX_FORWARD_CFI_COMPAT 	                    0x0040 	The image is compatible with forward control flow integrity
+    IMAGE_DLLCHARACTERISTICS_EX_HOTPATCH_COMPATIBLE 	                0x0080 	The image can be hotpatched
+*/
+    pub static ref EXDLLCHARACTERISTIC: HashMap<u32, &'static str> = vec![
+        (0x0001, "IMAGE_DLLCHARACTERISTICS_EX_CET_COMPAT"),
+        (0x0002, "IMAGE_DLLCHARACTERISTICS_EX_CET_COMPAT_STRICT_MODE"),
+        (0x0004, "IMAGE_DLLCHARACTERISTICS_EX_CET_SET_CONTEXT_IP_VALIDATION_RELAXED_MODE"),
+        (0x0008, "IMAGE_DLLCHARACTERISTICS_EX_CET_DYNAMIC_APIS_ALLOW_IN_PROC"),
+        (0x0010, "IMAGE_DLLCHARACTERISTICS_EX_CET_RESERVED_1"),
+        (0x0020, "IMAGE_DLLCHARACTERISTICS_EX_CET_RESERVED_2"),
+        (0x0040, "IMAGE_DLLCHARACTERISTICS_EX_FORWARD_CFI_COMPAT"),
+        (0x0080, "IMAGE_DLLCHARACTERISTICS_EX_HOTPATCH_COMPATIBLE"),
+    ].into_iter().collect();
+}
+
+lazy_static! {
+/** Load configuration GuardFlags, the upper nibble holds the size of the metadata following each table entry
+    Constant Name 	                                    Value 	    Description
+    IMAGE_GUARD_CF_INSTRUMENTED 	                    0x00000100 	Module performs control flow integrity checks
+    IMAGE_GUARD_CFW_INSTRUMENTED 	                    0x00000200 	Module performs control flow and write integrity checks
+    IMAGE_GUARD_CF_FUNCTION_TABLE_PRESENT 	            0x00000400 	Module contains valid control flow target metadata
+    IMAGE_GUARD_SECURITY_COOKIE_UNUSED 	                0x00000800 	Module does not make use of the /GS security cookie
+    IMAGE_GUARD_PROTECT_DELAYLOAD_IAT 	                0x00001000 	Module supports read only delay load IAT
+    IMAGE_GUARD_DELAYLOAD_IAT_IN_ITS_OWN_SECTION 	    0x00002000 	Delayload import table in its own .didat section
+    IMAGE_GUARD_CF_EXPORT_SUPPRESSION_INFO_PRESENT 	    0x00004000 	Module contains suppressed export information
+    IMAGE_GUARD_CF_ENABLE_EXPORT_SUPPRESSION 	        0x00008000 	Module enables suppression of exports
+    IMAGE_GUARD_CF_LONGJUMP_TABLE_PRESENT 	            0x00010000 	Module contains longjmp target information
+    IMAGE_GUARD_RF_INSTRUMENTED 	                    0x00020000 	Module contains return flow instrumentation and metadata
+    IMAGE_GUARD_RF_ENABLE 	                            0x00040000 	Module requests that the OS enable return flow protection
+    IMAGE_GUARD_RF_STRICT 	                            0x00080000 	Module requests that the OS enable return flow protection in strict mode
+    IMAGE_GUARD_RETPOLINE_PRESENT 	                    0x00100000 	Module was built with retpoline support
+    IMAGE_GUARD_EH_CONTINUATION_TABLE_PRESENT 	        0x00400000 	Module contains EH continuation target information
+    IMAGE_GUARD_XFG_ENABLED 	                        0x00800000 	Module was built with xfg
+    IMAGE_GUARD_CASTGUARD_PRESENT 	                    0x01000000 	Module has CastGuard instrumentation present
+    IMAGE_GUARD_MEMCPY_PRESENT 	                        0x02000000 	Module has Guarded Memcpy instrumentation present
+*/
+    pub static ref GUARDFLAG: HashMap<u32, &'static str> = vec![
+        (0x00000100, "IMAGE_GUARD_CF_INSTRUMENTED"),
+        (0x00000200, "IMAGE_GUARD_CFW_INSTRUMENTED"),
+        (0x00000400, "IMAGE_GUARD_CF_FUNCTION_TABLE_PRESENT"),
+        (0x00000800, "IMAGE_GUARD_SECURITY_COOKIE_UNUSED"),
+        (0x00001000, "IMAGE_GUARD_PROTECT_DELAYLOAD_IAT"),
+        (0x00002000, "IMAGE_GUARD_DELAYLOAD_IAT_IN_ITS_OWN_SECTION"),
+        (0x00004000, "IMAGE_GUARD_CF_EXPORT_SUPPRESSION_INFO_PRESENT"),
+        (0x00008000, "IMAGE_GUARD_CF_ENABLE_EXPORT_SUPPRESSION"),
+        (0x00010000, "IMAGE_GUARD_CF_LONGJUMP_TABLE_PRESENT"),
+        (0x00020000, "IMAGE_GUARD_RF_INSTRUMENTED"),
+        (0x00040000, "IMAGE_GUARD_RF_ENABLE"),
+        (0x00080000, "IMAGE_GUARD_RF_STRICT"),
+        (0x00100000, "IMAGE_GUARD_RETPOLINE_PRESENT"),
+        (0x00400000, "IMAGE_GUARD_EH_CONTINUATION_TABLE_PRESENT"),
+        (0x00800000, "IMAGE_GUARD_XFG_ENABLED"),
+        (0x01000000, "IMAGE_GUARD_CASTGUARD_PRESENT"),
+        (0x02000000, "IMAGE_GUARD_MEMCPY_PRESENT"),
+    ].into_iter().collect();
+}
+
+lazy_static! {
+/** CLR runtime header flags
+    Constant Name 	                        Value 	    Description
+    COMIMAGE_FLAGS_ILONLY 	                0x00000001 	Image contains only IL code
+    COMIMAGE_FLAGS_32BITREQUIRED 	        0x00000002 	Image can only be loaded into a 32-bit process
+    COMIMAGE_FLAGS_IL_LIBRARY 	            0x00000004 	Image is an IL library
+    COMIMAGE_FLAGS_STRONGNAMESIGNED 	    0x00000008 	Image has a strong name signature
+    COMIMAGE_FLAGS_NATIVE_ENTRYPOINT 	    0x00000010 	Entry point is an RVA of native code instead of a token
+    COMIMAGE_FLAGS_TRACKDEBUGDATA 	        0x00010000 	Runtime tracks debug data
+    COMIMAGE_FLAGS_32BITPREFERRED 	        0x00020000 	Image prefers to run in a 32-bit process
+*/
+    pub static ref COMIMAGEFLAG: HashMap<u32, &'static str> = vec![
+        (0x00000001, "COMIMAGE_FLAGS_ILONLY"),
+        (0x00000002, "COMIMAGE_FLAGS_32BITREQUIRED"),
+        (0x00000004, "COMIMAGE_FLAGS_IL_LIBRARY"),
+        (0x00000008, "COMIMAGE_FLAGS_STRONGNAMESIGNED"),
+        (0x00000010, "COMIMAGE_FLAGS_NATIVE_ENTRYPOINT"),
+        (0x00010000, "COMIMAGE_FLAGS_TRACKDEBUGDATA"),
+        (0x00020000, "COMIMAGE_FLAGS_32BITPREFERRED"),
+    ].into_iter().collect();
+}
+
+lazy_static! {
+/** ReadyToRun section types
+    Value 	Name
+    100 	CompilerIdentifier
+    101 	ImportSections
+    102 	RuntimeFunctions
+    103 	MethodDefEntryPoints
+    104 	ExceptionInfo
+    105 	DebugInfo
+    106 	DelayLoadMethodCallThunks
+    107 	AvailableTypes (obsolete)
+    108 	AvailableTypes
+    109 	InstanceMethodEntryPoints
+    110 	InliningInfo
+    111 	ProfileDataInfo
+    112 	ManifestMetadata
+    113 	AttributePresence
+    114 	InliningInfo2
+    115 	ComponentAssemblies
+    116 	OwnerCompositeExecutable
+    117 	PgoInstrumentationData
+    118 	ManifestAssemblyMvids
+    119 	CrossModuleInlineInfo
+    120 	HotColdMap
+    121 	MethodIsGenericMap
+    122 	EnclosingTypeMap
+    123 	TypeGenericInfoMap
+*/
+    pub static ref READYTORUNSECTION: HashMap<u32, &'static str> = vec![
+        (100, "CompilerIdentifier"),
+        (101, "ImportSections"),
+        (102, "RuntimeFunctions"),
+        (103, "MethodDefEntryPoints"),
+        (104, "ExceptionInfo"),
+        (105, "DebugInfo"),
+        (106, "DelayLoadMethodCallThunks"),
+        (107, "AvailableTypes (obsolete)"),
+        (108, "AvailableTypes"),
+        (109, "InstanceMethodEntryPoints"),
+        (110, "InliningInfo"),
+        (111, "ProfileDataInfo"),
+        (112, "ManifestMetadata"),
+        (113, "AttributePresence"),
+        (114, "InliningInfo2"),
+        (115, "ComponentAssemblies"),
+        (116, "OwnerCompositeExecutable"),
+        (117, "PgoInstrumentationData"),
+        (118, "ManifestAssemblyMvids"),
+        (119, "CrossModuleInlineInfo"),
+        (120, "HotColdMap"),
+        (121, "MethodIsGenericMap"),
+        (122, "EnclosingTypeMap"),
+        (123, "TypeGenericInfoMap"),
+    ].into_iter().collect();
+}
+
+lazy_static! {
+/** ReadyToRun header flags
+    Constant Name 	                                    Value 	    Description
+    READYTORUN_FLAG_PLATFORM_NEUTRAL_SOURCE 	        0x00000001 	Set if the original IL assembly was platform-neutral
+    READYTORUN_FLAG_SKIP_TYPE_VALIDATION 	            0x00000002 	Set if the runtime should skip type layout validation
+    READYTORUN_FLAG_PARTIAL 	                        0x00000004 	Set if not all methods were compiled
+    READYTORUN_FLAG_NONSHARED_PINVOKE_STUBS 	        0x00000008 	PInvoke stubs compiled into the image are non-shareable
+    READYTORUN_FLAG_EMBEDDED_MSIL 	                    0x00000010 	Input MSIL is embedded in the R2R image
+    READYTORUN_FLAG_COMPONENT 	                        0x00000020 	Component assembly of a composite image
+    READYTORUN_FLAG_MULTIMODULE_VERSION_BUBBLE 	        0x00000040 	Image was compiled with a larger version bubble
+    READYTORUN_FLAG_UNRELATED_R2R_CODE 	                0x00000080 	Image contains code for methods outside its version bubble
+*/
+    pub static ref READYTORUNFLAG: HashMap<u32, &'static str> = vec![
+        (0x00000001, "READYTORUN_FLAG_PLATFORM_NEUTRAL_SOURCE"),
+        (0x00000002, "READYTORUN_FLAG_SKIP_TYPE_VALIDATION"),
+        (0x00000004, "READYTORUN_FLAG_PARTIAL"),
+        (0x00000008, "READYTORUN_FLAG_NONSHARED_PINVOKE_STUBS"),
+        (0x00000010, "READYTORUN_FLAG_EMBEDDED_MSIL"),
+        (0x00000020, "READYTORUN_FLAG_COMPONENT"),
+        (0x00000040, "READYTORUN_FLAG_MULTIMODULE_VERSION_BUBBLE"),
+        (0x00000080, "READYTORUN_FLAG_UNRELATED_R2R_CODE"),
+    ].into_iter().collect();
+}
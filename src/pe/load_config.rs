@@ -0,0 +1,295 @@
+use scroll::{IOread, IOwrite, Pread, Pwrite, SizeWith};
+use serde::{Deserialize, Serialize, Serializer};
+use serde::ser::SerializeStruct;
+
+use crate::error;
+use crate::pe::header::{DataDirectory, Headers, OPTIONAL_HEADER_SIGNATURE_64};
+
+#[derive(Debug, PartialEq, Copy, Clone, Default, Pread, Pwrite, IOread, IOwrite, SizeWith, Serialize, Deserialize)]
+pub struct LoadConfigCodeIntegrity {
+    pub flags: u16,
+    pub catalog: u16,
+    pub catalog_offset: u32,
+    pub reserved: u32,
+}
+
+/// IMAGE_LOAD_CONFIG_DIRECTORY32/64, fields past the declared `size` are left to zero
+#[derive(Debug, PartialEq, Copy, Clone, Default, Deserialize)]
+pub struct LoadConfigDirectory {
+    pub size: u32,
+    pub time_date_stamp: u32,
+    pub major_version: u16,
+    pub minor_version: u16,
+    pub global_flags_clear: u32,
+    pub global_flags_set: u32,
+    pub critical_section_default_timeout: u32,
+    pub de_commit_free_block_threshold: u64,
+    pub de_commit_total_free_threshold: u64,
+    pub lock_prefix_table: u64,
+    pub maximum_allocation_size: u64,
+    pub virtual_memory_threshold: u64,
+    pub process_affinity_mask: u64,
+    pub process_heap_flags: u32,
+    pub csd_version: u16,
+    pub dependent_load_flags: u16,
+    pub edit_list: u64,
+    pub security_cookie: u64,
+    pub se_handler_table: u64,
+    pub se_handler_count: u64,
+    pub guard_cf_check_function_pointer: u64,
+    pub guard_cf_dispatch_function_pointer: u64,
+    pub guard_cf_function_table: u64,
+    pub guard_cf_function_count: u64,
+    pub guard_flags: u32,
+    pub code_integrity: LoadConfigCodeIntegrity,
+    pub guard_address_taken_iat_entry_table: u64,
+    pub guard_address_taken_iat_entry_count: u64,
+    pub guard_long_jump_target_table: u64,
+    pub guard_long_jump_target_count: u64,
+    pub dynamic_value_reloc_table: u64,
+    pub chpe_metadata_pointer: u64,
+    pub guard_rf_failure_routine: u64,
+    pub guard_rf_failure_routine_function_pointer: u64,
+    pub dynamic_value_reloc_table_offset: u32,
+    pub dynamic_value_reloc_table_section: u16,
+    pub reserved2: u16,
+    pub guard_rf_verify_stack_pointer_function_pointer: u64,
+    pub hot_patch_table_offset: u32,
+    pub reserved3: u32,
+    pub enclave_configuration_pointer: u64,
+    pub volatile_metadata_pointer: u64,
+    pub guard_eh_continuation_table: u64,
+    pub guard_eh_continuation_count: u64,
+    pub guard_xfg_check_function_pointer: u64,
+    pub guard_xfg_dispatch_function_pointer: u64,
+    pub guard_xfg_table_dispatch_function_pointer: u64,
+    pub cast_guard_os_determined_failure_mode: u64,
+    pub guard_memcpy_function_pointer: u64,
+}
+
+impl Serialize for LoadConfigDirectory {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("LoadConfigDirectory", 49)?;
+        state.serialize_field("size", &self.size)?;
+        state.serialize_field("time_date_stamp", &format!("0x{:x}", &self.time_date_stamp))?;
+        state.serialize_field("major_version", &self.major_version)?;
+        state.serialize_field("minor_version", &self.minor_version)?;
+        state.serialize_field("global_flags_clear", &format!("0x{:x}", &self.global_flags_clear))?;
+        state.serialize_field("global_flags_set", &format!("0x{:x}", &self.global_flags_set))?;
+        state.serialize_field("critical_section_default_timeout", &self.critical_section_default_timeout)?;
+        state.serialize_field("de_commit_free_block_threshold", &format!("0x{:x}", &self.de_commit_free_block_threshold))?;
+        state.serialize_field("de_commit_total_free_threshold", &format!("0x{:x}", &self.de_commit_total_free_threshold))?;
+        state.serialize_field("lock_prefix_table", &format!("0x{:x}", &self.lock_prefix_table))?;
+        state.serialize_field("maximum_allocation_size", &format!("0x{:x}", &self.maximum_allocation_size))?;
+        state.serialize_field("virtual_memory_threshold", &format!("0x{:x}", &self.virtual_memory_threshold))?;
+        state.serialize_field("process_affinity_mask", &format!("0x{:x}", &self.process_affinity_mask))?;
+        state.serialize_field("process_heap_flags", &format!("0x{:x}", &self.process_heap_flags))?;
+        state.serialize_field("csd_version", &self.csd_version)?;
+        state.serialize_field("dependent_load_flags", &format!("0x{:x}", &self.dependent_load_flags))?;
+        state.serialize_field("edit_list", &format!("0x{:x}", &self.edit_list))?;
+        state.serialize_field("security_cookie", &format!("0x{:x}", &self.security_cookie))?;
+        state.serialize_field("se_handler_table", &format!("0x{:x}", &self.se_handler_table))?;
+        state.serialize_field("se_handler_count", &self.se_handler_count)?;
+        state.serialize_field("guard_cf_check_function_pointer", &format!("0x{:x}", &self.guard_cf_check_function_pointer))?;
+        state.serialize_field("guard_cf_dispatch_function_pointer", &format!("0x{:x}", &self.guard_cf_dispatch_function_pointer))?;
+        state.serialize_field("guard_cf_function_table", &format!("0x{:x}", &self.guard_cf_function_table))?;
+        state.serialize_field("guard_cf_function_count", &self.guard_cf_function_count)?;
+        state.serialize_field("guard_flags", &format!("0x{:x}", &self.guard_flags))?;
+        state.serialize_field("code_integrity", &self.code_integrity)?;
+        state.serialize_field("guard_address_taken_iat_entry_table", &format!("0x{:x}", &self.guard_address_taken_iat_entry_table))?;
+        state.serialize_field("guard_address_taken_iat_entry_count", &self.guard_address_taken_iat_entry_count)?;
+        state.serialize_field("guard_long_jump_target_table", &format!("0x{:x}", &self.guard_long_jump_target_table))?;
+        state.serialize_field("guard_long_jump_target_count", &self.guard_long_jump_target_count)?;
+        state.serialize_field("dynamic_value_reloc_table", &format!("0x{:x}", &self.dynamic_value_reloc_table))?;
+        state.serialize_field("chpe_metadata_pointer", &format!("0x{:x}", &self.chpe_metadata_pointer))?;
+        state.serialize_field("guard_rf_failure_routine", &format!("0x{:x}", &self.guard_rf_failure_routine))?;
+        state.serialize_field("guard_rf_failure_routine_function_pointer", &format!("0x{:x}", &self.guard_rf_failure_routine_function_pointer))?;
+        state.serialize_field("dynamic_value_reloc_table_offset", &format!("0x{:x}", &self.dynamic_value_reloc_table_offset))?;
+        state.serialize_field("dynamic_value_reloc_table_section", &self.dynamic_value_reloc_table_section)?;
+        state.serialize_field("reserved2", &self.reserved2)?;
+        state.serialize_field("guard_rf_verify_stack_pointer_function_pointer", &format!("0x{:x}", &self.guard_rf_verify_stack_pointer_function_pointer))?;
+        state.serialize_field("hot_patch_table_offset", &format!("0x{:x}", &self.hot_patch_table_offset))?;
+        state.serialize_field("reserved3", &self.reserved3)?;
+        state.serialize_field("enclave_configuration_pointer", &format!("0x{:x}", &self.enclave_configuration_pointer))?;
+        state.serialize_field("volatile_metadata_pointer", &format!("0x{:x}", &self.volatile_metadata_pointer))?;
+        state.serialize_field("guard_eh_continuation_table", &format!("0x{:x}", &self.guard_eh_continuation_table))?;
+        state.serialize_field("guard_eh_continuation_count", &self.guard_eh_continuation_count)?;
+        state.serialize_field("guard_xfg_check_function_pointer", &format!("0x{:x}", &self.guard_xfg_check_function_pointer))?;
+        state.serialize_field("guard_xfg_dispatch_function_pointer", &format!("0x{:x}", &self.guard_xfg_dispatch_function_pointer))?;
+        state.serialize_field("guard_xfg_table_dispatch_function_pointer", &format!("0x{:x}", &self.guard_xfg_table_dispatch_function_pointer))?;
+        state.serialize_field("cast_guard_os_determined_failure_mode", &format!("0x{:x}", &self.cast_guard_os_determined_failure_mode))?;
+        state.serialize_field("guard_memcpy_function_pointer", &format!("0x{:x}", &self.guard_memcpy_function_pointer))?;
+        state.end()
+    }
+}
+
+/// Sequential reader that yields zero for every field starting past the declared size
+struct LoadConfigReader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+    is_64: bool,
+}
+
+impl<'a> LoadConfigReader<'a> {
+    fn read<T: scroll::ctx::TryFromCtx<'a, scroll::Endian, Error = scroll::Error> + Default>(&mut self) -> T {
+        match self.bytes.gread_with(&mut self.offset, scroll::LE) {
+            Ok(value) => value,
+            Err(_) => {
+                // A field cut by the declared size ends the structure
+                self.offset = self.bytes.len();
+                T::default()
+            }
+        }
+    }
+    fn read_pointer(&mut self) -> u64 {
+        if self.is_64 { self.read::<u64>() } else { u64::from(self.read::<u32>()) }
+    }
+}
+
+impl LoadConfigDirectory {
+    pub fn parse(bytes: &[u8], headers: &Headers, data_directory: DataDirectory) -> error::Result<Self> {
+        let offset:usize = headers.rva_to_offset(data_directory.virtual_address).ok_or(error::Error::BadRva(data_directory.virtual_address))?;
+        let size:u32 = bytes.pread_with(offset, scroll::LE)?;
+        let end:usize = (offset + size as usize).min(bytes.len());
+        let is_64:bool = headers.optional.standard_fields.signature == OPTIONAL_HEADER_SIGNATURE_64;
+        let mut reader:LoadConfigReader = LoadConfigReader { bytes: &bytes[offset..end.max(offset)], offset: 4, is_64 };
+
+        let mut load_config:LoadConfigDirectory = LoadConfigDirectory {
+            size,
+            time_date_stamp: reader.read(),
+            major_version: reader.read(),
+            minor_version: reader.read(),
+            global_flags_clear: reader.read(),
+            global_flags_set: reader.read(),
+            critical_section_default_timeout: reader.read(),
+            de_commit_free_block_threshold: reader.read_pointer(),
+            de_commit_total_free_threshold: reader.read_pointer(),
+            lock_prefix_table: reader.read_pointer(),
+            maximum_allocation_size: reader.read_pointer(),
+            virtual_memory_threshold: reader.read_pointer(),
+            ..Default::default()
+        };
+        // The affinity mask and heap flags are swapped between the two layouts
+        if is_64 {
+            load_config.process_affinity_mask = reader.read();
+            load_config.process_heap_flags = reader.read();
+        } else {
+            load_config.process_heap_flags = reader.read();
+            load_config.process_affinity_mask = u64::from(reader.read::<u32>());
+        }
+        load_config.csd_version = reader.read();
+        load_config.dependent_load_flags = reader.read();
+        load_config.edit_list = reader.read_pointer();
+        load_config.security_cookie = reader.read_pointer();
+        load_config.se_handler_table = reader.read_pointer();
+        load_config.se_handler_count = reader.read_pointer();
+        load_config.guard_cf_check_function_pointer = reader.read_pointer();
+        load_config.guard_cf_dispatch_function_pointer = reader.read_pointer();
+        load_config.guard_cf_function_table = reader.read_pointer();
+        load_config.guard_cf_function_count = reader.read_pointer();
+        load_config.guard_flags = reader.read();
+        load_config.code_integrity = reader.read();
+        load_config.guard_address_taken_iat_entry_table = reader.read_pointer();
+        load_config.guard_address_taken_iat_entry_count = reader.read_pointer();
+        load_config.guard_long_jump_target_table = reader.read_pointer();
+        load_config.guard_long_jump_target_count = reader.read_pointer();
+        load_config.dynamic_value_reloc_table = reader.read_pointer();
+        load_config.chpe_metadata_pointer = reader.read_pointer();
+        load_config.guard_rf_failure_routine = reader.read_pointer();
+        load_config.guard_rf_failure_routine_function_pointer = reader.read_pointer();
+        load_config.dynamic_value_reloc_table_offset = reader.read();
+        load_config.dynamic_value_reloc_table_section = reader.read();
+        load_config.reserved2 = reader.read();
+        load_config.guard_rf_verify_stack_pointer_function_pointer = reader.read_pointer();
+        load_config.hot_patch_table_offset = reader.read();
+        load_config.reserved3 = reader.read();
+        load_config.enclave_configuration_pointer = reader.read_pointer();
+        load_config.volatile_metadata_pointer = reader.read_pointer();
+        load_config.guard_eh_continuation_table = reader.read_pointer();
+        load_config.guard_eh_continuation_count = reader.read_pointer();
+        load_config.guard_xfg_check_function_pointer = reader.read_pointer();
+        load_config.guard_xfg_dispatch_function_pointer = reader.read_pointer();
+        load_config.guard_xfg_table_dispatch_function_pointer = reader.read_pointer();
+        load_config.cast_guard_os_determined_failure_mode = reader.read_pointer();
+        load_config.guard_memcpy_function_pointer = reader.read_pointer();
+        Ok(load_config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use scroll::Pwrite;
+
+    use super::LoadConfigDirectory;
+    use crate::pe::header::{DataDirectory, Headers, IMAGE_DIRECTORY_ENTRY_LOAD_CONFIG, OPTIONAL_HEADER_SIGNATURE_32, OPTIONAL_HEADER_SIGNATURE_64, test_headers, test_image};
+    use crate::pe::pe::PE;
+
+    fn headers(signature: u16) -> Headers {
+        let mut headers = test_headers(0x1000, 0x200);
+        headers.optional.standard_fields.signature = signature;
+        headers
+    }
+
+    #[test]
+    fn load_config_64() {
+        let mut bytes = vec![0u8; 0x200];
+        bytes.pwrite_with(0x94u32, 0, scroll::LE).unwrap();
+        bytes.pwrite_with(0x1400_3000u64, 88, scroll::LE).unwrap();
+        bytes.pwrite_with(0x1400_2000u64, 128, scroll::LE).unwrap();
+        bytes.pwrite_with(12u64, 136, scroll::LE).unwrap();
+        bytes.pwrite_with(0x10500u32, 144, scroll::LE).unwrap();
+        // Past the declared size, must be ignored
+        bytes.pwrite_with(0xffffu16, 148, scroll::LE).unwrap();
+        let load_config = LoadConfigDirectory::parse(&bytes, &headers(OPTIONAL_HEADER_SIGNATURE_64), DataDirectory { virtual_address: 0x1000, size: 0x140 }).unwrap();
+        assert_eq!(load_config.security_cookie, 0x1400_3000);
+        assert_eq!(load_config.guard_cf_function_table, 0x1400_2000);
+        assert_eq!(load_config.guard_cf_function_count, 12);
+        assert_eq!(load_config.guard_flags, 0x10500);
+        assert_eq!(load_config.code_integrity.flags, 0);
+    }
+
+    #[test]
+    fn load_config_32() {
+        let mut bytes = vec![0u8; 0x200];
+        bytes.pwrite_with(0x48u32, 0, scroll::LE).unwrap();
+        bytes.pwrite_with(0x40u32, 44, scroll::LE).unwrap();
+        bytes.pwrite_with(0xffu32, 48, scroll::LE).unwrap();
+        bytes.pwrite_with(0x403000u32, 60, scroll::LE).unwrap();
+        bytes.pwrite_with(0x402000u32, 64, scroll::LE).unwrap();
+        bytes.pwrite_with(3u32, 68, scroll::LE).unwrap();
+        bytes.pwrite_with(0x401000u32, 72, scroll::LE).unwrap();
+        let load_config = LoadConfigDirectory::parse(&bytes, &headers(OPTIONAL_HEADER_SIGNATURE_32), DataDirectory { virtual_address: 0x1000, size: 0x40 }).unwrap();
+        assert_eq!(load_config.process_heap_flags, 0x40);
+        assert_eq!(load_config.process_affinity_mask, 0xff);
+        assert_eq!(load_config.security_cookie, 0x403000);
+        assert_eq!(load_config.se_handler_table, 0x402000);
+        assert_eq!(load_config.se_handler_count, 3);
+        assert_eq!(load_config.guard_cf_check_function_pointer, 0);
+    }
+
+    #[test]
+    fn load_config_bad_size() {
+        // declared size running past the end of the file, the structure is cut after the virtual memory threshold
+        let mut data = vec![0u8; 0x200];
+        data.pwrite_with(0xffff_ffffu32, 0x1c0, scroll::LE).unwrap();
+        data.pwrite_with(0x1234u32, 0x1c4, scroll::LE).unwrap();
+        data.pwrite_with(0x1000u64, 0x1f8, scroll::LE).unwrap();
+        let bytes = test_image(&[(IMAGE_DIRECTORY_ENTRY_LOAD_CONFIG, DataDirectory { virtual_address: 0x11c0, size: 0x40 })], &data);
+        let pe = PE::new(&bytes).unwrap();
+        let load_config = pe.load_config_directory.unwrap();
+        assert_eq!(load_config.time_date_stamp, 0x1234);
+        assert_eq!(load_config.virtual_memory_threshold, 0x1000);
+        assert_eq!(load_config.process_affinity_mask, 0);
+        assert_eq!(load_config.security_cookie, 0);
+        assert_eq!(load_config.guard_flags, 0);
+
+        // declared size smaller than the size field itself
+        data.pwrite_with(2u32, 0x1c0, scroll::LE).unwrap();
+        let load_config = LoadConfigDirectory::parse(&data, &headers(OPTIONAL_HEADER_SIGNATURE_64), DataDirectory { virtual_address: 0x11c0, size: 0x40 }).unwrap();
+        assert_eq!(load_config.size, 2);
+        assert_eq!(load_config.time_date_stamp, 0);
+    }
+}
@@ -0,0 +1,290 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error;
+use crate::pe::header::Headers;
+use crate::pe::index;
+use crate::pe::resource::{ResourceDirectory, RT_MANIFEST};
+
+/// `assemblyIdentity` element of the manifest or of one of its dependencies
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct AssemblyIdentity {
+    pub name: Option<String>,
+    pub version: Option<String>,
+    pub assembly_type: Option<String>,
+    pub processor_architecture: Option<String>,
+    pub public_key_token: Option<String>,
+    pub language: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct SupportedOs {
+    pub id: String,
+    /// Windows version matching the GUID, `None` for unknown identifiers
+    pub name: Option<String>,
+}
+
+/// Summary of the settings found in an application manifest
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct Manifest {
+    pub assembly_identity: Option<AssemblyIdentity>,
+    pub requested_execution_level: Option<String>,
+    pub ui_access: Option<bool>,
+    pub dpi_aware: Option<String>,
+    pub dpi_awareness: Option<String>,
+    pub long_path_aware: Option<bool>,
+    pub heap_type: Option<String>,
+    pub supported_os: Vec<SupportedOs>,
+    pub dependencies: Vec<AssemblyIdentity>,
+    pub xml: String,
+}
+
+/// Element of the XML document
+struct Element {
+    name: String,
+    attributes: Vec<(String, String)>,
+    text: String,
+}
+
+impl Element {
+    fn get_attribute(&self, name: &str) -> Option<String> {
+        self.attributes.iter().find(|(key, _)| key == name).map(|(_, value)| value.clone())
+    }
+}
+
+/// Removes the namespace prefix of a qualified name
+fn local_name(name: &str) -> String {
+    match name.rfind(':') {
+        Some(index) => name[index + 1..].to_string(),
+        None => name.to_string()
+    }
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+fn parse_attributes(tag: &str) -> Vec<(String, String)> {
+    let mut attributes:Vec<(String, String)> = Vec::new();
+    let mut rest:&str = tag;
+    while let Some(equal) = rest.find('=') {
+        let name:String = local_name(rest[..equal].trim());
+        let value_part:&str = rest[equal + 1..].trim_start();
+        let quote:char = match value_part.chars().next() {
+            Some(quote) if quote == '"' || quote == '\'' => quote,
+            _ => break
+        };
+        let end:usize = match value_part[1..].find(quote) {
+            Some(end) => end + 1,
+            None => break
+        };
+        attributes.push((name, unescape(&value_part[1..end])));
+        rest = &value_part[end + 1..];
+    }
+    attributes
+}
+
+/// Minimal XML reader, enough for the flat structure of manifests.
+/// Each element is visited once closed, along with the stack of its open ancestors
+fn parse_elements<F: FnMut(&Element, &[Element])>(xml: &str, mut visit: F) {
+    let mut stack:Vec<Element> = Vec::new();
+    let mut rest:&str = xml;
+    while let Some(start) = rest.find('<') {
+        if let Some(current) = stack.last_mut() {
+            current.text.push_str(&unescape(&rest[..start]));
+        }
+        rest = &rest[start..];
+        let (skip, end_marker):(usize, &str) = if rest.starts_with("<!--") {
+            (4, "-->")
+        } else if rest.starts_with("<![CDATA[") {
+            (9, "]]>")
+        } else if rest.starts_with("<?") {
+            (2, "?>")
+        } else {
+            (1, ">")
+        };
+        let end:usize = match rest[skip..].find(end_marker) {
+            Some(end) => skip + end,
+            None => break
+        };
+        let content:&str = &rest[skip..end];
+        rest = &rest[end + end_marker.len()..];
+        if skip == 9 {
+            if let Some(current) = stack.last_mut() {
+                current.text.push_str(content);
+            }
+            continue;
+        }
+        if skip != 1 || content.starts_with('!') {
+            continue;
+        }
+        if let Some(name) = content.strip_prefix('/') {
+            let name:String = local_name(name.trim());
+            if let Some(position) = stack.iter().rposition(|element| element.name == name) {
+                // elements left open inside it are closed along with it
+                while stack.len() > position {
+                    let element:Element = stack.pop().unwrap();
+                    visit(&element, &stack);
+                }
+            }
+            continue;
+        }
+        let self_closing:bool = content.ends_with('/');
+        let content:&str = content.trim_end_matches('/');
+        let name_end:usize = content.find(char::is_whitespace).unwrap_or(content.len());
+        let element:Element = Element {
+            name: local_name(&content[..name_end]),
+            attributes: parse_attributes(&content[name_end..]),
+            text: String::new()
+        };
+        if self_closing {
+            visit(&element, &stack);
+        } else {
+            stack.push(element);
+        }
+    }
+    while let Some(element) = stack.pop() {
+        visit(&element, &stack);
+    }
+}
+
+fn parse_bool(text: &str) -> bool {
+    text.trim().eq_ignore_ascii_case("true")
+}
+
+impl AssemblyIdentity {
+    fn from_element(element: &Element) -> Self {
+        AssemblyIdentity {
+            name: element.get_attribute("name"),
+            version: element.get_attribute("version"),
+            assembly_type: element.get_attribute("type"),
+            processor_architecture: element.get_attribute("processorArchitecture"),
+            public_key_token: element.get_attribute("publicKeyToken"),
+            language: element.get_attribute("language"),
+        }
+    }
+}
+
+impl Manifest {
+    pub fn parse(xml: &str) -> Self {
+        let mut manifest:Manifest = Manifest {
+            xml: xml.to_string(),
+            ..Default::default()
+        };
+        parse_elements(xml, |element, ancestors| {
+            let text:&str = element.text.trim();
+            match element.name.as_str() {
+                "assemblyIdentity" => {
+                    if ancestors.iter().any(|ancestor| ancestor.name == "dependentAssembly") {
+                        manifest.dependencies.push(AssemblyIdentity::from_element(element));
+                    } else if manifest.assembly_identity.is_none() {
+                        manifest.assembly_identity = Some(AssemblyIdentity::from_element(element));
+                    }
+                }
+                "requestedExecutionLevel" => {
+                    manifest.requested_execution_level = element.get_attribute("level");
+                    manifest.ui_access = element.get_attribute("uiAccess").map(|ui_access| parse_bool(&ui_access));
+                }
+                "dpiAware" => manifest.dpi_aware = Some(text.to_string()),
+                "dpiAwareness" => manifest.dpi_awareness = Some(text.to_string()),
+                "longPathAware" => manifest.long_path_aware = Some(parse_bool(text)),
+                "heapType" => manifest.heap_type = Some(text.to_string()),
+                "supportedOS" => {
+                    if let Some(id) = element.get_attribute("Id") {
+                        let name:Option<String> = index::SUPPORTEDOS.get(id.to_lowercase().as_str()).map(|name| name.to_string());
+                        manifest.supported_os.push(SupportedOs { id, name });
+                    }
+                }
+                _ => {}
+            }
+        });
+        manifest
+    }
+    /// Decodes the manifest bytes, UTF-8 by default or UTF-16 when a byte order mark says so
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        let xml:String = if bytes.starts_with(&[0xff, 0xfe]) {
+            let chars:Vec<u16> = bytes[2..].chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect();
+            String::from_utf16_lossy(&chars)
+        } else {
+            String::from_utf8_lossy(bytes.strip_prefix(&[0xef, 0xbb, 0xbf]).unwrap_or(bytes)).into_owned()
+        };
+        Manifest::parse(xml.trim_end_matches(char::from(0)))
+    }
+    /// Decodes the first RT_MANIFEST resource of the tree, if any
+    pub fn from_resources(bytes: &[u8], headers: &Headers, resource_directory: &ResourceDirectory) -> error::Result<Option<Self>> {
+        match resource_directory.get_resources(RT_MANIFEST).first() {
+            Some(resource) => Ok(Some(Manifest::from_bytes(resource.get_data(bytes, headers)?))),
+            None => Ok(None)
+        }
+    }
+    /// True when the manifest explicitly requests the `asInvoker` execution level
+    pub fn is_as_invoker(&self) -> bool {
+        self.requested_execution_level.as_deref() == Some("asInvoker")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Manifest;
+
+    const MANIFEST: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<assembly xmlns="urn:schemas-microsoft-com:asm.v1" manifestVersion="1.0" xmlns:asmv3="urn:schemas-microsoft-com:asm.v3">
+  <assemblyIdentity type="win32" name="Acme.Widget" version="1.2.3.4" processorArchitecture="amd64"/>
+  <!-- <requestedExecutionLevel level="requireAdministrator"/> -->
+  <trustInfo xmlns="urn:schemas-microsoft-com:asm.v3">
+    <security><requestedPrivileges>
+      <requestedExecutionLevel level='asInvoker' uiAccess='false' />
+    </requestedPrivileges></security>
+  </trustInfo>
+  <compatibility xmlns="urn:schemas-microsoft-com:compatibility.v1">
+    <application>
+      <supportedOS Id="{8e0f7a12-bfb3-4fe8-b9a5-48fd50a15a9a}"/>
+      <supportedOS Id="{35138B9A-5D96-4FBD-8E2D-A2440225F93A}"/>
+    </application>
+  </compatibility>
+  <asmv3:application>
+    <asmv3:windowsSettings>
+      <dpiAware xmlns="http://schemas.microsoft.com/SMI/2005/WindowsSettings">true/pm</dpiAware>
+      <dpiAwareness xmlns="http://schemas.microsoft.com/SMI/2016/WindowsSettings">PerMonitorV2</dpiAwareness>
+      <ws2:longPathAware xmlns:ws2="http://schemas.microsoft.com/SMI/2016/WindowsSettings">true</ws2:longPathAware>
+      <heapType xmlns="http://schemas.microsoft.com/SMI/2020/WindowsSettings">SegmentHeap</heapType>
+    </asmv3:windowsSettings>
+  </asmv3:application>
+  <dependency>
+    <dependentAssembly>
+      <assemblyIdentity type="win32" name="Microsoft.Windows.Common-Controls" version="6.0.0.0" processorArchitecture="*" publicKeyToken="6595b64144ccf1df" language="*"/>
+    </dependentAssembly>
+  </dependency>
+</assembly>"#;
+
+    #[test]
+    fn manifest() {
+        let manifest = Manifest::from_bytes(MANIFEST.as_bytes());
+        assert!(manifest.is_as_invoker());
+        assert_eq!(manifest.ui_access, Some(false));
+        assert_eq!(manifest.assembly_identity.unwrap().name.as_deref(), Some("Acme.Widget"));
+        assert_eq!(manifest.supported_os.len(), 2);
+        assert_eq!(manifest.supported_os[1].name.as_deref(), Some("Windows 7"));
+        assert_eq!(manifest.dpi_aware.as_deref(), Some("true/pm"));
+        assert_eq!(manifest.dpi_awareness.as_deref(), Some("PerMonitorV2"));
+        assert_eq!(manifest.long_path_aware, Some(true));
+        assert_eq!(manifest.heap_type.as_deref(), Some("SegmentHeap"));
+        assert_eq!(manifest.dependencies.len(), 1);
+        assert_eq!(manifest.dependencies[0].name.as_deref(), Some("Microsoft.Windows.Common-Controls"));
+        assert_eq!(manifest.dependencies[0].public_key_token.as_deref(), Some("6595b64144ccf1df"));
+    }
+
+    #[test]
+    fn manifest_deeply_nested() {
+        let depth = 100_000;
+        let xml = format!("{}<assemblyIdentity name=\"Deep\"/>{}", "<a>".repeat(depth), "</a>".repeat(depth));
+        let manifest = Manifest::parse(&xml);
+        assert_eq!(manifest.assembly_identity.unwrap().name.as_deref(), Some("Deep"));
+        // unclosed elements are still visited
+        let manifest = Manifest::parse("<dependentAssembly><assemblyIdentity name=\"Dependency\">");
+        assert_eq!(manifest.dependencies.len(), 1);
+    }
+}
@@ -0,0 +1,270 @@
+use scroll::{IOread, IOwrite, Pread, Pwrite, SizeWith};
+use serde::{Deserialize, Serialize};
+
+use crate::error;
+use crate::pe::header::Headers;
+use crate::pe::resource::{ResourceDirectory, ResourceId, RT_ACCELERATOR, RT_MENU};
+use crate::pe::version::{align_4, read_utf16};
+
+/// Standard menu item opening a submenu
+pub const MF_POPUP: u16 = 0x10;
+/// Last item of a standard menu level or of an accelerator table
+pub const MF_END: u16 = 0x80;
+/// Extended menu item opening a submenu
+pub const MFR_POPUP: u16 = 0x01;
+/// Last item of an extended menu level
+pub const MFR_END: u16 = 0x80;
+
+pub const FVIRTKEY: u16 = 0x01;
+pub const FNOINVERT: u16 = 0x02;
+pub const FSHIFT: u16 = 0x04;
+pub const FCONTROL: u16 = 0x08;
+pub const FALT: u16 = 0x10;
+
+/// Menus nest rarely more than a few levels, anything deeper is treated as a loop
+const MENU_MAX_DEPTH: usize = 32;
+
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct MenuItem {
+    /// Item flags of a standard menu or MFT_* type of an extended one
+    pub flags: u32,
+    /// MFS_* state, only present in extended menus
+    pub state: Option<u32>,
+    pub id: u32,
+    pub text: String,
+    /// Help identifier of an extended submenu
+    pub help_id: Option<u32>,
+    pub items: Vec<MenuItem>,
+}
+
+/// RT_MENU resource, decoded from either MENUITEMTEMPLATE or MENUEX_TEMPLATE
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct Menu {
+    pub name: ResourceId,
+    pub language: ResourceId,
+    pub extended: bool,
+    pub help_id: Option<u32>,
+    pub items: Vec<MenuItem>,
+}
+
+#[derive(Debug, PartialEq, Copy, Clone, Default, Pread, Pwrite, IOread, IOwrite, SizeWith, Deserialize, Serialize)]
+pub struct AcceleratorTableEntry {
+    pub flags: u16,
+    /// Virtual key code when FVIRTKEY is set, character code otherwise
+    pub key: u16,
+    pub id: u16,
+    pub padding: u16,
+}
+
+impl AcceleratorTableEntry {
+    pub fn parse(bytes: &[u8], offset: &mut usize) -> error::Result<Self> {
+        Ok(bytes.gread_with(offset, scroll::LE)?)
+    }
+    /// Modifier and option names of the entry flags
+    pub fn get_modifiers(&self) -> Vec<&'static str> {
+        [(FVIRTKEY, "VIRTKEY"), (FNOINVERT, "NOINVERT"), (FSHIFT, "SHIFT"), (FCONTROL, "CONTROL"), (FALT, "ALT")].iter()
+            .filter(|(flag, _)| self.flags & flag != 0)
+            .map(|(_, name)| *name)
+            .collect()
+    }
+}
+
+/// RT_ACCELERATOR resource
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct AcceleratorTable {
+    pub name: ResourceId,
+    pub language: ResourceId,
+    pub entries: Vec<AcceleratorTableEntry>,
+}
+
+impl MenuItem {
+    fn parse_level(bytes: &[u8], offset: &mut usize, depth: usize) -> error::Result<Vec<Self>> {
+        if depth > MENU_MAX_DEPTH {
+            return Err(error::Error::Malformed(format!("Menu nested too deeply at offset 0x{:x}", offset)));
+        }
+        let mut items:Vec<MenuItem> = Vec::new();
+        loop {
+            let flags:u16 = bytes.gread_with(offset, scroll::LE)?;
+            let popup:bool = flags & MF_POPUP != 0;
+            let id:u16 = if popup { 0 } else { bytes.gread_with(offset, scroll::LE)? };
+            let text:String = read_utf16(bytes, offset, bytes.len())?;
+            let children:Vec<MenuItem> = if popup { MenuItem::parse_level(bytes, offset, depth + 1)? } else { Vec::new() };
+            items.push(MenuItem {
+                flags: u32::from(flags),
+                id: u32::from(id),
+                text,
+                items: children,
+                ..Default::default()
+            });
+            if flags & MF_END != 0 {
+                return Ok(items);
+            }
+        }
+    }
+    fn parse_level_ex(bytes: &[u8], offset: &mut usize, depth: usize) -> error::Result<Vec<Self>> {
+        if depth > MENU_MAX_DEPTH {
+            return Err(error::Error::Malformed(format!("Menu nested too deeply at offset 0x{:x}", offset)));
+        }
+        let mut items:Vec<MenuItem> = Vec::new();
+        loop {
+            *offset = align_4(*offset);
+            let flags:u32 = bytes.gread_with(offset, scroll::LE)?;
+            let state:u32 = bytes.gread_with(offset, scroll::LE)?;
+            let id:u32 = bytes.gread_with(offset, scroll::LE)?;
+            let resource_flags:u16 = bytes.gread_with(offset, scroll::LE)?;
+            let text:String = read_utf16(bytes, offset, bytes.len())?;
+            let mut item:MenuItem = MenuItem {
+                flags,
+                state: Some(state),
+                id,
+                text,
+                ..Default::default()
+            };
+            if resource_flags & MFR_POPUP != 0 {
+                *offset = align_4(*offset);
+                item.help_id = Some(bytes.gread_with(offset, scroll::LE)?);
+                item.items = MenuItem::parse_level_ex(bytes, offset, depth + 1)?;
+            }
+            items.push(item);
+            if resource_flags & MFR_END != 0 {
+                return Ok(items);
+            }
+        }
+    }
+}
+
+impl Menu {
+    pub fn parse(bytes: &[u8]) -> error::Result<Self> {
+        let version:u16 = bytes.pread_with(0, scroll::LE)?;
+        let header_size:u16 = bytes.pread_with(2, scroll::LE)?;
+        if version == 1 {
+            // The items offset is relative to the end of the wOffset field
+            let help_id:u32 = bytes.pread_with(4, scroll::LE)?;
+            let mut offset:usize = 4 + header_size as usize;
+            Ok(Menu {
+                extended: true,
+                help_id: Some(help_id),
+                items: MenuItem::parse_level_ex(bytes, &mut offset, 0)?,
+                ..Default::default()
+            })
+        } else {
+            let mut offset:usize = 4 + header_size as usize;
+            Ok(Menu {
+                items: MenuItem::parse_level(bytes, &mut offset, 0)?,
+                ..Default::default()
+            })
+        }
+    }
+    /// Decodes every RT_MENU resource of the tree
+    pub fn from_resources(bytes: &[u8], headers: &Headers, resource_directory: &ResourceDirectory) -> error::Result<Vec<Self>> {
+        let mut menus:Vec<Menu> = Vec::new();
+        for resource in resource_directory.get_resources(RT_MENU) {
+            menus.push(Menu {
+                name: resource.name.clone(),
+                language: resource.language.clone(),
+                ..Menu::parse(resource.get_data(bytes, headers)?)?
+            });
+        }
+        Ok(menus)
+    }
+}
+
+impl AcceleratorTable {
+    pub fn parse(bytes: &[u8]) -> error::Result<Self> {
+        let mut offset:usize = 0;
+        let mut entries:Vec<AcceleratorTableEntry> = Vec::new();
+        while offset + 8 <= bytes.len() {
+            let entry:AcceleratorTableEntry = AcceleratorTableEntry::parse(bytes, &mut offset)?;
+            entries.push(entry);
+            if entry.flags & MF_END != 0 {
+                break;
+            }
+        }
+        Ok(AcceleratorTable {
+            entries,
+            ..Default::default()
+        })
+    }
+    /// Decodes every RT_ACCELERATOR resource of the tree
+    pub fn from_resources(bytes: &[u8], headers: &Headers, resource_directory: &ResourceDirectory) -> error::Result<Vec<Self>> {
+        let mut tables:Vec<AcceleratorTable> = Vec::new();
+        for resource in resource_directory.get_resources(RT_ACCELERATOR) {
+            tables.push(AcceleratorTable {
+                name: resource.name.clone(),
+                language: resource.language.clone(),
+                ..AcceleratorTable::parse(resource.get_data(bytes, headers)?)?
+            });
+        }
+        Ok(tables)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AcceleratorTable, Menu, FCONTROL, FVIRTKEY, MF_END, MF_POPUP, MFR_END, MFR_POPUP};
+
+    fn utf16(text: &str) -> Vec<u8> {
+        text.encode_utf16().chain(Some(0)).flat_map(|c| c.to_le_bytes().to_vec()).collect()
+    }
+
+    #[test]
+    fn menu() {
+        let mut bytes = vec![0u8; 4];
+        bytes.extend(&MF_POPUP.to_le_bytes());
+        bytes.extend(utf16("&File"));
+        bytes.extend(&[0, 0, 0x65, 0]);
+        bytes.extend(utf16("&Open"));
+        bytes.extend(&MF_END.to_le_bytes());
+        bytes.extend(&[0x66, 0]);
+        bytes.extend(utf16("E&xit"));
+        bytes.extend(&(MF_POPUP | MF_END).to_le_bytes());
+        bytes.extend(utf16("&Help"));
+        bytes.extend(&MF_END.to_le_bytes());
+        bytes.extend(&[0x67, 0]);
+        bytes.extend(utf16("&About"));
+        let menu = Menu::parse(&bytes).unwrap();
+        assert!(!menu.extended);
+        assert_eq!(menu.items.len(), 2);
+        assert_eq!(menu.items[0].text, "&File");
+        assert_eq!(menu.items[0].items.len(), 2);
+        assert_eq!(menu.items[0].items[1].id, 0x66);
+        assert_eq!(menu.items[1].items[0].text, "&About");
+    }
+
+    #[test]
+    fn menu_ex() {
+        let mut bytes = vec![1, 0, 4, 0, 0, 0, 0, 0];
+        bytes.extend(&[0u8; 8]);
+        bytes.extend(&[0, 0, 0, 0]);
+        bytes.extend(&(MFR_POPUP | MFR_END).to_le_bytes());
+        bytes.extend(utf16("&View"));
+        bytes.resize((bytes.len() + 3) & !3, 0);
+        bytes.extend(&[9, 0, 0, 0]);
+        bytes.extend(&[0u8; 4]);
+        bytes.extend(&[8, 0, 0, 0]);
+        bytes.extend(&[0xc8, 0, 0, 0]);
+        bytes.extend(&MFR_END.to_le_bytes());
+        bytes.extend(utf16("&Status Bar"));
+        let menu = Menu::parse(&bytes).unwrap();
+        assert!(menu.extended);
+        assert_eq!(menu.items[0].help_id, Some(9));
+        assert_eq!(menu.items[0].items[0].id, 0xc8);
+        assert_eq!(menu.items[0].items[0].state, Some(8));
+        assert_eq!(menu.items[0].items[0].text, "&Status Bar");
+    }
+
+    #[test]
+    fn accelerators() {
+        let mut bytes:Vec<u8> = Vec::new();
+        for (flags, key, id) in [(FVIRTKEY | FCONTROL, 0x4f, 0x65), (FVIRTKEY | MF_END, 0x73, 0x66)].iter() {
+            bytes.extend(&flags.to_le_bytes());
+            bytes.extend(&u16::to_le_bytes(*key));
+            bytes.extend(&u16::to_le_bytes(*id));
+            bytes.extend(&[0, 0]);
+        }
+        let table = AcceleratorTable::parse(&bytes).unwrap();
+        assert_eq!(table.entries.len(), 2);
+        assert_eq!(table.entries[0].get_modifiers(), vec!["VIRTKEY", "CONTROL"]);
+        assert_eq!(table.entries[1].id, 0x66);
+    }
+}
@@ -0,0 +1,777 @@
+use std::collections::BTreeMap;
+use std::convert::TryInto;
+
+use scroll::{IOread, IOwrite, Pread, Pwrite, SizeWith};
+use serde::{Deserialize, Serialize, Serializer};
+use serde::ser::SerializeStruct;
+
+use crate::error;
+use crate::pe::debug::format_guid;
+use crate::pe::header::{DataDirectory, Headers};
+use crate::pe::version::align_4;
+
+/// "BSJB"
+pub const METADATA_SIGNATURE: u32 = 0x424a5342;
+
+/// HeapSizes bits of the tables stream, set when the heap needs 4-byte indexes
+pub const HEAP_STRING_WIDE: u8 = 0x01;
+pub const HEAP_GUID_WIDE: u8 = 0x02;
+pub const HEAP_BLOB_WIDE: u8 = 0x04;
+/// Set in uncompressed `#-` streams carrying 4 extra bytes after the row counts
+pub const HEAP_EXTRA_DATA: u8 = 0x40;
+
+pub const TABLE_MODULE: usize = 0x00;
+pub const TABLE_TYPEREF: usize = 0x01;
+pub const TABLE_TYPEDEF: usize = 0x02;
+pub const TABLE_FIELD: usize = 0x04;
+pub const TABLE_METHODDEF: usize = 0x06;
+pub const TABLE_PARAM: usize = 0x08;
+pub const TABLE_MEMBERREF: usize = 0x0a;
+pub const TABLE_MODULEREF: usize = 0x1a;
+pub const TABLE_IMPLMAP: usize = 0x1c;
+pub const TABLE_ASSEMBLY: usize = 0x20;
+pub const TABLE_ASSEMBLYREF: usize = 0x23;
+pub const TABLE_MANIFESTRESOURCE: usize = 0x28;
+
+/// Names of the ECMA-335 tables, indexed by table number
+pub const TABLE_NAMES: [&str; 45] = [
+    "Module", "TypeRef", "TypeDef", "FieldPtr", "Field", "MethodPtr", "MethodDef", "ParamPtr",
+    "Param", "InterfaceImpl", "MemberRef", "Constant", "CustomAttribute", "FieldMarshal", "DeclSecurity", "ClassLayout",
+    "FieldLayout", "StandAloneSig", "EventMap", "EventPtr", "Event", "PropertyMap", "PropertyPtr", "Property",
+    "MethodSemantics", "MethodImpl", "ModuleRef", "TypeSpec", "ImplMap", "FieldRVA", "ENCLog", "ENCMap",
+    "Assembly", "AssemblyProcessor", "AssemblyOS", "AssemblyRef", "AssemblyRefProcessor", "AssemblyRefOS", "File", "ExportedType",
+    "ManifestResource", "NestedClass", "GenericParam", "MethodSpec", "GenericParamConstraint",
+];
+
+/// Table number used for the unused tags of a coded index
+const TABLE_UNUSED: usize = 0x3f;
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+enum CodedIndex {
+    TypeDefOrRef,
+    HasConstant,
+    HasCustomAttribute,
+    HasFieldMarshal,
+    HasDeclSecurity,
+    MemberRefParent,
+    HasSemantics,
+    MethodDefOrRef,
+    MemberForwarded,
+    Implementation,
+    CustomAttributeType,
+    ResolutionScope,
+    TypeOrMethodDef,
+}
+
+impl CodedIndex {
+    /// Tables referenced by each tag value
+    fn tables(self) -> &'static [usize] {
+        match self {
+            CodedIndex::TypeDefOrRef => &[0x02, 0x01, 0x1b],
+            CodedIndex::HasConstant => &[0x04, 0x08, 0x17],
+            CodedIndex::HasCustomAttribute => &[0x06, 0x04, 0x01, 0x02, 0x08, 0x09, 0x0a, 0x00, 0x0e, 0x17, 0x14, 0x11, 0x1a, 0x1b, 0x20, 0x23, 0x26, 0x27, 0x28, 0x2a, 0x2c, 0x2b],
+            CodedIndex::HasFieldMarshal => &[0x04, 0x08],
+            CodedIndex::HasDeclSecurity => &[0x02, 0x06, 0x20],
+            CodedIndex::MemberRefParent => &[0x02, 0x01, 0x1a, 0x06, 0x1b],
+            CodedIndex::HasSemantics => &[0x14, 0x17],
+            CodedIndex::MethodDefOrRef => &[0x06, 0x0a],
+            CodedIndex::MemberForwarded => &[0x04, 0x06],
+            CodedIndex::Implementation => &[0x26, 0x23, 0x27],
+            CodedIndex::CustomAttributeType => &[TABLE_UNUSED, TABLE_UNUSED, 0x06, 0x0a, TABLE_UNUSED],
+            CodedIndex::ResolutionScope => &[0x00, 0x1a, 0x23, 0x01],
+            CodedIndex::TypeOrMethodDef => &[0x02, 0x06],
+        }
+    }
+    fn tag_bits(self) -> u32 {
+        let count:usize = self.tables().len();
+        usize::BITS - (count - 1).leading_zeros()
+    }
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+enum Column {
+    U8,
+    U16,
+    U32,
+    String,
+    Guid,
+    Blob,
+    Table(usize),
+    Coded(CodedIndex),
+}
+
+use self::Column::{Blob, Coded, Guid, Table, U16, U32, U8};
+use self::CodedIndex::*;
+
+/// Column layout of every ECMA-335 table
+const TABLE_COLUMNS: [&[Column]; 45] = [
+    /* Module */ &[U16, Column::String, Guid, Guid, Guid],
+    /* TypeRef */ &[Coded(ResolutionScope), Column::String, Column::String],
+    /* TypeDef */ &[U32, Column::String, Column::String, Coded(TypeDefOrRef), Table(0x04), Table(0x06)],
+    /* FieldPtr */ &[Table(0x04)],
+    /* Field */ &[U16, Column::String, Blob],
+    /* MethodPtr */ &[Table(0x06)],
+    /* MethodDef */ &[U32, U16, U16, Column::String, Blob, Table(0x08)],
+    /* ParamPtr */ &[Table(0x08)],
+    /* Param */ &[U16, U16, Column::String],
+    /* InterfaceImpl */ &[Table(0x02), Coded(TypeDefOrRef)],
+    /* MemberRef */ &[Coded(MemberRefParent), Column::String, Blob],
+    /* Constant */ &[U8, U8, Coded(HasConstant), Blob],
+    /* CustomAttribute */ &[Coded(HasCustomAttribute), Coded(CustomAttributeType), Blob],
+    /* FieldMarshal */ &[Coded(HasFieldMarshal), Blob],
+    /* DeclSecurity */ &[U16, Coded(HasDeclSecurity), Blob],
+    /* ClassLayout */ &[U16, U32, Table(0x02)],
+    /* FieldLayout */ &[U32, Table(0x04)],
+    /* StandAloneSig */ &[Blob],
+    /* EventMap */ &[Table(0x02), Table(0x14)],
+    /* EventPtr */ &[Table(0x14)],
+    /* Event */ &[U16, Column::String, Coded(TypeDefOrRef)],
+    /* PropertyMap */ &[Table(0x02), Table(0x17)],
+    /* PropertyPtr */ &[Table(0x17)],
+    /* Property */ &[U16, Column::String, Blob],
+    /* MethodSemantics */ &[U16, Table(0x06), Coded(HasSemantics)],
+    /* MethodImpl */ &[Table(0x02), Coded(MethodDefOrRef), Coded(MethodDefOrRef)],
+    /* ModuleRef */ &[Column::String],
+    /* TypeSpec */ &[Blob],
+    /* ImplMap */ &[U16, Coded(MemberForwarded), Column::String, Table(0x1a)],
+    /* FieldRVA */ &[U32, Table(0x04)],
+    /* ENCLog */ &[U32, U32],
+    /* ENCMap */ &[U32],
+    /* Assembly */ &[U32, U16, U16, U16, U16, U32, Blob, Column::String, Column::String],
+    /* AssemblyProcessor */ &[U32],
+    /* AssemblyOS */ &[U32, U32, U32],
+    /* AssemblyRef */ &[U16, U16, U16, U16, U32, Blob, Column::String, Column::String, Blob],
+    /* AssemblyRefProcessor */ &[U32, Table(0x23)],
+    /* AssemblyRefOS */ &[U32, U32, U32, Table(0x23)],
+    /* File */ &[U32, Column::String, Blob],
+    /* ExportedType */ &[U32, U32, Column::String, Column::String, Coded(Implementation)],
+    /* ManifestResource */ &[U32, U32, Column::String, Coded(Implementation)],
+    /* NestedClass */ &[Table(0x02), Table(0x02)],
+    /* GenericParam */ &[U16, U16, Coded(TypeOrMethodDef), Column::String],
+    /* MethodSpec */ &[Coded(MethodDefOrRef), Blob],
+    /* GenericParamConstraint */ &[Table(0x2a), Coded(TypeDefOrRef)],
+];
+
+/// Fixed part of the metadata root, followed by the version string
+#[derive(Debug, PartialEq, Copy, Clone, Default, Pread, Pwrite, IOread, IOwrite, SizeWith, Serialize, Deserialize)]
+pub struct MetadataRootHeader {
+    pub signature: u32,
+    pub major_version: u16,
+    pub minor_version: u16,
+    pub reserved: u32,
+    pub length: u32,
+}
+
+impl MetadataRootHeader {
+    pub fn parse(bytes: &[u8], offset: &mut usize) -> error::Result<Self> {
+        Ok(bytes.gread_with(offset, scroll::LE)?)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Default, Deserialize)]
+pub struct StreamHeader {
+    /// Offset from the metadata root
+    pub offset: u32,
+    pub size: u32,
+    pub name: String,
+}
+
+impl Serialize for StreamHeader {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("StreamHeader", 3)?;
+        state.serialize_field("offset", &format!("0x{:x}", &self.offset))?;
+        state.serialize_field("size", &self.size)?;
+        state.serialize_field("name", &self.name)?;
+        state.end()
+    }
+}
+
+/// Header of the `#~` and `#-` streams, followed by one row count per valid table
+#[derive(Debug, PartialEq, Copy, Clone, Default, Pread, Pwrite, IOread, IOwrite, SizeWith, Deserialize)]
+pub struct TablesHeader {
+    pub reserved: u32,
+    pub major_version: u8,
+    pub minor_version: u8,
+    pub heap_sizes: u8,
+    pub reserved2: u8,
+    pub valid: u64,
+    pub sorted: u64,
+}
+
+impl Serialize for TablesHeader {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("TablesHeader", 5)?;
+        state.serialize_field("major_version", &self.major_version)?;
+        state.serialize_field("minor_version", &self.minor_version)?;
+        state.serialize_field("heap_sizes", &format!("0x{:x}", &self.heap_sizes))?;
+        state.serialize_field("valid", &format!("0x{:x}", &self.valid))?;
+        state.serialize_field("sorted", &format!("0x{:x}", &self.sorted))?;
+        state.end()
+    }
+}
+
+impl TablesHeader {
+    pub fn parse(bytes: &[u8], offset: &mut usize) -> error::Result<Self> {
+        Ok(bytes.gread_with(offset, scroll::LE)?)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct Module {
+    pub generation: u16,
+    pub name: String,
+    pub mvid: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct TypeRef {
+    /// Token of the Module, ModuleRef, AssemblyRef or TypeRef owning the type
+    pub resolution_scope: u32,
+    pub name: String,
+    pub namespace: String,
+}
+
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct TypeDef {
+    pub flags: u32,
+    pub name: String,
+    pub namespace: String,
+    /// Token of the base type, 0 for interfaces and `<Module>`
+    pub extends: u32,
+    pub field_list: u32,
+    pub method_list: u32,
+}
+
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct MethodDef {
+    pub rva: u32,
+    pub impl_flags: u16,
+    pub flags: u16,
+    pub name: String,
+    pub signature: u32,
+    pub param_list: u32,
+}
+
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct MemberRef {
+    /// Token of the TypeDef, TypeRef, ModuleRef, MethodDef or TypeSpec declaring the member
+    pub class: u32,
+    pub name: String,
+    pub signature: u32,
+}
+
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct Assembly {
+    pub hash_alg_id: u32,
+    pub version: String,
+    pub flags: u32,
+    pub public_key: String,
+    pub name: String,
+    pub culture: String,
+}
+
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct AssemblyRef {
+    pub version: String,
+    pub flags: u32,
+    pub public_key_or_token: String,
+    pub name: String,
+    pub culture: String,
+    pub hash_value: String,
+}
+
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct ManifestResource {
+    pub offset: u32,
+    pub flags: u32,
+    pub name: String,
+    /// Token of the File or AssemblyRef holding the resource, 0 when embedded in this image
+    pub implementation: u32,
+}
+
+/// P/Invoke mapping of a method to an export of a native module
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct ImplMap {
+    pub mapping_flags: u16,
+    /// Token of the forwarded Field or MethodDef
+    pub member_forwarded: u32,
+    pub member_name: Option<String>,
+    pub import_name: String,
+    pub import_scope: u32,
+    pub module_name: Option<String>,
+}
+
+/// Reads the compressed length prefix of a blob or user string
+fn read_compressed_length(heap: &[u8], offset: &mut usize) -> error::Result<usize> {
+    let first:u8 = heap.gread_with(offset, scroll::LE)?;
+    Ok(if first & 0x80 == 0 {
+        first as usize
+    } else if first & 0xc0 == 0x80 {
+        let second:u8 = heap.gread_with(offset, scroll::LE)?;
+        ((first as usize & 0x3f) << 8) | second as usize
+    } else {
+        let rest:[u8; 3] = [heap.gread_with(offset, scroll::LE)?, heap.gread_with(offset, scroll::LE)?, heap.gread_with(offset, scroll::LE)?];
+        ((first as usize & 0x1f) << 24) | (rest[0] as usize) << 16 | (rest[1] as usize) << 8 | rest[2] as usize
+    })
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// The #Strings, #GUID and #Blob heaps referenced by the table rows
+struct Heaps<'a> {
+    strings: &'a [u8],
+    guid: &'a [u8],
+    blob: &'a [u8],
+}
+
+impl<'a> Heaps<'a> {
+    fn get_string(&self, index: u32) -> error::Result<String> {
+        let string:&[u8] = self.strings.get(index as usize..).ok_or_else(|| error::Error::Malformed(format!("String heap index 0x{:x} out of bounds", index)))?;
+        let end:usize = string.iter().position(|&c| c == 0).unwrap_or(string.len());
+        Ok(String::from_utf8_lossy(&string[..end]).into_owned())
+    }
+    fn get_guid(&self, index: u32) -> error::Result<Option<String>> {
+        if index == 0 {
+            return Ok(None);
+        }
+        let start:usize = (index as usize - 1) * 16;
+        let guid:&[u8] = self.guid.get(start..start + 16).ok_or_else(|| error::Error::Malformed(format!("GUID heap index {} out of bounds", index)))?;
+        Ok(Some(format_guid(guid.try_into().unwrap())))
+    }
+    fn get_blob(&self, index: u32) -> error::Result<&'a [u8]> {
+        let mut offset:usize = index as usize;
+        let length:usize = read_compressed_length(self.blob, &mut offset)?;
+        self.blob.get(offset..offset + length).ok_or_else(|| error::Error::Malformed(format!("Blob heap index 0x{:x} out of bounds", index)))
+    }
+}
+
+/// Sizes and locations of the tables of a `#~` or `#-` stream
+struct TableReader<'a> {
+    bytes: &'a [u8],
+    heap_sizes: u8,
+    rows: [u32; 64],
+    offsets: [Option<usize>; 64],
+}
+
+impl<'a> TableReader<'a> {
+    fn new(bytes: &'a [u8], header: &TablesHeader, rows: [u32; 64], start: usize) -> Self {
+        let mut reader:TableReader = TableReader { bytes, heap_sizes: header.heap_sizes, rows, offsets: [None; 64] };
+        let mut offset:usize = start;
+        for (table, count) in rows.iter().enumerate().take(TABLE_COLUMNS.len()) {
+            if header.valid & (1 << table) != 0 {
+                reader.offsets[table] = Some(offset);
+                offset = offset.saturating_add(reader.row_size(table).saturating_mul(*count as usize));
+            }
+        }
+        reader
+    }
+    fn column_size(&self, column: Column) -> usize {
+        match column {
+            Column::U8 => 1,
+            Column::U16 => 2,
+            Column::U32 => 4,
+            Column::String => if self.heap_sizes & HEAP_STRING_WIDE != 0 { 4 } else { 2 },
+            Column::Guid => if self.heap_sizes & HEAP_GUID_WIDE != 0 { 4 } else { 2 },
+            Column::Blob => if self.heap_sizes & HEAP_BLOB_WIDE != 0 { 4 } else { 2 },
+            Column::Table(table) => if self.rows[table] < 1 << 16 { 2 } else { 4 },
+            Column::Coded(coded_index) => {
+                let max_rows:u32 = coded_index.tables().iter().map(|&table| self.rows[table]).max().unwrap_or(0);
+                if max_rows < 1 << (16 - coded_index.tag_bits()) { 2 } else { 4 }
+            }
+        }
+    }
+    fn row_size(&self, table: usize) -> usize {
+        TABLE_COLUMNS[table].iter().map(|&column| self.column_size(column)).sum()
+    }
+    /// Raw column values of the rows, coded indexes converted to metadata tokens
+    /// The rows stop at the first one cut by the end of the stream
+    fn read_rows(&self, table: usize) -> Vec<Vec<u32>> {
+        let mut rows:Vec<Vec<u32>> = Vec::new();
+        let mut offset:usize = match self.offsets[table] {
+            Some(offset) => offset,
+            None => return rows
+        };
+        for _ in 0..self.rows[table] {
+            let mut values:Vec<u32> = Vec::new();
+            for &column in TABLE_COLUMNS[table] {
+                let value:Option<u32> = match self.column_size(column) {
+                    1 => self.bytes.gread_with::<u8>(&mut offset, scroll::LE).ok().map(u32::from),
+                    2 => self.bytes.gread_with::<u16>(&mut offset, scroll::LE).ok().map(u32::from),
+                    _ => self.bytes.gread_with::<u32>(&mut offset, scroll::LE).ok()
+                };
+                let value:u32 = match value {
+                    Some(value) => value,
+                    None => return rows
+                };
+                values.push(match column {
+                    Column::Coded(coded_index) => {
+                        let tag_bits:u32 = coded_index.tag_bits();
+                        let tag:usize = (value & ((1 << tag_bits) - 1)) as usize;
+                        let table:usize = *coded_index.tables().get(tag).unwrap_or(&TABLE_UNUSED);
+                        // Null references stay null whatever their tag
+                        if value >> tag_bits == 0 { 0 } else { ((table as u32) << 24) | (value >> tag_bits) }
+                    }
+                    _ => value
+                });
+            }
+            rows.push(values);
+        }
+        rows
+    }
+}
+
+/// .NET metadata root, its streams and the core ECMA-335 tables
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct ClrMetadata {
+    pub major_version: u16,
+    pub minor_version: u16,
+    /// Targeted runtime such as "v4.0.30319"
+    pub version: String,
+    pub flags: u16,
+    pub streams: Vec<StreamHeader>,
+    pub tables_header: Option<TablesHeader>,
+    pub row_counts: BTreeMap<String, u32>,
+    pub user_strings: Vec<String>,
+    pub guids: Vec<String>,
+    pub module: Option<Module>,
+    pub type_refs: Vec<TypeRef>,
+    pub type_defs: Vec<TypeDef>,
+    pub method_defs: Vec<MethodDef>,
+    pub member_refs: Vec<MemberRef>,
+    pub module_refs: Vec<String>,
+    pub assembly: Option<Assembly>,
+    pub assembly_refs: Vec<AssemblyRef>,
+    pub manifest_resources: Vec<ManifestResource>,
+    pub impl_maps: Vec<ImplMap>,
+}
+
+impl ClrMetadata {
+    /// Parses the metadata pointed by the CLR header
+    pub fn parse(bytes: &[u8], headers: &Headers, data_directory: DataDirectory) -> error::Result<Self> {
+        let root:usize = headers.rva_to_offset(data_directory.virtual_address).ok_or(error::Error::BadRva(data_directory.virtual_address))?;
+        let mut offset:usize = root;
+        let root_header:MetadataRootHeader = MetadataRootHeader::parse(bytes, &mut offset)?;
+        if root_header.signature != METADATA_SIGNATURE {
+            return Err(error::Error::BadSignature(u64::from(root_header.signature)));
+        }
+        let version:&[u8] = bytes.get(offset..offset + root_header.length as usize).ok_or_else(|| error::Error::Malformed(String::from("Metadata version string out of bounds")))?;
+        let end:usize = version.iter().position(|&c| c == 0).unwrap_or(version.len());
+        let mut metadata:ClrMetadata = ClrMetadata {
+            major_version: root_header.major_version,
+            minor_version: root_header.minor_version,
+            version: String::from_utf8_lossy(&version[..end]).into_owned(),
+            ..Default::default()
+        };
+        offset += root_header.length as usize;
+        metadata.flags = bytes.gread_with(&mut offset, scroll::LE)?;
+        let stream_count:u16 = bytes.gread_with(&mut offset, scroll::LE)?;
+        for _ in 0..stream_count {
+            // A stream header that cannot be read ends the list, the streams before it are kept
+            let stream_offset:u32 = match bytes.gread_with(&mut offset, scroll::LE) {
+                Ok(stream_offset) => stream_offset,
+                Err(_) => break
+            };
+            let size:u32 = match bytes.gread_with(&mut offset, scroll::LE) {
+                Ok(size) => size,
+                Err(_) => break
+            };
+            let name:&[u8] = bytes.get(offset..(offset + 32).min(bytes.len())).unwrap_or(&[]);
+            let length:usize = match name.iter().position(|&c| c == 0) {
+                Some(length) => length,
+                None => break
+            };
+            metadata.streams.push(StreamHeader { offset: stream_offset, size, name: String::from_utf8_lossy(&name[..length]).into_owned() });
+            // Names are padded to 4 bytes from the metadata root
+            offset = root + align_4(offset + length + 1 - root);
+        }
+
+        // A missing stream or one out of bounds is read as empty
+        let get_stream = |name: &str| -> &[u8] {
+            metadata.streams.iter()
+                .find(|stream| stream.name == name)
+                .and_then(|stream| {
+                    let start:usize = root + stream.offset as usize;
+                    bytes.get(start..start + stream.size as usize)
+                })
+                .unwrap_or(&[])
+        };
+        let heaps:Heaps = Heaps { strings: get_stream("#Strings"), guid: get_stream("#GUID"), blob: get_stream("#Blob") };
+        let user_strings:&[u8] = get_stream("#US");
+        let tables:&[u8] = match get_stream("#~") {
+            tables if !tables.is_empty() => tables,
+            _ => get_stream("#-")
+        };
+
+        // User strings are blobs of UTF-16 characters followed by a flag byte
+        // A corrupt one ends the heap
+        let mut offset:usize = 1;
+        while offset < user_strings.len() {
+            let length:usize = match read_compressed_length(user_strings, &mut offset) {
+                Ok(length) => length,
+                Err(_) => break
+            };
+            if length == 0 {
+                continue;
+            }
+            let string:&[u8] = match user_strings.get(offset..offset + length) {
+                Some(string) => string,
+                None => break
+            };
+            let chars:Vec<u16> = string[..length - 1].chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect();
+            metadata.user_strings.push(String::from_utf16_lossy(&chars));
+            offset += length;
+        }
+        metadata.guids = heaps.guid.chunks_exact(16).map(|guid| format_guid(guid.try_into().unwrap())).collect();
+
+        // Without a readable tables header the tables are left empty
+        if !tables.is_empty() {
+            let _ = metadata.parse_tables(tables, &heaps);
+        }
+        Ok(metadata)
+    }
+    fn parse_tables(&mut self, tables: &[u8], heaps: &Heaps) -> error::Result<()> {
+        let mut offset:usize = 0;
+        let header:TablesHeader = TablesHeader::parse(tables, &mut offset)?;
+        let mut rows:[u32; 64] = [0; 64];
+        for (table, count) in rows.iter_mut().enumerate() {
+            if header.valid & (1 << table) != 0 {
+                *count = tables.gread_with(&mut offset, scroll::LE)?;
+                let name:String = TABLE_NAMES.get(table).map(|name| name.to_string()).unwrap_or_else(|| format!("0x{:x}", table));
+                self.row_counts.insert(name, *count);
+            }
+        }
+        if header.heap_sizes & HEAP_EXTRA_DATA != 0 {
+            offset += 4;
+        }
+        self.tables_header = Some(header);
+        let reader:TableReader = TableReader::new(tables, &header, rows, offset);
+
+        // Each table is decoded on its own, a corrupt row only ends its table
+        self.module = reader.read_rows(TABLE_MODULE).first().and_then(|row| {
+            Some(Module { generation: row[0] as u16, name: heaps.get_string(row[1]).ok()?, mvid: heaps.get_guid(row[2]).ok()? })
+        });
+        self.type_refs = reader.read_rows(TABLE_TYPEREF).iter().map_while(|row| {
+            Some(TypeRef { resolution_scope: row[0], name: heaps.get_string(row[1]).ok()?, namespace: heaps.get_string(row[2]).ok()? })
+        }).collect();
+        self.type_defs = reader.read_rows(TABLE_TYPEDEF).iter().map_while(|row| {
+            Some(TypeDef {
+                flags: row[0],
+                name: heaps.get_string(row[1]).ok()?,
+                namespace: heaps.get_string(row[2]).ok()?,
+                extends: row[3],
+                field_list: row[4],
+                method_list: row[5]
+            })
+        }).collect();
+        self.method_defs = reader.read_rows(TABLE_METHODDEF).iter().map_while(|row| {
+            Some(MethodDef {
+                rva: row[0],
+                impl_flags: row[1] as u16,
+                flags: row[2] as u16,
+                name: heaps.get_string(row[3]).ok()?,
+                signature: row[4],
+                param_list: row[5]
+            })
+        }).collect();
+        self.member_refs = reader.read_rows(TABLE_MEMBERREF).iter().map_while(|row| {
+            Some(MemberRef { class: row[0], name: heaps.get_string(row[1]).ok()?, signature: row[2] })
+        }).collect();
+        self.module_refs = reader.read_rows(TABLE_MODULEREF).iter().map_while(|row| heaps.get_string(row[0]).ok()).collect();
+        self.assembly = reader.read_rows(TABLE_ASSEMBLY).first().and_then(|row| {
+            Some(Assembly {
+                hash_alg_id: row[0],
+                version: format!("{}.{}.{}.{}", row[1], row[2], row[3], row[4]),
+                flags: row[5],
+                public_key: to_hex(heaps.get_blob(row[6]).ok()?),
+                name: heaps.get_string(row[7]).ok()?,
+                culture: heaps.get_string(row[8]).ok()?
+            })
+        });
+        self.assembly_refs = reader.read_rows(TABLE_ASSEMBLYREF).iter().map_while(|row| {
+            Some(AssemblyRef {
+                version: format!("{}.{}.{}.{}", row[0], row[1], row[2], row[3]),
+                flags: row[4],
+                public_key_or_token: to_hex(heaps.get_blob(row[5]).ok()?),
+                name: heaps.get_string(row[6]).ok()?,
+                culture: heaps.get_string(row[7]).ok()?,
+                hash_value: to_hex(heaps.get_blob(row[8]).ok()?)
+            })
+        }).collect();
+        self.manifest_resources = reader.read_rows(TABLE_MANIFESTRESOURCE).iter().map_while(|row| {
+            Some(ManifestResource { offset: row[0], flags: row[1], name: heaps.get_string(row[2]).ok()?, implementation: row[3] })
+        }).collect();
+        self.impl_maps = reader.read_rows(TABLE_IMPLMAP).iter().map_while(|row| {
+            let member_name:Option<String> = match (row[1] >> 24) as usize {
+                TABLE_METHODDEF => self.get_row(&self.method_defs, row[1]).map(|method| method.name.clone()),
+                _ => None
+            };
+            Some(ImplMap {
+                mapping_flags: row[0] as u16,
+                member_forwarded: row[1],
+                member_name,
+                import_name: heaps.get_string(row[2]).ok()?,
+                import_scope: row[3],
+                module_name: (row[3] as usize).checked_sub(1).and_then(|index| self.module_refs.get(index)).cloned()
+            })
+        }).collect();
+        Ok(())
+    }
+    fn get_row<'a, T>(&self, rows: &'a [T], token: u32) -> Option<&'a T> {
+        ((token & 0x00ff_ffff) as usize).checked_sub(1).and_then(|index| rows.get(index))
+    }
+    /// Native module and entry point of every P/Invoke declaration
+    pub fn pinvoke_targets(&self) -> Vec<(String, String)> {
+        self.impl_maps.iter()
+            .map(|impl_map| (impl_map.module_name.clone().unwrap_or_default(), impl_map.import_name.clone()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use scroll::Pwrite;
+
+    use super::{ClrMetadata, MetadataRootHeader, TablesHeader, METADATA_SIGNATURE};
+    use crate::pe::header::{DataDirectory, test_headers};
+
+    fn write_stream_header(bytes: &mut Vec<u8>, offset: &mut usize, stream_offset: u32, size: u32, name: &str) {
+        bytes.pwrite_with(stream_offset, *offset, scroll::LE).unwrap();
+        bytes.pwrite_with(size, *offset + 4, scroll::LE).unwrap();
+        bytes[*offset + 8..*offset + 8 + name.len()].copy_from_slice(name.as_bytes());
+        *offset += 8 + ((name.len() + 4) & !3);
+    }
+
+    fn image() -> Vec<u8> {
+        let mut bytes = vec![0u8; 0x400];
+        bytes.pwrite_with(MetadataRootHeader { signature: METADATA_SIGNATURE, major_version: 1, minor_version: 1, reserved: 0, length: 12 }, 0, scroll::LE).unwrap();
+        bytes[16..26].copy_from_slice(b"v4.0.30319");
+        bytes.pwrite_with(5u16, 30, scroll::LE).unwrap();
+        let mut offset:usize = 32;
+        write_stream_header(&mut bytes, &mut offset, 0x100, 0x80, "#~");
+        write_stream_header(&mut bytes, &mut offset, 0x180, 0x40, "#Strings");
+        write_stream_header(&mut bytes, &mut offset, 0x1c0, 0x10, "#US");
+        write_stream_header(&mut bytes, &mut offset, 0x1d0, 0x10, "#GUID");
+        write_stream_header(&mut bytes, &mut offset, 0x1e0, 0x10, "#Blob");
+
+        // #Strings
+        let strings:&[u8] = b"\0app.exe\0app\0kernel32\0GetTickCount\0System.Runtime\0";
+        bytes[0x180..0x180 + strings.len()].copy_from_slice(strings);
+        // #US, "Hi"
+        bytes[0x1c1..0x1c7].copy_from_slice(&[5, b'H', 0, b'i', 0, 0]);
+        // #GUID
+        bytes[0x1d0..0x1e0].copy_from_slice(&[0x78, 0x56, 0x34, 0x12, 0x34, 0x12, 0x34, 0x12, 1, 2, 3, 4, 5, 6, 7, 8]);
+        // #Blob, public key token
+        bytes[0x1e1..0x1ea].copy_from_slice(&[8, 0xb0, 0x3f, 0x5f, 0x7f, 0x11, 0xd5, 0x0a, 0x3a]);
+
+        // #~ with Module, MethodDef, ModuleRef, ImplMap, Assembly and AssemblyRef
+        let valid:u64 = 1 << 0x00 | 1 << 0x06 | 1 << 0x1a | 1 << 0x1c | 1 << 0x20 | 1 << 0x23;
+        bytes.pwrite_with(TablesHeader { major_version: 2, valid, ..Default::default() }, 0x100, scroll::LE).unwrap();
+        for index in 0..6 {
+            bytes.pwrite_with(1u32, 0x118 + index * 4, scroll::LE).unwrap();
+        }
+        let mut offset:usize = 0x130;
+        // Module: Generation, Name, Mvid, EncId, EncBaseId
+        for value in &[0u16, 1, 1, 0, 0] {
+            bytes.gwrite_with(*value, &mut offset, scroll::LE).unwrap();
+        }
+        // MethodDef: RVA, ImplFlags, Flags, Name, Signature, ParamList
+        bytes.gwrite_with(0u32, &mut offset, scroll::LE).unwrap();
+        for value in &[0x80u16, 0x2096, 22, 0, 1] {
+            bytes.gwrite_with(*value, &mut offset, scroll::LE).unwrap();
+        }
+        // ModuleRef: Name
+        bytes.gwrite_with(13u16, &mut offset, scroll::LE).unwrap();
+        // ImplMap: MappingFlags, MemberForwarded (MethodDef 1), ImportName, ImportScope
+        for value in &[0x100u16, 1 << 1 | 1, 22, 1] {
+            bytes.gwrite_with(*value, &mut offset, scroll::LE).unwrap();
+        }
+        // Assembly: HashAlgId, version, Flags, PublicKey, Name, Culture
+        bytes.gwrite_with(0x8004u32, &mut offset, scroll::LE).unwrap();
+        for value in &[1u16, 2, 3, 4] {
+            bytes.gwrite_with(*value, &mut offset, scroll::LE).unwrap();
+        }
+        bytes.gwrite_with(0u32, &mut offset, scroll::LE).unwrap();
+        for value in &[0u16, 9, 0] {
+            bytes.gwrite_with(*value, &mut offset, scroll::LE).unwrap();
+        }
+        // AssemblyRef: version, Flags, PublicKeyOrToken, Name, Culture, HashValue
+        for value in &[8u16, 0, 0, 0] {
+            bytes.gwrite_with(*value, &mut offset, scroll::LE).unwrap();
+        }
+        bytes.gwrite_with(0u32, &mut offset, scroll::LE).unwrap();
+        for value in &[1u16, 35, 0, 0] {
+            bytes.gwrite_with(*value, &mut offset, scroll::LE).unwrap();
+        }
+        assert!(offset <= 0x180);
+        bytes
+    }
+
+    #[test]
+    fn clr_metadata() {
+        let headers = test_headers(0x2000, 0x400);
+        let bytes = image();
+        let metadata = ClrMetadata::parse(&bytes, &headers, DataDirectory { virtual_address: 0x2000, size: 0x200 }).unwrap();
+        assert_eq!(metadata.version, "v4.0.30319");
+        assert_eq!(metadata.streams.len(), 5);
+        assert_eq!(metadata.user_strings, vec!["Hi"]);
+        assert_eq!(metadata.guids, vec!["{12345678-1234-1234-0102-030405060708}"]);
+        assert_eq!(metadata.row_counts["ImplMap"], 1);
+        let module = metadata.module.as_ref().unwrap();
+        assert_eq!(module.name, "app.exe");
+        assert_eq!(module.mvid.as_deref(), Some("{12345678-1234-1234-0102-030405060708}"));
+        assert_eq!(metadata.method_defs[0].name, "GetTickCount");
+        let assembly = metadata.assembly.as_ref().unwrap();
+        assert_eq!(assembly.name, "app");
+        assert_eq!(assembly.version, "1.2.3.4");
+        assert_eq!(metadata.assembly_refs[0].name, "System.Runtime");
+        assert_eq!(metadata.assembly_refs[0].version, "8.0.0.0");
+        assert_eq!(metadata.assembly_refs[0].public_key_or_token, "b03f5f7f11d50a3a");
+        assert_eq!(metadata.impl_maps[0].member_forwarded, 0x06000001);
+        assert_eq!(metadata.impl_maps[0].member_name.as_deref(), Some("GetTickCount"));
+        assert_eq!(metadata.pinvoke_targets(), vec![(String::from("kernel32"), String::from("GetTickCount"))]);
+    }
+
+    #[test]
+    fn clr_metadata_malformed() {
+        let headers = test_headers(0x2000, 0x400);
+        let data_directory = DataDirectory { virtual_address: 0x2000, size: 0x200 };
+        // MethodDef name outside of the #Strings heap, the Module row before it is kept
+        let mut bytes = image();
+        bytes.pwrite_with(0x7fffu16, 0x142, scroll::LE).unwrap();
+        // user string longer than its heap
+        bytes[0x1c1] = 0x7f;
+        let metadata = ClrMetadata::parse(&bytes, &headers, data_directory).unwrap();
+        assert_eq!(metadata.module.as_ref().unwrap().name, "app.exe");
+        assert!(metadata.method_defs.is_empty());
+        assert_eq!(metadata.impl_maps[0].member_name, None);
+        assert_eq!(metadata.assembly.as_ref().unwrap().name, "app");
+        assert_eq!(metadata.assembly_refs[0].name, "System.Runtime");
+        assert!(metadata.user_strings.is_empty());
+
+        // #US stream running past the end of the file
+        let mut bytes = image();
+        bytes.pwrite_with(0x10000u32, 0x44, scroll::LE).unwrap();
+        let metadata = ClrMetadata::parse(&bytes, &headers, data_directory).unwrap();
+        assert!(metadata.user_strings.is_empty());
+        assert_eq!(metadata.method_defs[0].name, "GetTickCount");
+
+        // unterminated name of the second stream, the tables are kept without their heaps
+        let mut bytes = image();
+        bytes[0x34..0x54].copy_from_slice(&[b'#'; 32]);
+        let metadata = ClrMetadata::parse(&bytes, &headers, data_directory).unwrap();
+        assert_eq!(metadata.streams.len(), 1);
+        assert_eq!(metadata.row_counts["Assembly"], 1);
+        assert!(metadata.module.is_none());
+        // bad signature
+        bytes[0] = 0;
+        assert!(ClrMetadata::parse(&bytes, &headers, data_directory).is_err());
+    }
+}
@@ -1,5 +1,29 @@
 pub mod pe;
 pub mod header;
 pub mod section;
+pub mod export;
+pub mod delay_import;
+pub mod bound_import;
+pub mod resource;
+pub mod version;
+pub mod manifest;
+pub mod icon;
+pub mod string_table;
+pub mod dialog;
+pub mod menu;
+pub mod exception;
+pub mod exception_arm;
+pub mod relocation;
+pub mod debug;
+pub mod tls;
+pub mod load_config;
+pub mod guard;
+pub mod safeseh;
+pub mod dynamic_relocation;
+pub mod chpe;
+pub mod clr;
+pub mod metadata;
+pub mod ready_to_run;
+pub mod bundle;
 pub mod index;
 pub mod display;
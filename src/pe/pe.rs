@@ -1,6 +1,28 @@
 use crate::error;
-use crate::pe::header::Headers;
-use crate::pe::section::ImportDirectoryTable;
+use crate::pe::header::{Headers, IMAGE_DIRECTORY_ENTRY_EXPORT, IMAGE_DIRECTORY_ENTRY_IMPORT, IMAGE_DIRECTORY_ENTRY_RESOURCE, IMAGE_DIRECTORY_ENTRY_EXCEPTION, IMAGE_DIRECTORY_ENTRY_BASERELOC, IMAGE_DIRECTORY_ENTRY_DEBUG, IMAGE_DIRECTORY_ENTRY_TLS, IMAGE_DIRECTORY_ENTRY_LOAD_CONFIG, IMAGE_DIRECTORY_ENTRY_BOUND_IMPORT, IMAGE_DIRECTORY_ENTRY_DELAY_IMPORT, IMAGE_DIRECTORY_ENTRY_COM_DESCRIPTOR};
+use crate::pe::export::ExportDirectory;
+use crate::pe::delay_import::DelayImportDirectory;
+use crate::pe::bound_import::BoundImportDirectory;
+use crate::pe::resource::ResourceDirectory;
+use crate::pe::version::VersionInfo;
+use crate::pe::manifest::Manifest;
+use crate::pe::string_table::LocalizedStrings;
+use crate::pe::dialog::Dialog;
+use crate::pe::menu::{AcceleratorTable, Menu};
+use crate::pe::exception::ExceptionDirectory;
+use crate::pe::relocation::BaseRelocationDirectory;
+use crate::pe::debug::DebugDirectory;
+use crate::pe::tls::TlsDirectory;
+use crate::pe::load_config::LoadConfigDirectory;
+use crate::pe::guard::ControlFlowGuard;
+use crate::pe::safeseh::SafeSeh;
+use crate::pe::dynamic_relocation::DynamicRelocationTable;
+use crate::pe::chpe::HybridMetadata;
+use crate::pe::clr::ClrDirectory;
+use crate::pe::metadata::ClrMetadata;
+use crate::pe::ready_to_run::ReadyToRunHeader;
+use crate::pe::bundle::Bundle;
+use crate::pe::section::{Import, ImportDirectoryTable};
 use scroll::Pread;
 use serde::{Deserialize, Serialize};
 
@@ -11,18 +33,129 @@ use crate::pe::display;
 pub struct PE {
     pub headers: Headers,
     pub import_directory_table: ImportDirectoryTable,
+    pub imports: Vec<Import>,
+    pub export_directory: Option<ExportDirectory>,
+    pub delay_import_directory: Option<DelayImportDirectory>,
+    pub bound_import_directory: Option<BoundImportDirectory>,
+    pub resource_directory: Option<ResourceDirectory>,
+    pub version_info: Option<VersionInfo>,
+    pub manifest: Option<Manifest>,
+    pub string_tables: Vec<LocalizedStrings>,
+    pub message_tables: Vec<LocalizedStrings>,
+    pub dialogs: Vec<Dialog>,
+    pub menus: Vec<Menu>,
+    pub accelerator_tables: Vec<AcceleratorTable>,
+    pub exception_directory: Option<ExceptionDirectory>,
+    pub base_relocation_directory: Option<BaseRelocationDirectory>,
+    pub debug_directory: Option<DebugDirectory>,
+    pub tls_directory: Option<TlsDirectory>,
+    pub load_config_directory: Option<LoadConfigDirectory>,
+    pub control_flow_guard: Option<ControlFlowGuard>,
+    pub safe_seh: Option<SafeSeh>,
+    pub dynamic_relocation_table: Option<DynamicRelocationTable>,
+    pub hybrid_metadata: Option<HybridMetadata>,
+    pub clr_directory: Option<ClrDirectory>,
+    pub clr_metadata: Option<ClrMetadata>,
+    pub ready_to_run: Option<ReadyToRunHeader>,
+    pub bundle: Option<Bundle>,
 }
 
 impl PE {
+    /// Only the headers are required, an optional directory that cannot be read is left out
+    /// and the parsers keep what they decoded of a partly malformed one
     pub fn new(bytes: &[u8]) -> error::Result<Self> {
         let headers:Headers = Headers::parse(&bytes)?;
-        let import_directory_table_offset:usize = headers.clone().get_imports_offset().unwrap();
-        let import_directory_table:ImportDirectoryTable = bytes.pread_with::<ImportDirectoryTable>(import_directory_table_offset, scroll::LE)?;
+        let import_directory_table:ImportDirectoryTable = headers.get_data_directory(IMAGE_DIRECTORY_ENTRY_IMPORT)
+            .and_then(|data_directory| headers.rva_to_offset(data_directory.virtual_address))
+            .and_then(|import_directory_table_offset| bytes.pread_with::<ImportDirectoryTable>(import_directory_table_offset, scroll::LE).ok())
+            .unwrap_or_default();
+        let imports:Vec<Import> = import_directory_table.imports.iter()
+            .filter_map(|import_directory| Import::parse(bytes, &headers, import_directory).ok())
+            .collect();
+
+        let export_directory:Option<ExportDirectory> = headers.get_data_directory(IMAGE_DIRECTORY_ENTRY_EXPORT)
+            .and_then(|data_directory| ExportDirectory::parse(bytes, &headers, data_directory).ok());
+        let delay_import_directory:Option<DelayImportDirectory> = headers.get_data_directory(IMAGE_DIRECTORY_ENTRY_DELAY_IMPORT)
+            .and_then(|data_directory| DelayImportDirectory::parse(bytes, &headers, data_directory).ok());
+        let bound_import_directory:Option<BoundImportDirectory> = headers.get_data_directory(IMAGE_DIRECTORY_ENTRY_BOUND_IMPORT)
+            .and_then(|data_directory| BoundImportDirectory::parse(bytes, &headers, data_directory).ok());
+        let resource_directory:Option<ResourceDirectory> = headers.get_data_directory(IMAGE_DIRECTORY_ENTRY_RESOURCE)
+            .and_then(|data_directory| ResourceDirectory::parse(bytes, &headers, data_directory).ok());
+        let (version_info, manifest, string_tables, message_tables, dialogs, menus, accelerator_tables) = match resource_directory {
+            Some(ref resource_directory) => (
+                VersionInfo::from_resources(bytes, &headers, resource_directory).ok().flatten(),
+                Manifest::from_resources(bytes, &headers, resource_directory).ok().flatten(),
+                LocalizedStrings::string_tables(bytes, &headers, resource_directory).unwrap_or_default(),
+                LocalizedStrings::message_tables(bytes, &headers, resource_directory).unwrap_or_default(),
+                Dialog::from_resources(bytes, &headers, resource_directory).unwrap_or_default(),
+                Menu::from_resources(bytes, &headers, resource_directory).unwrap_or_default(),
+                AcceleratorTable::from_resources(bytes, &headers, resource_directory).unwrap_or_default()
+            ),
+            None => (None, None, Vec::new(), Vec::new(), Vec::new(), Vec::new(), Vec::new())
+        };
+        let exception_directory:Option<ExceptionDirectory> = headers.get_data_directory(IMAGE_DIRECTORY_ENTRY_EXCEPTION)
+            .and_then(|data_directory| ExceptionDirectory::parse(bytes, &headers, data_directory).ok());
+        let base_relocation_directory:Option<BaseRelocationDirectory> = headers.get_data_directory(IMAGE_DIRECTORY_ENTRY_BASERELOC)
+            .and_then(|data_directory| BaseRelocationDirectory::parse(bytes, &headers, data_directory).ok());
+        let debug_directory:Option<DebugDirectory> = headers.get_data_directory(IMAGE_DIRECTORY_ENTRY_DEBUG)
+            .and_then(|data_directory| DebugDirectory::parse(bytes, &headers, data_directory).ok());
+        let tls_directory:Option<TlsDirectory> = headers.get_data_directory(IMAGE_DIRECTORY_ENTRY_TLS)
+            .and_then(|data_directory| TlsDirectory::parse(bytes, &headers, data_directory).ok());
+        let load_config_directory:Option<LoadConfigDirectory> = headers.get_data_directory(IMAGE_DIRECTORY_ENTRY_LOAD_CONFIG)
+            .and_then(|data_directory| LoadConfigDirectory::parse(bytes, &headers, data_directory).ok());
+        // Tables referenced by the load configuration are read independently from it and from each other
+        let control_flow_guard:Option<ControlFlowGuard> = load_config_directory.as_ref()
+            .and_then(|load_config_directory| ControlFlowGuard::parse(bytes, &headers, load_config_directory).ok().flatten());
+        let safe_seh:Option<SafeSeh> = SafeSeh::parse(bytes, &headers, load_config_directory.as_ref()).ok().flatten();
+        let dynamic_relocation_table:Option<DynamicRelocationTable> = load_config_directory.as_ref()
+            .and_then(|load_config_directory| DynamicRelocationTable::parse(bytes, &headers, load_config_directory).ok().flatten());
+        let hybrid_metadata:Option<HybridMetadata> = load_config_directory.as_ref()
+            .and_then(|load_config_directory| HybridMetadata::parse(bytes, &headers, load_config_directory).ok().flatten());
+        let clr_directory:Option<ClrDirectory> = headers.get_data_directory(IMAGE_DIRECTORY_ENTRY_COM_DESCRIPTOR)
+            .and_then(|data_directory| ClrDirectory::parse(bytes, &headers, data_directory).ok());
+        let clr_metadata:Option<ClrMetadata> = match clr_directory {
+            Some(ref clr_directory) if clr_directory.header.metadata.virtual_address != 0 => ClrMetadata::parse(bytes, &headers, clr_directory.header.metadata).ok(),
+            _ => None
+        };
+        let ready_to_run:Option<ReadyToRunHeader> = match clr_directory {
+            Some(ref clr_directory) if clr_directory.header.managed_native_header.virtual_address != 0 => ReadyToRunHeader::parse(bytes, &headers, clr_directory.header.managed_native_header).ok().flatten(),
+            _ => None
+        };
+        let bundle:Option<Bundle> = Bundle::parse(bytes, &headers).ok().flatten();
 
         Ok(PE {
             headers,
-            import_directory_table
+            import_directory_table,
+            imports,
+            export_directory,
+            delay_import_directory,
+            bound_import_directory,
+            resource_directory,
+            version_info,
+            manifest,
+            string_tables,
+            message_tables,
+            dialogs,
+            menus,
+            accelerator_tables,
+            exception_directory,
+            base_relocation_directory,
+            debug_directory,
+            tls_directory,
+            load_config_directory,
+            control_flow_guard,
+            safe_seh,
+            dynamic_relocation_table,
+            hybrid_metadata,
+            clr_directory,
+            clr_metadata,
+            ready_to_run,
+            bundle
         })
     }
+    /// True for managed images carrying a CLR runtime header
+    pub fn is_dotnet(&self) -> bool {
+        self.clr_directory.is_some()
+    }
 }
 
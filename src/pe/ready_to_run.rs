@@ -0,0 +1,478 @@
+use scroll::{IOread, IOwrite, Pread, Pwrite, SizeWith};
+use scroll::ctx::SizeWith as _;
+use serde::{Deserialize, Serialize, Serializer};
+use serde::ser::SerializeStruct;
+
+use crate::error;
+use crate::pe::header::{DataDirectory, Headers, IMAGE_FILE_MACHINE_AMD64, IMAGE_FILE_MACHINE_ARM64, IMAGE_FILE_MACHINE_ARMNT, IMAGE_FILE_MACHINE_I386};
+use crate::pe::index;
+
+/// "RTR"
+pub const READYTORUN_SIGNATURE: u32 = 0x00525452;
+
+pub const READYTORUN_SECTION_COMPILER_IDENTIFIER: u32 = 100;
+pub const READYTORUN_SECTION_IMPORT_SECTIONS: u32 = 101;
+pub const READYTORUN_SECTION_RUNTIME_FUNCTIONS: u32 = 102;
+pub const READYTORUN_SECTION_METHODDEF_ENTRYPOINTS: u32 = 103;
+pub const READYTORUN_SECTION_AVAILABLE_TYPES: u32 = 108;
+pub const READYTORUN_SECTION_INSTANCE_METHOD_ENTRYPOINTS: u32 = 109;
+
+/// XORed into the COFF machine of images compiled for Apple, FreeBSD, Linux, NetBSD and SunOS
+pub const READYTORUN_MACHINE_OS_OVERRIDES: [u16; 5] = [0x4644, 0xadc4, 0x7b79, 0x1993, 0x1992];
+
+/// Elements of a native array are grouped in blocks indexed by a binary tree
+const NATIVE_ARRAY_BLOCK_SIZE: u32 = 16;
+/// MethodDef tokens hold the row number in their low 24 bits
+const METHODDEF_MAX_ROWS: u32 = 0x00ff_ffff;
+
+/// COFF machine of the precompiled code, without the OS override of non Windows images
+pub fn get_native_machine(machine: u16) -> u16 {
+    let machines:[u16; 4] = [IMAGE_FILE_MACHINE_I386, IMAGE_FILE_MACHINE_ARMNT, IMAGE_FILE_MACHINE_AMD64, IMAGE_FILE_MACHINE_ARM64];
+    READYTORUN_MACHINE_OS_OVERRIDES.iter()
+        .map(|os| machine ^ os)
+        .find(|native| !machines.contains(&machine) && machines.contains(native))
+        .unwrap_or(machine)
+}
+
+#[derive(Debug, PartialEq, Copy, Clone, Default, Pread, Pwrite, IOread, IOwrite, SizeWith, Serialize, Deserialize)]
+pub struct ReadyToRunHeaderFields {
+    pub signature: u32,
+    pub major_version: u16,
+    pub minor_version: u16,
+    pub flags: u32,
+    pub number_of_sections: u32,
+}
+
+impl ReadyToRunHeaderFields {
+    pub fn parse(bytes: &[u8], offset: &mut usize) -> error::Result<Self> {
+        Ok(bytes.gread_with(offset, scroll::LE)?)
+    }
+}
+
+#[derive(Debug, PartialEq, Copy, Clone, Default, Pread, Pwrite, IOread, IOwrite, SizeWith, Deserialize)]
+pub struct ReadyToRunSection {
+    pub section_type: u32,
+    pub section: DataDirectory,
+}
+
+impl Serialize for ReadyToRunSection {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("ReadyToRunSection", 3)?;
+        state.serialize_field("section_type", &self.section_type)?;
+        state.serialize_field("section_type_name", index::READYTORUNSECTION.get(&self.section_type).unwrap_or(&"UNKNOWN"))?;
+        state.serialize_field("section", &self.section)?;
+        state.end()
+    }
+}
+
+impl ReadyToRunSection {
+    pub fn parse(bytes: &[u8], offset: &mut usize) -> error::Result<Self> {
+        Ok(bytes.gread_with(offset, scroll::LE)?)
+    }
+}
+
+/// READYTORUN_IMPORT_SECTION, a group of cells fixed up lazily or eagerly by the runtime
+#[derive(Debug, PartialEq, Copy, Clone, Default, Pread, Pwrite, IOread, IOwrite, SizeWith, Deserialize)]
+pub struct ReadyToRunImportSection {
+    pub section: DataDirectory,
+    pub flags: u16,
+    pub import_type: u8,
+    pub entry_size: u8,
+    pub signatures: u32,
+    pub auxiliary_data: u32,
+}
+
+impl Serialize for ReadyToRunImportSection {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("ReadyToRunImportSection", 6)?;
+        state.serialize_field("section", &self.section)?;
+        state.serialize_field("flags", &format!("0x{:x}", &self.flags))?;
+        state.serialize_field("import_type", &self.import_type)?;
+        state.serialize_field("entry_size", &self.entry_size)?;
+        state.serialize_field("signatures", &format!("0x{:x}", &self.signatures))?;
+        state.serialize_field("auxiliary_data", &format!("0x{:x}", &self.auxiliary_data))?;
+        state.end()
+    }
+}
+
+impl ReadyToRunImportSection {
+    pub fn parse(bytes: &[u8], offset: &mut usize) -> error::Result<Self> {
+        Ok(bytes.gread_with(offset, scroll::LE)?)
+    }
+}
+
+/// Precompiled code of a MethodDef
+#[derive(Debug, PartialEq, Clone, Default, Deserialize)]
+pub struct MethodEntryPoint {
+    pub method_token: u32,
+    /// Index in the RuntimeFunctions section
+    pub runtime_function: u32,
+    pub rva: Option<u32>,
+    pub has_fixups: bool,
+}
+
+impl Serialize for MethodEntryPoint {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("MethodEntryPoint", 4)?;
+        state.serialize_field("method_token", &format!("0x{:08x}", &self.method_token))?;
+        state.serialize_field("runtime_function", &self.runtime_function)?;
+        state.serialize_field("rva", &self.rva.map(|rva| format!("0x{:x}", rva)))?;
+        state.serialize_field("has_fixups", &self.has_fixups)?;
+        state.end()
+    }
+}
+
+/// Decodes a NativeFormat unsigned integer, returns it with the offset following it
+fn decode_unsigned(bytes: &[u8], offset: usize) -> error::Result<(u32, usize)> {
+    let first:u32 = u32::from(bytes.pread_with::<u8>(offset, scroll::LE)?);
+    let byte = |index: usize| -> error::Result<u32> { Ok(u32::from(bytes.pread_with::<u8>(offset + index, scroll::LE)?)) };
+    Ok(if first & 1 == 0 {
+        (first >> 1, offset + 1)
+    } else if first & 2 == 0 {
+        ((first >> 2) | (byte(1)? << 6), offset + 2)
+    } else if first & 4 == 0 {
+        ((first >> 3) | (byte(1)? << 5) | (byte(2)? << 13), offset + 3)
+    } else if first & 8 == 0 {
+        ((first >> 4) | (byte(1)? << 4) | (byte(2)? << 12) | (byte(3)? << 20), offset + 4)
+    } else if first & 16 == 0 {
+        (bytes.pread_with(offset + 1, scroll::LE)?, offset + 5)
+    } else {
+        return Err(error::Error::Malformed(format!("Bad NativeFormat integer at offset 0x{:x}", offset)));
+    })
+}
+
+/// Decodes a NativeFormat signed integer, returns it with the offset following it
+fn decode_signed(bytes: &[u8], offset: usize) -> error::Result<(i32, usize)> {
+    let first:u8 = bytes.pread_with(offset, scroll::LE)?;
+    let byte = |index: usize| -> error::Result<i32> { Ok(i32::from(bytes.pread_with::<u8>(offset + index, scroll::LE)?)) };
+    let signed = |index: usize| -> error::Result<i32> { Ok(i32::from(bytes.pread_with::<i8>(offset + index, scroll::LE)?)) };
+    let low:i32 = i32::from(first);
+    Ok(if first & 1 == 0 {
+        (i32::from(first as i8) >> 1, offset + 1)
+    } else if first & 2 == 0 {
+        ((low >> 2) | (signed(1)? << 6), offset + 2)
+    } else if first & 4 == 0 {
+        ((low >> 3) | (byte(1)? << 5) | (signed(2)? << 13), offset + 3)
+    } else if first & 8 == 0 {
+        ((low >> 4) | (byte(1)? << 4) | (byte(2)? << 12) | (signed(3)? << 20), offset + 4)
+    } else if first & 16 == 0 {
+        (bytes.pread_with(offset + 1, scroll::LE)?, offset + 5)
+    } else {
+        return Err(error::Error::Malformed(format!("Bad NativeFormat integer at offset 0x{:x}", offset)));
+    })
+}
+
+fn read_entry_index(bytes: &[u8], base: usize, entry_index_size: u32, index: usize) -> error::Result<usize> {
+    Ok(match entry_index_size {
+        0 => bytes.pread_with::<u8>(base + index, scroll::LE)? as usize,
+        1 => bytes.pread_with::<u16>(base + 2 * index, scroll::LE)? as usize,
+        _ => bytes.pread_with::<u32>(base + 4 * index, scroll::LE)? as usize
+    })
+}
+
+/// Sparse array of the NativeFormat, used for the MethodDef entry points
+struct NativeArray<'a> {
+    bytes: &'a [u8],
+    base: usize,
+    element_count: u32,
+    entry_index_size: u32,
+}
+
+impl<'a> NativeArray<'a> {
+    fn new(bytes: &'a [u8], offset: usize) -> error::Result<Self> {
+        let (header, base) = decode_unsigned(bytes, offset)?;
+        Ok(NativeArray { bytes, base, element_count: header >> 2, entry_index_size: header & 3 })
+    }
+    /// Offset of the element at `index`, `None` when the array has a hole there
+    fn get(&self, index: u32) -> error::Result<Option<usize>> {
+        if index >= self.element_count {
+            return Ok(None);
+        }
+        let mut offset:usize = self.base + read_entry_index(self.bytes, self.base, self.entry_index_size, (index / NATIVE_ARRAY_BLOCK_SIZE) as usize)?;
+        let mut bit:u32 = NATIVE_ARRAY_BLOCK_SIZE >> 1;
+        while bit > 0 {
+            let (value, next) = decode_unsigned(self.bytes, offset)?;
+            if index & bit != 0 {
+                if value & 2 != 0 {
+                    offset += (value >> 2) as usize;
+                    bit >>= 1;
+                    continue;
+                }
+            } else if value & 1 != 0 {
+                offset = next;
+                bit >>= 1;
+                continue;
+            }
+            // Leaf holding a single element of the block
+            if value & 3 == 0 && value >> 2 == index & (NATIVE_ARRAY_BLOCK_SIZE - 1) {
+                return Ok(Some(next));
+            }
+            return Ok(None);
+        }
+        Ok(Some(offset))
+    }
+}
+
+/// Offsets of every entry of a NativeFormat hashtable
+fn native_hashtable_entries(bytes: &[u8], offset: usize) -> error::Result<Vec<usize>> {
+    let header:u8 = bytes.pread_with(offset, scroll::LE)?;
+    let base:usize = offset + 1;
+    let bucket_shift:u8 = header >> 2;
+    if bucket_shift > 24 {
+        return Err(error::Error::Malformed(format!("Too many hashtable buckets at offset 0x{:x}", offset)));
+    }
+    let entry_index_size:u32 = u32::from(header & 3);
+    let mut entries:Vec<usize> = Vec::new();
+    for bucket in 0..1usize << bucket_shift {
+        let end:usize = base + read_entry_index(bytes, base, entry_index_size, bucket + 1)?;
+        let mut position:usize = base + read_entry_index(bytes, base, entry_index_size, bucket)?;
+        while position < end {
+            // Low byte of the hashcode followed by the relative offset of the entry
+            let (delta, next) = decode_signed(bytes, position + 1)?;
+            entries.push((position as i64 + 1 + i64::from(delta)) as usize);
+            position = next;
+        }
+    }
+    Ok(entries)
+}
+
+/// READYTORUN_HEADER of crossgen compiled assemblies
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct ReadyToRunHeader {
+    pub header: ReadyToRunHeaderFields,
+    pub flag_names: Vec<String>,
+    pub sections: Vec<ReadyToRunSection>,
+    pub compiler_identifier: Option<String>,
+    pub import_sections: Vec<ReadyToRunImportSection>,
+    pub method_entry_points: Vec<MethodEntryPoint>,
+    /// TypeDef or ExportedType tokens of the types with precompiled layouts
+    pub available_types: Vec<u32>,
+    /// Number of precompiled generic instantiations
+    pub instance_entry_point_count: usize,
+}
+
+impl ReadyToRunHeader {
+    /// Parses the header pointed by ManagedNativeHeader, `None` for non ReadyToRun native images
+    pub fn parse(bytes: &[u8], headers: &Headers, data_directory: DataDirectory) -> error::Result<Option<Self>> {
+        let mut offset:usize = headers.rva_to_offset(data_directory.virtual_address).ok_or(error::Error::BadRva(data_directory.virtual_address))?;
+        let header:ReadyToRunHeaderFields = ReadyToRunHeaderFields::parse(bytes, &mut offset)?;
+        if header.signature != READYTORUN_SIGNATURE {
+            return Ok(None);
+        }
+        let mut flag_names:Vec<(&u32, &&str)> = index::READYTORUNFLAG.iter().filter(|(flag, _)| header.flags & **flag != 0).collect();
+        flag_names.sort();
+        let mut ready_to_run:ReadyToRunHeader = ReadyToRunHeader {
+            header,
+            flag_names: flag_names.into_iter().map(|(_, name)| name.to_string()).collect(),
+            ..Default::default()
+        };
+        // A section entry cut by the end of the file ends the table, the entries before it are kept
+        for _ in 0..header.number_of_sections {
+            match ReadyToRunSection::parse(bytes, &mut offset) {
+                Ok(section) => ready_to_run.sections.push(section),
+                Err(_) => break
+            }
+        }
+
+        // Each section is decoded on its own, one that cannot be read is left out
+        if let Some(section) = ready_to_run.get_section(READYTORUN_SECTION_COMPILER_IDENTIFIER) {
+            ready_to_run.compiler_identifier = headers.rva_to_offset(section.virtual_address)
+                .and_then(|offset| bytes.get(offset..(offset + section.size as usize).min(bytes.len())))
+                .map(|identifier| {
+                    let end:usize = identifier.iter().position(|&c| c == 0).unwrap_or(identifier.len());
+                    String::from_utf8_lossy(&identifier[..end]).into_owned()
+                });
+        }
+        if let Some(section) = ready_to_run.get_section(READYTORUN_SECTION_IMPORT_SECTIONS) {
+            if let Some(mut offset) = headers.rva_to_offset(section.virtual_address) {
+                for _ in 0..section.size as usize / ReadyToRunImportSection::size_with(&scroll::LE) {
+                    match ReadyToRunImportSection::parse(bytes, &mut offset) {
+                        Ok(import_section) => ready_to_run.import_sections.push(import_section),
+                        Err(_) => break
+                    }
+                }
+            }
+        }
+        if let Some(section) = ready_to_run.get_section(READYTORUN_SECTION_METHODDEF_ENTRYPOINTS) {
+            let _ = ready_to_run.parse_method_entry_points(bytes, headers, section);
+        }
+        if let Some(section) = ready_to_run.get_section(READYTORUN_SECTION_AVAILABLE_TYPES) {
+            let _ = ready_to_run.parse_available_types(bytes, headers, section);
+        }
+        if let Some(section) = ready_to_run.get_section(READYTORUN_SECTION_INSTANCE_METHOD_ENTRYPOINTS) {
+            ready_to_run.instance_entry_point_count = headers.rva_to_offset(section.virtual_address)
+                .and_then(|offset| native_hashtable_entries(bytes, offset).ok())
+                .map_or(0, |entries| entries.len());
+        }
+        Ok(Some(ready_to_run))
+    }
+    /// Entry points are kept up to the first one that cannot be decoded
+    fn parse_method_entry_points(&mut self, bytes: &[u8], headers: &Headers, section: DataDirectory) -> error::Result<()> {
+        let offset:usize = headers.rva_to_offset(section.virtual_address).ok_or(error::Error::BadRva(section.virtual_address))?;
+        let array:NativeArray = NativeArray::new(bytes, offset)?;
+        // Every block of the array needs at least one index byte
+        if u64::from(array.element_count) > u64::from(section.size) * u64::from(NATIVE_ARRAY_BLOCK_SIZE) {
+            return Err(error::Error::Malformed(format!("MethodDef entry points array of {} elements too large", array.element_count)));
+        }
+        let runtime_functions:Option<DataDirectory> = self.get_section(READYTORUN_SECTION_RUNTIME_FUNCTIONS);
+        let runtime_function_size:u32 = if get_native_machine(headers.coff.machine) == IMAGE_FILE_MACHINE_AMD64 { 12 } else { 8 };
+        for index in 0..array.element_count.min(METHODDEF_MAX_ROWS) {
+            let offset:usize = match array.get(index)? {
+                Some(offset) => offset,
+                None => continue
+            };
+            let (id, _) = decode_unsigned(bytes, offset)?;
+            let runtime_function:u32 = if id & 1 != 0 { id >> 2 } else { id >> 1 };
+            let rva:Option<u32> = runtime_functions
+                .filter(|runtime_functions| u64::from(runtime_function + 1) * u64::from(runtime_function_size) <= u64::from(runtime_functions.size))
+                .and_then(|runtime_functions| headers.rva_to_offset(runtime_functions.virtual_address.wrapping_add(runtime_function * runtime_function_size)))
+                .and_then(|offset| bytes.pread_with(offset, scroll::LE).ok());
+            self.method_entry_points.push(MethodEntryPoint {
+                method_token: 0x0600_0000 | (index + 1),
+                runtime_function,
+                rva,
+                has_fixups: id & 3 == 3
+            });
+        }
+        Ok(())
+    }
+    /// Types are kept up to the first entry that cannot be decoded
+    fn parse_available_types(&mut self, bytes: &[u8], headers: &Headers, section: DataDirectory) -> error::Result<()> {
+        let offset:usize = headers.rva_to_offset(section.virtual_address).ok_or(error::Error::BadRva(section.virtual_address))?;
+        for entry in native_hashtable_entries(bytes, offset)? {
+            let (value, _) = decode_unsigned(bytes, entry)?;
+            let table:u32 = if value & 1 != 0 { 0x27 } else { 0x02 };
+            self.available_types.push(table << 24 | (value >> 1));
+        }
+        Ok(())
+    }
+    pub fn get_section(&self, section_type: u32) -> Option<DataDirectory> {
+        self.sections.iter()
+            .find(|section| section.section_type == section_type && section.section.virtual_address != 0)
+            .map(|section| section.section)
+    }
+    /// ReadyToRun format version, tied to the runtime that produced the image
+    pub fn get_version(&self) -> String {
+        format!("{}.{}", self.header.major_version, self.header.minor_version)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use scroll::Pwrite;
+
+    use super::{ReadyToRunHeader, ReadyToRunHeaderFields, ReadyToRunImportSection, ReadyToRunSection, READYTORUN_SIGNATURE};
+    use crate::pe::clr::ClrHeader;
+    use crate::pe::header::{DataDirectory, Headers, test_headers, test_image, IMAGE_DIRECTORY_ENTRY_COM_DESCRIPTOR, IMAGE_FILE_MACHINE_AMD64};
+    use crate::pe::pe::PE;
+
+    fn image() -> (Headers, Vec<u8>) {
+        let mut headers = test_headers(0x2000, 0x400);
+        headers.coff.machine = IMAGE_FILE_MACHINE_AMD64;
+        let mut bytes = vec![0u8; 0x400];
+        bytes.pwrite_with(ReadyToRunHeaderFields { signature: READYTORUN_SIGNATURE, major_version: 9, minor_version: 2, flags: 0x4, number_of_sections: 6 }, 0, scroll::LE).unwrap();
+        let sections = [(100, 0x2100, 14), (101, 0x2140, 20), (102, 0x2180, 24), (103, 0x21c0, 8), (108, 0x2200, 6), (109, 0x2200, 6)];
+        for (index, &(section_type, virtual_address, size)) in sections.iter().enumerate() {
+            bytes.pwrite_with(ReadyToRunSection { section_type, section: DataDirectory { virtual_address, size } }, 16 + index * 12, scroll::LE).unwrap();
+        }
+        bytes[0x100..0x10d].copy_from_slice(b"Crossgen2 9.0");
+        bytes.pwrite_with(ReadyToRunImportSection { section: DataDirectory { virtual_address: 0x2300, size: 16 }, entry_size: 8, ..Default::default() }, 0x140, scroll::LE).unwrap();
+        bytes.pwrite_with(0x3000u32, 0x180, scroll::LE).unwrap();
+        bytes.pwrite_with(0x3100u32, 0x18c, scroll::LE).unwrap();
+        // Two elements: header, block index, three left nodes, a node with both children and the two entries
+        bytes[0x1c0..0x1c8].copy_from_slice(&[0x10, 1, 2, 2, 2, 22, 0, 14]);
+        // One bucket holding one entry pointing at TypeDef 2
+        bytes[0x200..0x206].copy_from_slice(&[0, 2, 4, 0x55, 2, 8]);
+        (headers, bytes)
+    }
+
+    #[test]
+    fn ready_to_run() {
+        let (headers, mut bytes) = image();
+        let ready_to_run = ReadyToRunHeader::parse(&bytes, &headers, DataDirectory { virtual_address: 0x2000, size: 88 }).unwrap().unwrap();
+        assert_eq!(ready_to_run.get_version(), "9.2");
+        assert_eq!(ready_to_run.flag_names, vec!["READYTORUN_FLAG_PARTIAL"]);
+        assert_eq!(ready_to_run.compiler_identifier.as_deref(), Some("Crossgen2 9.0"));
+        assert_eq!(ready_to_run.import_sections[0].entry_size, 8);
+        assert_eq!(ready_to_run.method_entry_points.len(), 2);
+        assert_eq!(ready_to_run.method_entry_points[0].method_token, 0x06000001);
+        assert_eq!(ready_to_run.method_entry_points[0].rva, Some(0x3000));
+        assert_eq!(ready_to_run.method_entry_points[1].runtime_function, 1);
+        assert_eq!(ready_to_run.method_entry_points[1].rva, Some(0x3100));
+        assert!(ready_to_run.method_entry_points[1].has_fixups);
+        assert_eq!(ready_to_run.available_types, vec![0x02000002]);
+        assert_eq!(ready_to_run.instance_entry_point_count, 1);
+
+        bytes.pwrite_with(0u32, 0, scroll::LE).unwrap();
+        assert!(ReadyToRunHeader::parse(&bytes, &headers, DataDirectory { virtual_address: 0x2000, size: 88 }).unwrap().is_none());
+    }
+
+    #[test]
+    fn ready_to_run_linux_x64() {
+        let (mut headers, bytes) = image();
+        headers.coff.machine = 0xfd1d;
+        assert_eq!(super::get_native_machine(headers.coff.machine), IMAGE_FILE_MACHINE_AMD64);
+        let ready_to_run = ReadyToRunHeader::parse(&bytes, &headers, DataDirectory { virtual_address: 0x2000, size: 88 }).unwrap().unwrap();
+        assert_eq!(ready_to_run.method_entry_points[1].rva, Some(0x3100));
+    }
+
+    #[test]
+    fn ready_to_run_malformed() {
+        let data_directory = DataDirectory { virtual_address: 0x2000, size: 88 };
+        // sections table past the end of the file
+        let (headers, mut bytes) = image();
+        bytes.pwrite_with(0x1000_0000u32, 12, scroll::LE).unwrap();
+        let ready_to_run = ReadyToRunHeader::parse(&bytes, &headers, data_directory).unwrap().unwrap();
+        assert_eq!(ready_to_run.compiler_identifier.as_deref(), Some("Crossgen2 9.0"));
+        assert_eq!(ready_to_run.method_entry_points.len(), 2);
+
+        // reserved NativeFormat integer encoding
+        let (headers, mut bytes) = image();
+        bytes[0x1c0] = 0x1f;
+        let ready_to_run = ReadyToRunHeader::parse(&bytes, &headers, data_directory).unwrap().unwrap();
+        assert!(ready_to_run.method_entry_points.is_empty());
+        assert_eq!(ready_to_run.available_types, vec![0x02000002]);
+
+        // hashtable with 2^63 buckets
+        let (headers, mut bytes) = image();
+        bytes[0x200] = 0xfc;
+        let ready_to_run = ReadyToRunHeader::parse(&bytes, &headers, data_directory).unwrap().unwrap();
+        assert!(ready_to_run.available_types.is_empty());
+        assert_eq!(ready_to_run.instance_entry_point_count, 0);
+        assert_eq!(ready_to_run.method_entry_points.len(), 2);
+
+        // compiler identifier outside of the image, then cut by the end of the file
+        let (headers, mut bytes) = image();
+        bytes.pwrite_with(0x9000u32, 16 + 4, scroll::LE).unwrap();
+        let ready_to_run = ReadyToRunHeader::parse(&bytes, &headers, data_directory).unwrap().unwrap();
+        assert_eq!(ready_to_run.compiler_identifier, None);
+        assert_eq!(ready_to_run.import_sections.len(), 1);
+        bytes.pwrite_with(0x23f8u32, 16 + 4, scroll::LE).unwrap();
+        bytes[0x3f8..0x400].copy_from_slice(b"Crossgen");
+        let ready_to_run = ReadyToRunHeader::parse(&bytes, &headers, data_directory).unwrap().unwrap();
+        assert_eq!(ready_to_run.compiler_identifier.as_deref(), Some("Crossgen"));
+    }
+
+    #[test]
+    fn ready_to_run_truncated_image() {
+        let mut data = vec![0u8; 0x200];
+        data.pwrite_with(ClrHeader { cb: 72, managed_native_header: DataDirectory { virtual_address: 0x1100, size: 0x1c }, ..Default::default() }, 0, scroll::LE).unwrap();
+        // the section count runs the table past the end of the file
+        data.pwrite_with(ReadyToRunHeaderFields { signature: READYTORUN_SIGNATURE, major_version: 9, number_of_sections: 0x100, ..Default::default() }, 0x100, scroll::LE).unwrap();
+        data.pwrite_with(ReadyToRunSection { section_type: 100, section: DataDirectory { virtual_address: 0x11f0, size: 4 } }, 0x110, scroll::LE).unwrap();
+        data[0x1f0..0x1f3].copy_from_slice(b"R2R");
+        let com_descriptor = DataDirectory { virtual_address: 0x1000, size: 72 };
+        let pe = PE::new(&test_image(&[(IMAGE_DIRECTORY_ENTRY_COM_DESCRIPTOR, com_descriptor)], &data)).unwrap();
+        assert!(pe.is_dotnet());
+        assert_eq!(pe.ready_to_run.unwrap().compiler_identifier.as_deref(), Some("R2R"));
+    }
+}
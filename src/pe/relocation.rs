@@ -0,0 +1,232 @@
+use scroll::{IOread, IOwrite, Pread, Pwrite, SizeWith};
+use scroll::ctx::SizeWith as _;
+use serde::{Deserialize, Serialize, Serializer};
+use serde::ser::SerializeStruct;
+
+use crate::error;
+use crate::pe::header::{DataDirectory, Headers, IMAGE_FILE_MACHINE_ARMNT, IMAGE_FILE_MACHINE_RISCV32, IMAGE_FILE_MACHINE_RISCV64, IMAGE_FILE_MACHINE_RISCV128};
+
+pub const IMAGE_REL_BASED_ABSOLUTE: u8 = 0;
+pub const IMAGE_REL_BASED_HIGH: u8 = 1;
+pub const IMAGE_REL_BASED_LOW: u8 = 2;
+pub const IMAGE_REL_BASED_HIGHLOW: u8 = 3;
+pub const IMAGE_REL_BASED_HIGHADJ: u8 = 4;
+pub const IMAGE_REL_BASED_MACHINE_SPECIFIC_5: u8 = 5;
+pub const IMAGE_REL_BASED_RESERVED: u8 = 6;
+pub const IMAGE_REL_BASED_MACHINE_SPECIFIC_7: u8 = 7;
+pub const IMAGE_REL_BASED_MACHINE_SPECIFIC_8: u8 = 8;
+pub const IMAGE_REL_BASED_MACHINE_SPECIFIC_9: u8 = 9;
+pub const IMAGE_REL_BASED_DIR64: u8 = 10;
+
+/// Header of a relocation block, followed by 16-bit entries for one 4K page
+#[derive(Debug, PartialEq, Copy, Clone, Default, Pread, Pwrite, IOread, IOwrite, SizeWith, Deserialize)]
+pub struct BaseRelocationBlock {
+    pub page_rva: u32,
+    pub block_size: u32,
+}
+
+impl Serialize for BaseRelocationBlock {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("BaseRelocationBlock", 2)?;
+        state.serialize_field("page_rva", &format!("0x{:x}", &self.page_rva))?;
+        state.serialize_field("block_size", &self.block_size)?;
+        state.end()
+    }
+}
+
+impl BaseRelocationBlock {
+    pub fn parse(bytes: &[u8], offset: &mut usize) -> error::Result<Self> {
+        Ok(bytes.gread_with(offset, scroll::LE)?)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Default, Deserialize)]
+pub struct BaseRelocationEntry {
+    pub relocation_type: u8,
+    /// Offset within the page
+    pub offset: u16,
+    pub rva: u32,
+    /// Low 16 bits of the adjustment, stored in the slot following an IMAGE_REL_BASED_HIGHADJ entry
+    pub high_adjust: Option<u16>,
+    pub type_name: String,
+}
+
+impl Serialize for BaseRelocationEntry {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("BaseRelocationEntry", 5)?;
+        state.serialize_field("relocation_type", &self.relocation_type)?;
+        state.serialize_field("offset", &format!("0x{:x}", &self.offset))?;
+        state.serialize_field("rva", &format!("0x{:x}", &self.rva))?;
+        state.serialize_field("high_adjust", &self.high_adjust)?;
+        state.serialize_field("type_name", &self.type_name)?;
+        state.end()
+    }
+}
+
+/// Name of a relocation type, types 5, 7, 8 and 9 being reused by several architectures
+pub fn get_type_name(relocation_type: u8, machine: u16) -> &'static str {
+    let riscv:bool = machine == IMAGE_FILE_MACHINE_RISCV32 || machine == IMAGE_FILE_MACHINE_RISCV64 || machine == IMAGE_FILE_MACHINE_RISCV128;
+    match relocation_type {
+        IMAGE_REL_BASED_ABSOLUTE => "IMAGE_REL_BASED_ABSOLUTE",
+        IMAGE_REL_BASED_HIGH => "IMAGE_REL_BASED_HIGH",
+        IMAGE_REL_BASED_LOW => "IMAGE_REL_BASED_LOW",
+        IMAGE_REL_BASED_HIGHLOW => "IMAGE_REL_BASED_HIGHLOW",
+        IMAGE_REL_BASED_HIGHADJ => "IMAGE_REL_BASED_HIGHADJ",
+        IMAGE_REL_BASED_MACHINE_SPECIFIC_5 if machine == IMAGE_FILE_MACHINE_ARMNT => "IMAGE_REL_BASED_ARM_MOV32",
+        IMAGE_REL_BASED_MACHINE_SPECIFIC_5 if riscv => "IMAGE_REL_BASED_RISCV_HIGH20",
+        IMAGE_REL_BASED_MACHINE_SPECIFIC_5 => "IMAGE_REL_BASED_MIPS_JMPADDR",
+        IMAGE_REL_BASED_RESERVED => "IMAGE_REL_BASED_RESERVED",
+        IMAGE_REL_BASED_MACHINE_SPECIFIC_7 if riscv => "IMAGE_REL_BASED_RISCV_LOW12I",
+        IMAGE_REL_BASED_MACHINE_SPECIFIC_7 => "IMAGE_REL_BASED_THUMB_MOV32",
+        IMAGE_REL_BASED_MACHINE_SPECIFIC_8 if riscv => "IMAGE_REL_BASED_RISCV_LOW12S",
+        IMAGE_REL_BASED_MACHINE_SPECIFIC_8 => "IMAGE_REL_BASED_LOONGARCH_MARK_LA",
+        IMAGE_REL_BASED_MACHINE_SPECIFIC_9 => "IMAGE_REL_BASED_MIPS_JMPADDR16",
+        IMAGE_REL_BASED_DIR64 => "IMAGE_REL_BASED_DIR64",
+        _ => "UNKNOWN"
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct BaseRelocation {
+    pub block: BaseRelocationBlock,
+    pub entries: Vec<BaseRelocationEntry>,
+}
+
+impl BaseRelocation {
+    /// Parses one block and its entries, `machine` only affects the type names.
+    /// The block is truncated at the end of `bytes`
+    pub fn parse(bytes: &[u8], offset: &mut usize, machine: u16) -> error::Result<Self> {
+        let start:usize = *offset;
+        let block:BaseRelocationBlock = BaseRelocationBlock::parse(bytes, offset)?;
+        let header_size:usize = BaseRelocationBlock::size_with(&scroll::LE);
+        if (block.block_size as usize) < header_size {
+            return Err(error::Error::Malformed(format!("Relocation block at offset 0x{:x} too small", start)));
+        }
+        let end:usize = (start + block.block_size as usize).min(bytes.len());
+        let mut entries:Vec<BaseRelocationEntry> = Vec::new();
+        while *offset + 2 <= end {
+            let entry:u16 = bytes.gread_with(offset, scroll::LE)?;
+            let relocation_type:u8 = (entry >> 12) as u8;
+            let high_adjust:Option<u16> = if relocation_type == IMAGE_REL_BASED_HIGHADJ {
+                if *offset + 2 > end {
+                    break;
+                }
+                Some(bytes.gread_with(offset, scroll::LE)?)
+            } else {
+                None
+            };
+            entries.push(BaseRelocationEntry {
+                relocation_type,
+                offset: entry & 0xfff,
+                rva: block.page_rva.wrapping_add(u32::from(entry & 0xfff)),
+                high_adjust,
+                type_name: get_type_name(relocation_type, machine).to_string()
+            });
+        }
+        *offset = end;
+        Ok(BaseRelocation { block, entries })
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct BaseRelocationDirectory {
+    pub blocks: Vec<BaseRelocation>,
+}
+
+impl BaseRelocationDirectory {
+    pub fn parse(bytes: &[u8], headers: &Headers, data_directory: DataDirectory) -> error::Result<Self> {
+        let start:usize = headers.rva_to_offset(data_directory.virtual_address).ok_or(error::Error::BadRva(data_directory.virtual_address))?;
+        let end:usize = (start + data_directory.size as usize).min(bytes.len());
+        // Blocks are bounded by the directory, an oversized last one must not read what follows it
+        let bytes:&[u8] = &bytes[..end];
+        let mut offset:usize = start;
+        let mut blocks:Vec<BaseRelocation> = Vec::new();
+        while offset + BaseRelocationBlock::size_with(&scroll::LE) <= end {
+            // A block smaller than its header ends the table, the next one cannot be located
+            let block:BaseRelocationBlock = bytes.pread_with(offset, scroll::LE)?;
+            if (block.block_size as usize) < BaseRelocationBlock::size_with(&scroll::LE) {
+                break;
+            }
+            blocks.push(BaseRelocation::parse(bytes, &mut offset, headers.coff.machine)?);
+        }
+        Ok(BaseRelocationDirectory { blocks })
+    }
+    /// RVAs of every location the loader patches, padding entries excluded
+    pub fn fixups(&self) -> impl Iterator<Item = u32> + '_ {
+        self.blocks.iter()
+            .flat_map(|block| block.entries.iter())
+            .filter(|entry| entry.relocation_type != IMAGE_REL_BASED_ABSOLUTE)
+            .map(|entry| entry.rva)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use scroll::Pwrite;
+
+    use super::{BaseRelocationBlock, BaseRelocationDirectory, IMAGE_REL_BASED_DIR64, IMAGE_REL_BASED_HIGHADJ};
+    use crate::pe::header::{DataDirectory, IMAGE_FILE_MACHINE_ARMNT, test_headers};
+
+    #[test]
+    fn relocations() {
+        let mut headers = test_headers(0x1000, 0x200);
+        headers.coff.machine = IMAGE_FILE_MACHINE_ARMNT;
+        let mut bytes = vec![0u8; 0x200];
+        bytes.pwrite_with(BaseRelocationBlock { page_rva: 0x2000, block_size: 16 }, 0, scroll::LE).unwrap();
+        bytes.pwrite_with(0xa010u16, 8, scroll::LE).unwrap();
+        bytes.pwrite_with(0x4020u16, 10, scroll::LE).unwrap();
+        bytes.pwrite_with(0x8000u16, 12, scroll::LE).unwrap();
+        bytes.pwrite_with(0x0000u16, 14, scroll::LE).unwrap();
+        bytes.pwrite_with(BaseRelocationBlock { page_rva: 0x3000, block_size: 12 }, 16, scroll::LE).unwrap();
+        bytes.pwrite_with(0x5008u16, 24, scroll::LE).unwrap();
+        bytes.pwrite_with(0x7010u16, 26, scroll::LE).unwrap();
+
+        let directory = BaseRelocationDirectory::parse(&bytes, &headers, DataDirectory { virtual_address: 0x1000, size: 28 }).unwrap();
+        assert_eq!(directory.blocks.len(), 2);
+        let first = &directory.blocks[0].entries;
+        assert_eq!(first.len(), 3);
+        assert_eq!(first[0].relocation_type, IMAGE_REL_BASED_DIR64);
+        assert_eq!(first[1].relocation_type, IMAGE_REL_BASED_HIGHADJ);
+        assert_eq!(first[1].high_adjust, Some(0x8000));
+        assert_eq!(directory.blocks[1].entries[0].type_name, "IMAGE_REL_BASED_ARM_MOV32");
+        assert_eq!(directory.blocks[1].entries[1].type_name, "IMAGE_REL_BASED_THUMB_MOV32");
+        assert_eq!(directory.fixups().collect::<Vec<u32>>(), vec![0x2010, 0x2020, 0x3008, 0x3010]);
+    }
+
+    #[test]
+    fn relocations_short_block() {
+        let mut headers = test_headers(0x1000, 0x200);
+        headers.coff.machine = IMAGE_FILE_MACHINE_ARMNT;
+        let mut bytes = vec![0u8; 0x200];
+        bytes.pwrite_with(BaseRelocationBlock { page_rva: 0x2000, block_size: 10 }, 0, scroll::LE).unwrap();
+        bytes.pwrite_with(0xa010u16, 8, scroll::LE).unwrap();
+        bytes.pwrite_with(BaseRelocationBlock { page_rva: 0x3000, block_size: 4 }, 10, scroll::LE).unwrap();
+        bytes.pwrite_with(BaseRelocationBlock { page_rva: 0x4000, block_size: 10 }, 18, scroll::LE).unwrap();
+
+        let directory = BaseRelocationDirectory::parse(&bytes, &headers, DataDirectory { virtual_address: 0x1000, size: 28 }).unwrap();
+        assert_eq!(directory.blocks.len(), 1);
+        assert_eq!(directory.fixups().collect::<Vec<u32>>(), vec![0x2010]);
+    }
+
+    #[test]
+    fn relocations_oversized_block() {
+        let headers = test_headers(0x1000, 0x200);
+        let mut bytes = vec![0u8; 0x200];
+        bytes.pwrite_with(BaseRelocationBlock { page_rva: 0x2000, block_size: 0x100 }, 0, scroll::LE).unwrap();
+        bytes.pwrite_with(0xa010u16, 8, scroll::LE).unwrap();
+        bytes.pwrite_with(0x4020u16, 10, scroll::LE).unwrap();
+        // data following the directory
+        bytes.pwrite_with(0xa030u16, 12, scroll::LE).unwrap();
+
+        let directory = BaseRelocationDirectory::parse(&bytes, &headers, DataDirectory { virtual_address: 0x1000, size: 12 }).unwrap();
+        assert_eq!(directory.blocks.len(), 1);
+        // the HIGHADJ entry lost its second slot to the directory bound
+        assert_eq!(directory.fixups().collect::<Vec<u32>>(), vec![0x2010]);
+    }
+}
@@ -0,0 +1,354 @@
+use scroll::{IOread, IOwrite, Pread, Pwrite, SizeWith};
+use serde::{Deserialize, Serialize, Serializer};
+use serde::ser::SerializeStruct;
+
+use crate::error;
+use crate::pe::header::{DataDirectory, Headers};
+
+pub const RT_CURSOR: u16 = 1;
+pub const RT_BITMAP: u16 = 2;
+pub const RT_ICON: u16 = 3;
+pub const RT_MENU: u16 = 4;
+pub const RT_DIALOG: u16 = 5;
+pub const RT_STRING: u16 = 6;
+pub const RT_ACCELERATOR: u16 = 9;
+pub const RT_RCDATA: u16 = 10;
+pub const RT_MESSAGETABLE: u16 = 11;
+pub const RT_GROUP_CURSOR: u16 = 12;
+pub const RT_GROUP_ICON: u16 = 14;
+pub const RT_VERSION: u16 = 16;
+pub const RT_MANIFEST: u16 = 24;
+
+/// Type, name and language levels of a well formed resource tree
+pub const RESOURCE_MAX_DEPTH: usize = 3;
+/// Shared subdirectories are listed under each parent, this bounds the size of the expanded tree
+pub const RESOURCE_MAX_ENTRIES: usize = 0x10000;
+
+const RESOURCE_NAME_IS_STRING: u32 = 0x8000_0000;
+const RESOURCE_DATA_IS_DIRECTORY: u32 = 0x8000_0000;
+
+#[derive(Debug, PartialEq, Copy, Clone, Default, Pread, Pwrite, IOread, IOwrite, SizeWith, Deserialize)]
+pub struct ResourceDirectoryTable {
+    pub characteristics: u32,
+    pub time_date_stamp: u32,
+    pub major_version: u16,
+    pub minor_version: u16,
+    pub number_of_name_entries: u16,
+    pub number_of_id_entries: u16,
+}
+
+impl Serialize for ResourceDirectoryTable {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("ResourceDirectoryTable", 6)?;
+        state.serialize_field("characteristics", &format!("0x{:x}", &self.characteristics))?;
+        state.serialize_field("time_date_stamp", &format!("0x{:x}", &self.time_date_stamp))?;
+        state.serialize_field("major_version", &self.major_version)?;
+        state.serialize_field("minor_version", &self.minor_version)?;
+        state.serialize_field("number_of_name_entries", &self.number_of_name_entries)?;
+        state.serialize_field("number_of_id_entries", &self.number_of_id_entries)?;
+        state.end()
+    }
+}
+
+impl ResourceDirectoryTable {
+    pub fn parse(bytes: &[u8], offset: &mut usize) -> error::Result<Self> {
+        Ok(bytes.gread_with(offset, scroll::LE)?)
+    }
+}
+
+#[derive(Debug, PartialEq, Copy, Clone, Default, Pread, Pwrite, IOread, IOwrite, SizeWith, Deserialize)]
+pub struct ResourceDirectoryEntry {
+    pub name_or_id: u32,
+    pub offset_to_data: u32,
+}
+
+impl ResourceDirectoryEntry {
+    pub fn parse(bytes: &[u8], offset: &mut usize) -> error::Result<Self> {
+        Ok(bytes.gread_with(offset, scroll::LE)?)
+    }
+}
+
+#[derive(Debug, PartialEq, Copy, Clone, Default, Pread, Pwrite, IOread, IOwrite, SizeWith, Deserialize)]
+pub struct ResourceDataEntry {
+    pub data_rva: u32,
+    pub size: u32,
+    pub codepage: u32,
+    pub reserved: u32,
+}
+
+impl Serialize for ResourceDataEntry {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("ResourceDataEntry", 4)?;
+        state.serialize_field("data_rva", &format!("0x{:x}", &self.data_rva))?;
+        state.serialize_field("size", &self.size)?;
+        state.serialize_field("codepage", &self.codepage)?;
+        state.serialize_field("reserved", &format!("0x{:x}", &self.reserved))?;
+        state.end()
+    }
+}
+
+impl ResourceDataEntry {
+    pub fn parse(bytes: &[u8], offset: &mut usize) -> error::Result<Self> {
+        Ok(bytes.gread_with(offset, scroll::LE)?)
+    }
+    /// Returns the raw bytes of the resource
+    pub fn get_data<'a>(&self, bytes: &'a [u8], headers: &Headers) -> error::Result<&'a [u8]> {
+        let offset:usize = headers.rva_to_offset(self.data_rva).ok_or(error::Error::BadRva(self.data_rva))?;
+        Ok(bytes.pread_with::<&[u8]>(offset, self.size as usize)?)
+    }
+}
+
+/// Entry identifier, either an integer ID or a UTF-16 name
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ResourceId {
+    Id(u16),
+    Name(String),
+}
+
+impl Default for ResourceId {
+    fn default() -> Self {
+        ResourceId::Id(0)
+    }
+}
+
+impl ResourceId {
+    pub fn get_id(&self) -> Option<u16> {
+        match *self {
+            ResourceId::Id(id) => Some(id),
+            ResourceId::Name(_) => None
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub enum ResourceContent {
+    Directory(ResourceDirectory),
+    Data(ResourceDataEntry),
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct ResourceEntry {
+    pub id: ResourceId,
+    pub content: ResourceContent,
+}
+
+/// Leaf of the resource tree along with its type, name and language
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct Resource {
+    pub resource_type: ResourceId,
+    pub name: ResourceId,
+    pub language: ResourceId,
+    pub data_entry: ResourceDataEntry,
+}
+
+impl Resource {
+    pub fn get_data<'a>(&self, bytes: &'a [u8], headers: &Headers) -> error::Result<&'a [u8]> {
+        self.data_entry.get_data(bytes, headers)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct ResourceDirectory {
+    pub resource_directory_table: ResourceDirectoryTable,
+    pub entries: Vec<ResourceEntry>,
+}
+
+impl ResourceDirectory {
+    pub fn parse(bytes: &[u8], headers: &Headers, data_directory: DataDirectory) -> error::Result<Self> {
+        let start:usize = headers.rva_to_offset(data_directory.virtual_address).ok_or(error::Error::BadRva(data_directory.virtual_address))?;
+        let mut ancestors:Vec<usize> = Vec::new();
+        let mut remaining_entries:usize = RESOURCE_MAX_ENTRIES;
+        ResourceDirectory::parse_directory(bytes, start, 0, &mut ancestors, &mut remaining_entries)
+    }
+    /// Offsets of names and subdirectories are relative to the beginning of the root directory.
+    /// A subdirectory looping back to one of its `ancestors` is skipped, and the tree is truncated
+    /// past `RESOURCE_MAX_DEPTH` or once `RESOURCE_MAX_ENTRIES` entries were read
+    fn parse_directory(bytes: &[u8], start: usize, directory_offset: usize, ancestors: &mut Vec<usize>, remaining_entries: &mut usize) -> error::Result<Self> {
+        ancestors.push(directory_offset);
+        let directory:error::Result<Self> = ResourceDirectory::parse_entries(bytes, start, directory_offset, ancestors, remaining_entries);
+        ancestors.pop();
+        directory
+    }
+    fn parse_entries(bytes: &[u8], start: usize, directory_offset: usize, ancestors: &mut Vec<usize>, remaining_entries: &mut usize) -> error::Result<Self> {
+        let mut offset:usize = start + directory_offset;
+        let resource_directory_table:ResourceDirectoryTable = ResourceDirectoryTable::parse(bytes, &mut offset)?;
+        let number_of_entries:usize = resource_directory_table.number_of_name_entries as usize + resource_directory_table.number_of_id_entries as usize;
+        let mut entries:Vec<ResourceEntry> = Vec::new();
+        for _ in 0..number_of_entries {
+            if *remaining_entries == 0 {
+                break
+            }
+            *remaining_entries -= 1;
+            let entry:ResourceDirectoryEntry = ResourceDirectoryEntry::parse(bytes, &mut offset)?;
+            let id:ResourceId = if entry.name_or_id & RESOURCE_NAME_IS_STRING != 0 {
+                let mut name_offset:usize = start + (entry.name_or_id & !RESOURCE_NAME_IS_STRING) as usize;
+                let length:u16 = bytes.gread_with(&mut name_offset, scroll::LE)?;
+                let mut name:Vec<u16> = Vec::new();
+                for _ in 0..length {
+                    name.push(bytes.gread_with(&mut name_offset, scroll::LE)?);
+                }
+                ResourceId::Name(String::from_utf16_lossy(&name))
+            } else {
+                ResourceId::Id(entry.name_or_id as u16)
+            };
+            let content:ResourceContent = if entry.offset_to_data & RESOURCE_DATA_IS_DIRECTORY != 0 {
+                let subdirectory_offset:usize = (entry.offset_to_data & !RESOURCE_DATA_IS_DIRECTORY) as usize;
+                if ancestors.len() >= RESOURCE_MAX_DEPTH || ancestors.contains(&subdirectory_offset) {
+                    continue
+                }
+                ResourceContent::Directory(ResourceDirectory::parse_directory(bytes, start, subdirectory_offset, ancestors, remaining_entries)?)
+            } else {
+                let mut data_offset:usize = start + entry.offset_to_data as usize;
+                ResourceContent::Data(ResourceDataEntry::parse(bytes, &mut data_offset)?)
+            };
+            entries.push(ResourceEntry { id, content });
+        }
+        Ok(ResourceDirectory {
+            resource_directory_table,
+            entries
+        })
+    }
+    /// Flattens the type, name and language levels into a list of leaves
+    pub fn resources(&self) -> Vec<Resource> {
+        let mut resources:Vec<Resource> = Vec::new();
+        for type_entry in &self.entries {
+            let names = match type_entry.content {
+                ResourceContent::Directory(ref directory) => directory,
+                ResourceContent::Data(_) => continue
+            };
+            for name_entry in &names.entries {
+                let languages = match name_entry.content {
+                    ResourceContent::Directory(ref directory) => directory,
+                    ResourceContent::Data(_) => continue
+                };
+                for language_entry in &languages.entries {
+                    if let ResourceContent::Data(data_entry) = language_entry.content {
+                        resources.push(Resource {
+                            resource_type: type_entry.id.clone(),
+                            name: name_entry.id.clone(),
+                            language: language_entry.id.clone(),
+                            data_entry
+                        });
+                    }
+                }
+            }
+        }
+        resources
+    }
+    /// Returns every leaf of the given resource type (`RT_*`)
+    pub fn get_resources(&self, resource_type: u16) -> Vec<Resource> {
+        self.resources()
+            .into_iter()
+            .filter(|resource| resource.resource_type == ResourceId::Id(resource_type))
+            .collect()
+    }
+    /// Looks up a resource by type and name, preferring the given language when several exist
+    pub fn find_resource(&self, resource_type: u16, name: &ResourceId, language: &ResourceId) -> Option<Resource> {
+        let candidates:Vec<Resource> = self.get_resources(resource_type)
+            .into_iter()
+            .filter(|resource| &resource.name == name)
+            .collect();
+        candidates.iter()
+            .find(|resource| &resource.language == language)
+            .or_else(|| candidates.first())
+            .cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use scroll::Pwrite;
+
+    use super::{ResourceContent, ResourceDataEntry, ResourceDirectory, ResourceDirectoryEntry, ResourceDirectoryTable, ResourceId, RESOURCE_MAX_ENTRIES, RT_RCDATA};
+    use crate::pe::header::{DataDirectory, test_headers};
+
+    #[test]
+    fn resources() {
+        // RT_RCDATA -> "CONFIG" -> 1033 -> 4 bytes
+        let mut bytes = vec![0u8; 0x200];
+        let id_directory = ResourceDirectoryTable { number_of_id_entries: 1, ..Default::default() };
+        let name_directory = ResourceDirectoryTable { number_of_name_entries: 1, ..Default::default() };
+        bytes.pwrite_with(id_directory, 0x00, scroll::LE).unwrap();
+        bytes.pwrite_with(ResourceDirectoryEntry { name_or_id: u32::from(RT_RCDATA), offset_to_data: 0x8000_0018 }, 0x10, scroll::LE).unwrap();
+        bytes.pwrite_with(name_directory, 0x18, scroll::LE).unwrap();
+        bytes.pwrite_with(ResourceDirectoryEntry { name_or_id: 0x8000_0080, offset_to_data: 0x8000_0030 }, 0x28, scroll::LE).unwrap();
+        bytes.pwrite_with(id_directory, 0x30, scroll::LE).unwrap();
+        bytes.pwrite_with(ResourceDirectoryEntry { name_or_id: 1033, offset_to_data: 0x48 }, 0x40, scroll::LE).unwrap();
+        bytes.pwrite_with(ResourceDataEntry { data_rva: 0x1100, size: 4, codepage: 1252, reserved: 0 }, 0x48, scroll::LE).unwrap();
+        bytes.pwrite_with(6u16, 0x80, scroll::LE).unwrap();
+        for (i, c) in "CONFIG".encode_utf16().enumerate() {
+            bytes.pwrite_with(c, 0x82 + i * 2, scroll::LE).unwrap();
+        }
+        bytes[0x100..0x104].copy_from_slice(b"\xde\xad\xbe\xef");
+
+        let headers = test_headers(0x1000, 0x200);
+        let data_directory = DataDirectory { virtual_address: 0x1000, size: 0x104 };
+        let resource_directory = ResourceDirectory::parse(&bytes, &headers, data_directory).unwrap();
+        let resources = resource_directory.get_resources(RT_RCDATA);
+        assert_eq!(resources.len(), 1);
+        assert_eq!(resources[0].name, ResourceId::Name("CONFIG".to_string()));
+        assert_eq!(resources[0].language, ResourceId::Id(1033));
+        assert_eq!(resources[0].data_entry.codepage, 1252);
+        assert_eq!(resources[0].get_data(&bytes, &headers).unwrap(), b"\xde\xad\xbe\xef");
+    }
+
+    #[test]
+    fn resources_shared_and_deep_directories() {
+        let mut bytes = vec![0u8; 0x200];
+        let two_entries = ResourceDirectoryTable { number_of_id_entries: 2, ..Default::default() };
+        let one_entry = ResourceDirectoryTable { number_of_id_entries: 1, ..Default::default() };
+        // both root entries share the same subdirectory
+        bytes.pwrite_with(two_entries, 0x00, scroll::LE).unwrap();
+        bytes.pwrite_with(ResourceDirectoryEntry { name_or_id: 1, offset_to_data: 0x8000_0020 }, 0x10, scroll::LE).unwrap();
+        bytes.pwrite_with(ResourceDirectoryEntry { name_or_id: 2, offset_to_data: 0x8000_0020 }, 0x18, scroll::LE).unwrap();
+        // the subdirectory loops back to the root and goes one level too deep
+        bytes.pwrite_with(two_entries, 0x20, scroll::LE).unwrap();
+        bytes.pwrite_with(ResourceDirectoryEntry { name_or_id: 3, offset_to_data: 0x8000_0000 }, 0x30, scroll::LE).unwrap();
+        bytes.pwrite_with(ResourceDirectoryEntry { name_or_id: 4, offset_to_data: 0x8000_0040 }, 0x38, scroll::LE).unwrap();
+        bytes.pwrite_with(one_entry, 0x40, scroll::LE).unwrap();
+        bytes.pwrite_with(ResourceDirectoryEntry { name_or_id: 5, offset_to_data: 0x8000_0060 }, 0x50, scroll::LE).unwrap();
+        bytes.pwrite_with(one_entry, 0x60, scroll::LE).unwrap();
+
+        let data_directory = DataDirectory { virtual_address: 0x1000, size: 0x80 };
+        let resource_directory = ResourceDirectory::parse(&bytes, &test_headers(0x1000, 0x200), data_directory).unwrap();
+        assert_eq!(resource_directory.entries.len(), 2);
+        for (entry, id) in resource_directory.entries.iter().zip(&[1, 2]) {
+            assert_eq!(entry.id, ResourceId::Id(*id));
+            let subdirectory = match entry.content {
+                ResourceContent::Directory(ref directory) => directory,
+                ResourceContent::Data(_) => panic!("expected a subdirectory")
+            };
+            // the loop back to the root is dropped, the third level is kept empty
+            assert_eq!(subdirectory.entries.len(), 1);
+            assert_eq!(subdirectory.entries[0].id, ResourceId::Id(4));
+            match subdirectory.entries[0].content {
+                ResourceContent::Directory(ref directory) => assert!(directory.entries.is_empty()),
+                ResourceContent::Data(_) => panic!("expected a subdirectory")
+            }
+        }
+    }
+
+    #[test]
+    fn resources_expanded_tree_limit() {
+        // every entry of each level points at the next level, which expands to 0x7ff^3 leaves
+        let mut bytes = vec![0u8; 0x12000];
+        let directory = ResourceDirectoryTable { number_of_id_entries: 0x7ff, ..Default::default() };
+        for (level, next) in [(0x0, 0x8000_4000u32), (0x4000, 0x8000_8000), (0x8000, 0xc000)].iter() {
+            bytes.pwrite_with(directory, *level, scroll::LE).unwrap();
+            for index in 0..0x7ff {
+                bytes.pwrite_with(ResourceDirectoryEntry { name_or_id: index, offset_to_data: *next }, level + 0x10 + index as usize * 8, scroll::LE).unwrap();
+            }
+        }
+        let data_directory = DataDirectory { virtual_address: 0x1000, size: 0x12000 };
+        let resource_directory = ResourceDirectory::parse(&bytes, &test_headers(0x1000, 0x12000), data_directory).unwrap();
+        // the budget also covers the type and the 32 name entries leading to the leaves
+        assert_eq!(resource_directory.resources().len(), RESOURCE_MAX_ENTRIES - 1 - 32);
+    }
+}
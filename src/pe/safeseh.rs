@@ -0,0 +1,149 @@
+use scroll::Pread;
+use serde::{Deserialize, Serialize, Serializer};
+use serde::ser::SerializeStruct;
+
+use crate::error;
+use crate::pe::header::{Headers, IMAGE_DLLCHARACTERISTICS_NO_SEH, OPTIONAL_HEADER_SIGNATURE_32};
+use crate::pe::load_config::LoadConfigDirectory;
+
+/// SafeSEH state of a PE32 image
+#[derive(Debug, PartialEq, Copy, Clone, Default, Serialize, Deserialize)]
+pub enum SafeSehVerdict {
+    /// No load configuration directory, any handler may be dispatched
+    #[default]
+    None,
+    /// The image does not use structured exception handling at all
+    NoSeh,
+    /// Only the handlers of the SEHandlerTable may be dispatched
+    Registered,
+    /// A load configuration exists but it does not declare a handler table
+    MissingTable,
+}
+
+#[derive(Debug, PartialEq, Clone, Default, Deserialize)]
+pub struct SafeSeh {
+    pub verdict: SafeSehVerdict,
+    /// RVAs of the registered exception handlers
+    pub handlers: Vec<u32>,
+}
+
+impl Serialize for SafeSeh {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("SafeSeh", 2)?;
+        state.serialize_field("verdict", &self.verdict)?;
+        state.serialize_field("handlers", &self.handlers.iter().map(|handler| format!("0x{:x}", handler)).collect::<Vec<String>>())?;
+        state.end()
+    }
+}
+
+impl SafeSeh {
+    /// SafeSEH only applies to PE32 images, `None` is returned for PE32+
+    pub fn parse(bytes: &[u8], headers: &Headers, load_config: Option<&LoadConfigDirectory>) -> error::Result<Option<Self>> {
+        if headers.optional.standard_fields.signature != OPTIONAL_HEADER_SIGNATURE_32 {
+            return Ok(None);
+        }
+        let dll_characteristics:u16 = headers.optional.specific_fields.dll_characteristics;
+        if dll_characteristics & IMAGE_DLLCHARACTERISTICS_NO_SEH != 0 {
+            return Ok(Some(SafeSeh { verdict: SafeSehVerdict::NoSeh, handlers: Vec::new() }));
+        }
+        let load_config:&LoadConfigDirectory = match load_config {
+            Some(load_config) => load_config,
+            None => return Ok(Some(SafeSeh { verdict: SafeSehVerdict::None, handlers: Vec::new() }))
+        };
+        if load_config.se_handler_table == 0 {
+            return Ok(Some(SafeSeh { verdict: SafeSehVerdict::MissingTable, handlers: Vec::new() }));
+        }
+
+        // Sorted array of handler RVAs, only the part inside the file is kept
+        let table_rva:u32 = load_config.se_handler_table.wrapping_sub(headers.optional.specific_fields.image_base) as u32;
+        let mut handlers:Vec<u32> = Vec::new();
+        if let Some(mut offset) = headers.rva_to_offset(table_rva) {
+            for _ in 0..load_config.se_handler_count {
+                match bytes.gread_with(&mut offset, scroll::LE) {
+                    Ok(handler) => handlers.push(handler),
+                    Err(_) => break
+                }
+            }
+        }
+        Ok(Some(SafeSeh { verdict: SafeSehVerdict::Registered, handlers }))
+    }
+    /// True when `rva` may be dispatched as an exception handler
+    pub fn is_valid_handler(&self, rva: u32) -> bool {
+        match self.verdict {
+            SafeSehVerdict::NoSeh => false,
+            SafeSehVerdict::Registered => self.handlers.contains(&rva),
+            SafeSehVerdict::None | SafeSehVerdict::MissingTable => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use scroll::Pwrite;
+
+    use super::{SafeSeh, SafeSehVerdict};
+    use crate::pe::header::{DataDirectory, IMAGE_DIRECTORY_ENTRY_LOAD_CONFIG, IMAGE_DLLCHARACTERISTICS_NO_SEH, OPTIONAL_HEADER_SIGNATURE_32, test_headers, test_image_32};
+    use crate::pe::load_config::LoadConfigDirectory;
+    use crate::pe::pe::PE;
+
+    #[test]
+    fn safe_seh() {
+        let mut headers = test_headers(0x1000, 0x200);
+        headers.optional.specific_fields.image_base = 0x400000;
+        let mut bytes = vec![0u8; 0x200];
+        bytes.pwrite_with(0x1100u32, 0x40, scroll::LE).unwrap();
+        bytes.pwrite_with(0x1180u32, 0x44, scroll::LE).unwrap();
+        let load_config = LoadConfigDirectory {
+            se_handler_table: 0x401040,
+            se_handler_count: 2,
+            ..Default::default()
+        };
+
+        // PE32+ images
+        assert!(SafeSeh::parse(&bytes, &headers, Some(&load_config)).unwrap().is_none());
+
+        headers.optional.standard_fields.signature = OPTIONAL_HEADER_SIGNATURE_32;
+        let safe_seh = SafeSeh::parse(&bytes, &headers, Some(&load_config)).unwrap().unwrap();
+        assert_eq!(safe_seh.verdict, SafeSehVerdict::Registered);
+        assert_eq!(safe_seh.handlers, vec![0x1100, 0x1180]);
+        assert!(safe_seh.is_valid_handler(0x1180));
+        assert!(!safe_seh.is_valid_handler(0x1120));
+
+        let safe_seh = SafeSeh::parse(&bytes, &headers, Some(&LoadConfigDirectory::default())).unwrap().unwrap();
+        assert_eq!(safe_seh.verdict, SafeSehVerdict::MissingTable);
+        let safe_seh = SafeSeh::parse(&bytes, &headers, None).unwrap().unwrap();
+        assert_eq!(safe_seh.verdict, SafeSehVerdict::None);
+
+        headers.optional.specific_fields.dll_characteristics = IMAGE_DLLCHARACTERISTICS_NO_SEH;
+        let safe_seh = SafeSeh::parse(&bytes, &headers, Some(&load_config)).unwrap().unwrap();
+        assert_eq!(safe_seh.verdict, SafeSehVerdict::NoSeh);
+        assert!(safe_seh.handlers.is_empty());
+    }
+
+    #[test]
+    fn safe_seh_truncated_table() {
+        // 32-bit load configuration up to SEHandlerCount, the count runs the table past the end of the file
+        let mut data = vec![0u8; 0x200];
+        data.pwrite_with(0x48u32, 0, scroll::LE).unwrap();
+        data.pwrite_with(0x4011f8u32, 64, scroll::LE).unwrap();
+        data.pwrite_with(4u32, 68, scroll::LE).unwrap();
+        data.pwrite_with(0x1100u32, 0x1f8, scroll::LE).unwrap();
+        data.pwrite_with(0x1180u32, 0x1fc, scroll::LE).unwrap();
+        let load_config_directory = DataDirectory { virtual_address: 0x1000, size: 0x48 };
+        let pe = PE::new(&test_image_32(&[(IMAGE_DIRECTORY_ENTRY_LOAD_CONFIG, load_config_directory)], &data)).unwrap();
+        assert_eq!(pe.load_config_directory.unwrap().se_handler_count, 4);
+        let safe_seh = pe.safe_seh.unwrap();
+        assert_eq!(safe_seh.verdict, SafeSehVerdict::Registered);
+        assert_eq!(safe_seh.handlers, vec![0x1100, 0x1180]);
+
+        // handler table outside of the image, no handler can be validated
+        data.pwrite_with(0x409000u32, 64, scroll::LE).unwrap();
+        let pe = PE::new(&test_image_32(&[(IMAGE_DIRECTORY_ENTRY_LOAD_CONFIG, load_config_directory)], &data)).unwrap();
+        let safe_seh = pe.safe_seh.unwrap();
+        assert_eq!(safe_seh.verdict, SafeSehVerdict::Registered);
+        assert!(!safe_seh.is_valid_handler(0x1100));
+    }
+}
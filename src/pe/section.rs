@@ -2,6 +2,7 @@ use scroll::{IOread, IOwrite, Pread, Pwrite, SizeWith};
 use serde::{Deserialize, Serialize, Serializer};
 
 use crate::error;
+use crate::pe::header::{Headers, OPTIONAL_HEADER_SIGNATURE_64};
 use serde::ser::SerializeStruct;
 
 #[derive(Debug, PartialEq, Copy, Clone, Default, Pread, Pwrite, IOread, IOwrite, SizeWith, Deserialize)]
@@ -57,4 +58,118 @@ impl<'a> scroll::ctx::TryFromCtx<'a, scroll::Endian> for ImportDirectoryTable {
         }
         Ok((ImportDirectoryTable { imports }, *offset))
     }
-}
\ No newline at end of file
+}
+
+pub const IMPORT_BY_ORDINAL_32: u64 = 0x8000_0000;
+pub const IMPORT_BY_ORDINAL_64: u64 = 0x8000_0000_0000_0000;
+
+/// A symbol imported through an import lookup table entry
+#[derive(Debug, PartialEq, Clone, Default, Deserialize)]
+pub struct ImportFunction {
+    pub name: Option<String>,
+    pub hint: u16,
+    pub ordinal: Option<u16>,
+    pub import_by_ordinal: bool,
+    /// RVA of the import address table slot patched by the loader
+    pub iat_rva: u32,
+}
+
+impl Serialize for ImportFunction {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("ImportFunction", 5)?;
+        state.serialize_field("name", &self.name)?;
+        state.serialize_field("hint", &self.hint)?;
+        state.serialize_field("ordinal", &self.ordinal)?;
+        state.serialize_field("import_by_ordinal", &self.import_by_ordinal)?;
+        state.serialize_field("iat_rva", &format!("0x{:x}", &self.iat_rva))?;
+        state.end()
+    }
+}
+
+impl ImportFunction {
+    /// Walks a null terminated thunk table, the thunk width depends on the optional header signature
+    pub fn parse_thunks(bytes: &[u8], headers: &Headers, lookup_table_rva: u32, address_table_rva: u32) -> error::Result<Vec<Self>> {
+        ImportFunction::parse_thunks_with_base(bytes, headers, lookup_table_rva, address_table_rva, 0)
+    }
+    /// Same as `parse_thunks` for tables whose hint/name entries are virtual addresses relative to `base`
+    pub fn parse_thunks_with_base(bytes: &[u8], headers: &Headers, lookup_table_rva: u32, address_table_rva: u32, base: u64) -> error::Result<Vec<Self>> {
+        let (thunk_size, import_by_ordinal_flag):(usize, u64) = match headers.optional.standard_fields.signature {
+            OPTIONAL_HEADER_SIGNATURE_64 => (8, IMPORT_BY_ORDINAL_64),
+            _ => (4, IMPORT_BY_ORDINAL_32)
+        };
+        // Some linkers leave the lookup table empty, the address table holds the same thunks on disk
+        let table_rva:u32 = if lookup_table_rva != 0 { lookup_table_rva } else { address_table_rva };
+        let mut offset:usize = headers.rva_to_offset(table_rva).ok_or(error::Error::BadRva(table_rva))?;
+        let mut functions:Vec<ImportFunction> = Vec::new();
+        loop {
+            let thunk:u64 = match thunk_size {
+                8 => bytes.gread_with::<u64>(&mut offset, scroll::LE)?,
+                _ => u64::from(bytes.gread_with::<u32>(&mut offset, scroll::LE)?)
+            };
+            if thunk == 0 {
+                break;
+            }
+            let iat_rva:u32 = address_table_rva.wrapping_add((functions.len() * thunk_size) as u32);
+            let function:ImportFunction = if thunk & import_by_ordinal_flag != 0 {
+                ImportFunction {
+                    name: None,
+                    hint: 0,
+                    ordinal: Some(thunk as u16),
+                    import_by_ordinal: true,
+                    iat_rva
+                }
+            } else {
+                let hint_name_rva:u32 = (thunk.wrapping_sub(base) & 0x7fff_ffff) as u32;
+                let hint_name_offset:usize = headers.rva_to_offset(hint_name_rva).ok_or(error::Error::BadRva(hint_name_rva))?;
+                ImportFunction {
+                    name: Some(headers.get_string(bytes, hint_name_rva + 2)?),
+                    hint: bytes.pread_with(hint_name_offset, scroll::LE)?,
+                    ordinal: None,
+                    import_by_ordinal: false,
+                    iat_rva
+                }
+            };
+            functions.push(function);
+        }
+        Ok(functions)
+    }
+}
+
+/// Import directory entry resolved to its DLL name and imported symbols
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct Import {
+    pub name: String,
+    pub functions: Vec<ImportFunction>,
+}
+
+impl Import {
+    pub fn parse(bytes: &[u8], headers: &Headers, import_directory: &ImportDirectory) -> error::Result<Self> {
+        Ok(Import {
+            name: headers.get_string(bytes, import_directory.name)?,
+            functions: ImportFunction::parse_thunks(bytes, headers, import_directory.import_lookup_table_rva, import_directory.import_address_table_rva)?
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pe::pe::PE;
+
+    const SAMPLE: &[u8] = include_bytes!("../../samples/pe.exe");
+
+    #[test]
+    fn imports() {
+        let pe = PE::new(SAMPLE).unwrap();
+        assert_eq!(pe.imports.len(), pe.import_directory_table.imports.len());
+        let kernel32 = &pe.imports[0];
+        assert_eq!(kernel32.name, "KERNEL32.dll");
+        assert_eq!(kernel32.functions.len(), 23);
+        assert_eq!(kernel32.functions[0].iat_rva, pe.import_directory_table.imports[0].import_address_table_rva);
+        assert_eq!(kernel32.functions[1].iat_rva, kernel32.functions[0].iat_rva + 8);
+        assert!(kernel32.functions.iter().all(|function| !function.import_by_ordinal && function.name.is_some()));
+        assert_eq!(pe.imports[1].name, "msvcrt.dll");
+    }
+}
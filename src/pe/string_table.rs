@@ -0,0 +1,179 @@
+use std::collections::BTreeMap;
+
+use scroll::{IOread, IOwrite, Pread, Pwrite, SizeWith};
+use serde::{Deserialize, Serialize};
+
+use crate::error;
+use crate::pe::header::Headers;
+use crate::pe::resource::{ResourceDirectory, RT_MESSAGETABLE, RT_STRING};
+
+/// Number of strings stored in every RT_STRING bundle
+pub const STRINGS_PER_BLOCK: u32 = 16;
+
+const MESSAGE_RESOURCE_UNICODE: u16 = 0x1;
+
+/// `id → string` map of one language
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct LocalizedStrings {
+    pub language: u16,
+    pub strings: BTreeMap<u32, String>,
+}
+
+#[derive(Debug, PartialEq, Copy, Clone, Default, Pread, Pwrite, IOread, IOwrite, SizeWith, Serialize, Deserialize)]
+pub struct MessageResourceBlock {
+    pub low_id: u32,
+    pub high_id: u32,
+    pub offset_to_entries: u32,
+}
+
+/// Decodes the data of one resource into the map of its language
+type Decoder = fn(&[u8], u16, &mut BTreeMap<u32, String>) -> error::Result<()>;
+
+fn decode_utf16(bytes: &[u8]) -> String {
+    let chars:Vec<u16> = bytes.chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect();
+    String::from_utf16_lossy(&chars)
+}
+
+/// Adds the strings of every leaf of `resource_type` to the map of its language
+fn collect(bytes: &[u8], headers: &Headers, resource_directory: &ResourceDirectory, resource_type: u16, decode: Decoder) -> error::Result<Vec<LocalizedStrings>> {
+    let mut languages:BTreeMap<u16, BTreeMap<u32, String>> = BTreeMap::new();
+    for resource in resource_directory.get_resources(resource_type) {
+        let id:u16 = match resource.name.get_id() {
+            Some(id) => id,
+            None => continue
+        };
+        let strings = languages.entry(resource.language.get_id().unwrap_or(0)).or_default();
+        decode(resource.get_data(bytes, headers)?, id, strings)?;
+    }
+    Ok(languages.into_iter().map(|(language, strings)| LocalizedStrings { language, strings }).collect())
+}
+
+impl LocalizedStrings {
+    /// Decodes a bundle of 16 length-prefixed UTF-16 strings, `block` is the resource name
+    pub fn parse_string_block(bytes: &[u8], block: u16, strings: &mut BTreeMap<u32, String>) -> error::Result<()> {
+        let mut offset:usize = 0;
+        for index in 0..STRINGS_PER_BLOCK {
+            let length:u16 = bytes.gread_with(&mut offset, scroll::LE)?;
+            if length == 0 {
+                continue;
+            }
+            let text:&[u8] = bytes.gread_with(&mut offset, length as usize * 2)?;
+            strings.insert((u32::from(block).saturating_sub(1)) * STRINGS_PER_BLOCK + index, decode_utf16(text));
+        }
+        Ok(())
+    }
+    /// Decodes a MESSAGE_RESOURCE_DATA structure, entries are either ANSI or UTF-16
+    pub fn parse_message_table(bytes: &[u8], _: u16, messages: &mut BTreeMap<u32, String>) -> error::Result<()> {
+        let mut offset:usize = 0;
+        let number_of_blocks:u32 = bytes.gread_with(&mut offset, scroll::LE)?;
+        for _ in 0..number_of_blocks {
+            let block:MessageResourceBlock = match bytes.gread_with(&mut offset, scroll::LE) {
+                Ok(block) => block,
+                Err(_) => break
+            };
+            // A short or truncated entry ends its block, the entries after it cannot be located
+            // while the next blocks have their own offset
+            let mut entry_offset:usize = block.offset_to_entries as usize;
+            for id in block.low_id..=block.high_id {
+                let (length, flags):(u16, u16) = match (bytes.pread_with(entry_offset, scroll::LE), bytes.pread_with(entry_offset + 2, scroll::LE)) {
+                    (Ok(length), Ok(flags)) => (length, flags),
+                    _ => break
+                };
+                if (length as usize) < 4 {
+                    break;
+                }
+                let text:&[u8] = match bytes.pread_with(entry_offset + 4, length as usize - 4) {
+                    Ok(text) => text,
+                    Err(_) => break
+                };
+                let message:String = if flags & MESSAGE_RESOURCE_UNICODE != 0 {
+                    decode_utf16(text)
+                } else {
+                    text.iter().map(|&c| char::from(c)).collect()
+                };
+                messages.insert(id, message.trim_end_matches(char::from(0)).to_string());
+                entry_offset += length as usize;
+            }
+        }
+        Ok(())
+    }
+    /// Decodes every RT_STRING bundle, string IDs are `(block - 1) * 16 + index`
+    pub fn string_tables(bytes: &[u8], headers: &Headers, resource_directory: &ResourceDirectory) -> error::Result<Vec<Self>> {
+        collect(bytes, headers, resource_directory, RT_STRING, LocalizedStrings::parse_string_block)
+    }
+    /// Decodes every RT_MESSAGETABLE resource
+    pub fn message_tables(bytes: &[u8], headers: &Headers, resource_directory: &ResourceDirectory) -> error::Result<Vec<Self>> {
+        collect(bytes, headers, resource_directory, RT_MESSAGETABLE, LocalizedStrings::parse_message_table)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use scroll::Pwrite;
+
+    use super::{LocalizedStrings, MessageResourceBlock};
+
+    #[test]
+    fn string_block() {
+        let mut bytes:Vec<u8> = Vec::new();
+        for index in 0..16 {
+            let text = if index == 2 { "Open" } else { "" };
+            bytes.extend_from_slice(&(text.len() as u16).to_le_bytes());
+            bytes.extend(text.encode_utf16().flat_map(|c| c.to_le_bytes().to_vec()));
+        }
+        let mut strings = BTreeMap::new();
+        LocalizedStrings::parse_string_block(&bytes, 7, &mut strings).unwrap();
+        assert_eq!(strings.len(), 1);
+        assert_eq!(strings.get(&98).map(|s| s.as_str()), Some("Open"));
+    }
+
+    #[test]
+    fn message_table() {
+        let mut bytes = vec![0u8; 0x40];
+        bytes.pwrite_with(1u32, 0, scroll::LE).unwrap();
+        bytes.pwrite_with(MessageResourceBlock { low_id: 0x100, high_id: 0x101, offset_to_entries: 0x10 }, 4, scroll::LE).unwrap();
+        // ANSI entry
+        bytes.pwrite_with(12u16, 0x10, scroll::LE).unwrap();
+        bytes[0x14..0x1a].copy_from_slice(b"Done\r\n");
+        // Unicode entry
+        bytes.pwrite_with(12u16, 0x1c, scroll::LE).unwrap();
+        bytes.pwrite_with(1u16, 0x1e, scroll::LE).unwrap();
+        for (i, c) in "Err".encode_utf16().enumerate() {
+            bytes.pwrite_with(c, 0x20 + i * 2, scroll::LE).unwrap();
+        }
+        let mut messages = BTreeMap::new();
+        LocalizedStrings::parse_message_table(&bytes, 1, &mut messages).unwrap();
+        assert_eq!(messages.get(&0x100).map(|s| s.as_str()), Some("Done\r\n"));
+        assert_eq!(messages.get(&0x101).map(|s| s.as_str()), Some("Err"));
+    }
+
+    #[test]
+    fn message_table_short_entry() {
+        let mut bytes = vec![0u8; 0x40];
+        bytes.pwrite_with(3u32, 0, scroll::LE).unwrap();
+        bytes.pwrite_with(MessageResourceBlock { low_id: 1, high_id: 3, offset_to_entries: 0x30 }, 4, scroll::LE).unwrap();
+        // entries past the end of the resource
+        bytes.pwrite_with(MessageResourceBlock { low_id: 4, high_id: 4, offset_to_entries: 0x1000 }, 0x10, scroll::LE).unwrap();
+        bytes.pwrite_with(MessageResourceBlock { low_id: 5, high_id: 5, offset_to_entries: 0x38 }, 0x1c, scroll::LE).unwrap();
+        bytes.pwrite_with(8u16, 0x30, scroll::LE).unwrap();
+        bytes[0x34..0x38].copy_from_slice(b"Done");
+        // zero length entry, the third one is never reached
+        bytes.pwrite_with(0u16, 0x38, scroll::LE).unwrap();
+        let mut messages = BTreeMap::new();
+        LocalizedStrings::parse_message_table(&bytes, 1, &mut messages).unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages.get(&1).map(|s| s.as_str()), Some("Done"));
+
+        // the blocks after a short entry are still read
+        bytes.pwrite_with(MessageResourceBlock { low_id: 5, high_id: 5, offset_to_entries: 0x30 }, 0x1c, scroll::LE).unwrap();
+        let mut messages = BTreeMap::new();
+        LocalizedStrings::parse_message_table(&bytes, 1, &mut messages).unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages.get(&5).map(|s| s.as_str()), Some("Done"));
+        // block headers past the end of the resource
+        bytes.pwrite_with(0x1000u32, 0, scroll::LE).unwrap();
+        LocalizedStrings::parse_message_table(&bytes[..0x28], 1, &mut BTreeMap::new()).unwrap();
+    }
+}
@@ -0,0 +1,177 @@
+use scroll::{IOread, IOwrite, Pread, Pwrite, SizeWith};
+use serde::{Deserialize, Serialize, Serializer};
+use serde::ser::SerializeStruct;
+
+use crate::error;
+use crate::pe::header::{DataDirectory, Headers, OPTIONAL_HEADER_SIGNATURE_64};
+
+/// Mask of the IMAGE_SCN_ALIGN_* value stored in the TLS characteristics
+pub const TLS_ALIGNMENT_MASK: u32 = 0x00f00000;
+
+#[derive(Debug, PartialEq, Copy, Clone, Default, Pread, Pwrite, IOread, IOwrite, SizeWith, Deserialize)]
+pub struct TlsDirectory32 {
+    pub start_address_of_raw_data: u32,
+    pub end_address_of_raw_data: u32,
+    pub address_of_index: u32,
+    pub address_of_callbacks: u32,
+    pub size_of_zero_fill: u32,
+    pub characteristics: u32,
+}
+
+#[derive(Debug, PartialEq, Copy, Clone, Default, Pread, Pwrite, IOread, IOwrite, SizeWith, Deserialize)]
+pub struct TlsDirectory64 {
+    pub start_address_of_raw_data: u64,
+    pub end_address_of_raw_data: u64,
+    pub address_of_index: u64,
+    pub address_of_callbacks: u64,
+    pub size_of_zero_fill: u32,
+    pub characteristics: u32,
+}
+
+/// TLS directory of either PE32 or PE32+ images, addresses are VAs
+#[derive(Debug, PartialEq, Clone, Default, Deserialize)]
+pub struct TlsDirectory {
+    pub start_address_of_raw_data: u64,
+    pub end_address_of_raw_data: u64,
+    pub address_of_index: u64,
+    pub address_of_callbacks: u64,
+    pub size_of_zero_fill: u32,
+    pub characteristics: u32,
+    /// RVAs of the callbacks run before the entry point
+    pub callbacks: Vec<u32>,
+}
+
+impl Serialize for TlsDirectory {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("TlsDirectory", 8)?;
+        state.serialize_field("start_address_of_raw_data", &format!("0x{:x}", &self.start_address_of_raw_data))?;
+        state.serialize_field("end_address_of_raw_data", &format!("0x{:x}", &self.end_address_of_raw_data))?;
+        state.serialize_field("address_of_index", &format!("0x{:x}", &self.address_of_index))?;
+        state.serialize_field("address_of_callbacks", &format!("0x{:x}", &self.address_of_callbacks))?;
+        state.serialize_field("size_of_zero_fill", &self.size_of_zero_fill)?;
+        state.serialize_field("characteristics", &format!("0x{:x}", &self.characteristics))?;
+        state.serialize_field("alignment", &self.get_alignment())?;
+        state.serialize_field("callbacks", &self.callbacks.iter().map(|callback| format!("0x{:x}", callback)).collect::<Vec<String>>())?;
+        state.end()
+    }
+}
+
+impl TlsDirectory {
+    pub fn parse(bytes: &[u8], headers: &Headers, data_directory: DataDirectory) -> error::Result<Self> {
+        let offset:usize = headers.rva_to_offset(data_directory.virtual_address).ok_or(error::Error::BadRva(data_directory.virtual_address))?;
+        let is_64:bool = headers.optional.standard_fields.signature == OPTIONAL_HEADER_SIGNATURE_64;
+        let mut tls_directory:TlsDirectory = if is_64 {
+            let tls:TlsDirectory64 = bytes.pread_with(offset, scroll::LE)?;
+            TlsDirectory {
+                start_address_of_raw_data: tls.start_address_of_raw_data,
+                end_address_of_raw_data: tls.end_address_of_raw_data,
+                address_of_index: tls.address_of_index,
+                address_of_callbacks: tls.address_of_callbacks,
+                size_of_zero_fill: tls.size_of_zero_fill,
+                characteristics: tls.characteristics,
+                callbacks: Vec::new()
+            }
+        } else {
+            let tls:TlsDirectory32 = bytes.pread_with(offset, scroll::LE)?;
+            TlsDirectory {
+                start_address_of_raw_data: u64::from(tls.start_address_of_raw_data),
+                end_address_of_raw_data: u64::from(tls.end_address_of_raw_data),
+                address_of_index: u64::from(tls.address_of_index),
+                address_of_callbacks: u64::from(tls.address_of_callbacks),
+                size_of_zero_fill: tls.size_of_zero_fill,
+                characteristics: tls.characteristics,
+                callbacks: Vec::new()
+            }
+        };
+
+        // Null terminated array of callback VAs, an unreadable array keeps the callbacks read before it
+        let image_base:u64 = headers.optional.specific_fields.image_base;
+        let array_rva:u32 = tls_directory.address_of_callbacks.wrapping_sub(image_base) as u32;
+        if let Some(mut offset) = headers.rva_to_offset(array_rva).filter(|_| tls_directory.address_of_callbacks != 0) {
+            let read_callback = |offset: &mut usize| -> Result<u64, scroll::Error> {
+                if is_64 { bytes.gread_with(offset, scroll::LE) } else { bytes.gread_with::<u32>(offset, scroll::LE).map(u64::from) }
+            };
+            while let Ok(callback) = read_callback(&mut offset) {
+                if callback == 0 {
+                    break;
+                }
+                tls_directory.callbacks.push(callback.wrapping_sub(image_base) as u32);
+            }
+        }
+        Ok(tls_directory)
+    }
+    /// Alignment of the TLS template in bytes, `None` when unspecified
+    pub fn get_alignment(&self) -> Option<u32> {
+        match (self.characteristics & TLS_ALIGNMENT_MASK) >> 20 {
+            0 => None,
+            align => Some(1 << (align - 1))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use scroll::Pwrite;
+
+    use super::{TlsDirectory, TlsDirectory32, TlsDirectory64};
+    use crate::pe::header::{DataDirectory, Headers, IMAGE_DIRECTORY_ENTRY_TLS, OPTIONAL_HEADER_SIGNATURE_32, test_headers, test_image};
+    use crate::pe::pe::PE;
+
+    #[test]
+    fn tls_directory_64() {
+        let bytes = include_bytes!("../../samples/pe.exe");
+        let headers = Headers::parse(bytes).unwrap();
+        let data_directory = headers.get_data_directory(IMAGE_DIRECTORY_ENTRY_TLS).unwrap();
+        let tls_directory = TlsDirectory::parse(bytes, &headers, data_directory).unwrap();
+        assert_eq!(tls_directory.start_address_of_raw_data, 0x40a000);
+        assert_eq!(tls_directory.end_address_of_raw_data, 0x40a008);
+        assert_eq!(tls_directory.callbacks, vec![0x1880, 0x1850]);
+    }
+
+    #[test]
+    fn tls_directory_32() {
+        let mut headers = test_headers(0x1000, 0x200);
+        headers.optional.standard_fields.signature = OPTIONAL_HEADER_SIGNATURE_32;
+        headers.optional.specific_fields.image_base = 0x400000;
+        let mut bytes = vec![0u8; 0x200];
+        bytes.pwrite_with(TlsDirectory32 {
+            start_address_of_raw_data: 0x401100,
+            end_address_of_raw_data: 0x401108,
+            address_of_index: 0x401110,
+            address_of_callbacks: 0x401040,
+            characteristics: 0x00300000,
+            ..Default::default()
+        }, 0, scroll::LE).unwrap();
+        bytes.pwrite_with(0x401180u32, 0x40, scroll::LE).unwrap();
+        bytes.pwrite_with(0x4011a0u32, 0x44, scroll::LE).unwrap();
+        let tls_directory = TlsDirectory::parse(&bytes, &headers, DataDirectory { virtual_address: 0x1000, size: 24 }).unwrap();
+        assert_eq!(tls_directory.callbacks, vec![0x1180, 0x11a0]);
+        assert_eq!(tls_directory.get_alignment(), Some(4));
+    }
+
+    #[test]
+    fn tls_directory_bad_callbacks() {
+        let tls_directory = DataDirectory { virtual_address: 0x1000, size: 40 };
+        let mut data = vec![0u8; 0x100];
+        // callback array outside of any section
+        data.pwrite_with(TlsDirectory64 { start_address_of_raw_data: 0x401080, address_of_callbacks: 0x409000, ..Default::default() }, 0, scroll::LE).unwrap();
+        let pe = PE::new(&test_image(&[(IMAGE_DIRECTORY_ENTRY_TLS, tls_directory)], &data)).unwrap();
+        let tls = pe.tls_directory.unwrap();
+        assert_eq!(tls.start_address_of_raw_data, 0x401080);
+        assert!(tls.callbacks.is_empty());
+
+        // unterminated callback array running into the end of the file
+        data.pwrite_with(TlsDirectory64 { address_of_callbacks: 0x4010f0, ..Default::default() }, 0, scroll::LE).unwrap();
+        data.pwrite_with(0x401180u64, 0xf0, scroll::LE).unwrap();
+        data.pwrite_with(0x401190u64, 0xf8, scroll::LE).unwrap();
+        let pe = PE::new(&test_image(&[(IMAGE_DIRECTORY_ENTRY_TLS, tls_directory)], &data)).unwrap();
+        assert_eq!(pe.tls_directory.unwrap().callbacks, vec![0x1180, 0x1190]);
+
+        // a directory past the end of the file is left out
+        let pe = PE::new(&test_image(&[(IMAGE_DIRECTORY_ENTRY_TLS, DataDirectory { virtual_address: 0x10f0, size: 40 })], &data)).unwrap();
+        assert_eq!(pe.tls_directory, None);
+    }
+}
@@ -0,0 +1,285 @@
+use std::collections::BTreeMap;
+
+use scroll::{IOread, IOwrite, Pread, Pwrite, SizeWith};
+use scroll::ctx::SizeWith as _;
+use serde::{Deserialize, Serialize, Serializer};
+use serde::ser::SerializeStruct;
+
+use crate::error;
+use crate::pe::header::Headers;
+use crate::pe::resource::{ResourceDirectory, RT_VERSION};
+
+/// VS_FIXEDFILEINFO signature
+pub const FIXED_FILE_INFO_SIGNATURE: u32 = 0xFEEF04BD;
+
+const VERSION_INFO_TEXT: u16 = 1;
+/// VS_VERSIONINFO nests four levels down to StringFileInfo strings, children past this depth are dropped
+const VERSION_MAX_DEPTH: usize = 8;
+
+#[derive(Debug, PartialEq, Copy, Clone, Default, Pread, Pwrite, IOread, IOwrite, SizeWith, Deserialize)]
+pub struct FixedFileInfo {
+    pub signature: u32,
+    pub struc_version: u32,
+    pub file_version_ms: u32,
+    pub file_version_ls: u32,
+    pub product_version_ms: u32,
+    pub product_version_ls: u32,
+    pub file_flags_mask: u32,
+    pub file_flags: u32,
+    pub file_os: u32,
+    pub file_type: u32,
+    pub file_subtype: u32,
+    pub file_date_ms: u32,
+    pub file_date_ls: u32,
+}
+
+impl Serialize for FixedFileInfo {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("FixedFileInfo", 11)?;
+        state.serialize_field("signature", &format!("0x{:x}", &self.signature))?;
+        state.serialize_field("struc_version", &format!("0x{:x}", &self.struc_version))?;
+        state.serialize_field("file_version", &self.get_file_version())?;
+        state.serialize_field("product_version", &self.get_product_version())?;
+        state.serialize_field("file_flags_mask", &format!("0x{:x}", &self.file_flags_mask))?;
+        state.serialize_field("file_flags", &format!("0x{:x}", &self.file_flags))?;
+        state.serialize_field("file_os", &format!("0x{:x}", &self.file_os))?;
+        state.serialize_field("file_type", &format!("0x{:x}", &self.file_type))?;
+        state.serialize_field("file_subtype", &format!("0x{:x}", &self.file_subtype))?;
+        state.serialize_field("file_date_ms", &format!("0x{:x}", &self.file_date_ms))?;
+        state.serialize_field("file_date_ls", &format!("0x{:x}", &self.file_date_ls))?;
+        state.end()
+    }
+}
+
+impl FixedFileInfo {
+    pub fn parse(bytes: &[u8], offset: &mut usize) -> error::Result<Self> {
+        Ok(bytes.gread_with(offset, scroll::LE)?)
+    }
+    /// File version formatted as `major.minor.build.revision`
+    pub fn get_file_version(&self) -> String {
+        format!("{}.{}.{}.{}", self.file_version_ms >> 16, self.file_version_ms & 0xffff, self.file_version_ls >> 16, self.file_version_ls & 0xffff)
+    }
+    /// Product version formatted as `major.minor.build.revision`
+    pub fn get_product_version(&self) -> String {
+        format!("{}.{}.{}.{}", self.product_version_ms >> 16, self.product_version_ms & 0xffff, self.product_version_ls >> 16, self.product_version_ls & 0xffff)
+    }
+}
+
+/// StringFileInfo table for one language and codepage
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct StringTable {
+    pub key: String,
+    pub language: u16,
+    pub codepage: u16,
+    pub strings: BTreeMap<String, String>,
+}
+
+/// VarFileInfo translation entry
+#[derive(Debug, PartialEq, Copy, Clone, Default, Pread, Pwrite, IOread, IOwrite, SizeWith, Serialize, Deserialize)]
+pub struct Translation {
+    pub language: u16,
+    pub codepage: u16,
+}
+
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct VersionInfo {
+    pub fixed_file_info: Option<FixedFileInfo>,
+    pub string_tables: Vec<StringTable>,
+    pub translations: Vec<Translation>,
+}
+
+/// Generic `wLength`, `wValueLength`, `wType`, `szKey` block every version structure is made of
+struct VersionBlock<'a> {
+    key: String,
+    value: &'a [u8],
+    children: Vec<VersionBlock<'a>>,
+}
+
+pub(crate) fn align_4(offset: usize) -> usize {
+    (offset + 3) & !3
+}
+
+pub(crate) fn read_utf16(bytes: &[u8], offset: &mut usize, end: usize) -> error::Result<String> {
+    let mut chars:Vec<u16> = Vec::new();
+    while *offset + 2 <= end {
+        let c:u16 = bytes.gread_with(offset, scroll::LE)?;
+        if c == 0 {
+            break;
+        }
+        chars.push(c);
+    }
+    Ok(String::from_utf16_lossy(&chars))
+}
+
+impl<'a> VersionBlock<'a> {
+    fn parse(bytes: &'a [u8], start: usize, depth: usize) -> error::Result<(Self, usize)> {
+        let mut offset:usize = start;
+        let length:u16 = bytes.gread_with(&mut offset, scroll::LE)?;
+        let value_length:u16 = bytes.gread_with(&mut offset, scroll::LE)?;
+        let value_type:u16 = bytes.gread_with(&mut offset, scroll::LE)?;
+        let end:usize = (start + length as usize).min(bytes.len());
+        if end < offset {
+            return Err(error::Error::Malformed(format!("Version block too short at offset 0x{:x}", start)));
+        }
+        let key:String = read_utf16(bytes, &mut offset, end)?;
+        offset = align_4(offset);
+        // Text values are counted in words
+        let value_size:usize = if value_type == VERSION_INFO_TEXT { value_length as usize * 2 } else { value_length as usize };
+        let value_end:usize = (offset + value_size).min(end);
+        let value:&[u8] = bytes.get(offset.min(value_end)..value_end).unwrap_or(&[]);
+        offset = align_4(value_end);
+        let mut children:Vec<VersionBlock> = Vec::new();
+        while depth < VERSION_MAX_DEPTH && offset + 6 <= end {
+            // A short child, e.g. zero padding, ends the list and keeps the children before it
+            let (child, child_end) = match VersionBlock::parse(bytes, offset, depth + 1) {
+                Ok(child) => child,
+                Err(_) => break
+            };
+            children.push(child);
+            offset = align_4(child_end);
+        }
+        Ok((VersionBlock { key, value, children }, end.max(start + 6)))
+    }
+}
+
+impl VersionInfo {
+    /// Decodes the content of an RT_VERSION resource
+    pub fn parse(bytes: &[u8]) -> error::Result<Self> {
+        let (root, _) = VersionBlock::parse(bytes, 0, 1)?;
+        let fixed_file_info:Option<FixedFileInfo> = if root.value.len() >= FixedFileInfo::size_with(&scroll::LE) {
+            let fixed_file_info:FixedFileInfo = FixedFileInfo::parse(root.value, &mut 0)?;
+            if fixed_file_info.signature == FIXED_FILE_INFO_SIGNATURE { Some(fixed_file_info) } else { None }
+        } else {
+            None
+        };
+        let mut string_tables:Vec<StringTable> = Vec::new();
+        let mut translations:Vec<Translation> = Vec::new();
+        for child in &root.children {
+            match child.key.as_str() {
+                "StringFileInfo" => {
+                    for table in &child.children {
+                        let language_codepage:u32 = u32::from_str_radix(&table.key, 16).unwrap_or(0);
+                        let mut strings:BTreeMap<String, String> = BTreeMap::new();
+                        for string in &table.children {
+                            strings.insert(string.key.clone(), read_utf16(string.value, &mut 0, string.value.len())?);
+                        }
+                        string_tables.push(StringTable {
+                            key: table.key.clone(),
+                            language: (language_codepage >> 16) as u16,
+                            codepage: language_codepage as u16,
+                            strings
+                        });
+                    }
+                }
+                "VarFileInfo" => {
+                    for var in child.children.iter().filter(|var| var.key == "Translation") {
+                        let mut offset:usize = 0;
+                        while offset + 4 <= var.value.len() {
+                            translations.push(var.value.gread_with(&mut offset, scroll::LE)?);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(VersionInfo {
+            fixed_file_info,
+            string_tables,
+            translations
+        })
+    }
+    /// Decodes the first RT_VERSION resource of the tree, if any
+    pub fn from_resources(bytes: &[u8], headers: &Headers, resource_directory: &ResourceDirectory) -> error::Result<Option<Self>> {
+        match resource_directory.get_resources(RT_VERSION).first() {
+            Some(resource) => Ok(Some(VersionInfo::parse(resource.get_data(bytes, headers)?)?)),
+            None => Ok(None)
+        }
+    }
+    /// Looks up a StringFileInfo value (e.g. `CompanyName`) in the first table defining it
+    pub fn get_string(&self, key: &str) -> Option<&str> {
+        self.string_tables.iter().find_map(|table| table.strings.get(key).map(|value| value.as_str()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use scroll::Pwrite;
+
+    use super::{FixedFileInfo, VersionInfo, FIXED_FILE_INFO_SIGNATURE};
+
+    fn utf16(text: &str) -> Vec<u8> {
+        text.encode_utf16().chain(Some(0)).flat_map(|c| c.to_le_bytes().to_vec()).collect()
+    }
+
+    fn block(key: &str, value_type: u16, value_length: u16, value: &[u8], children: &[Vec<u8>]) -> Vec<u8> {
+        let mut bytes = vec![0u8; 6];
+        bytes.extend(utf16(key));
+        bytes.resize((bytes.len() + 3) & !3, 0);
+        bytes.extend_from_slice(value);
+        for child in children {
+            bytes.resize((bytes.len() + 3) & !3, 0);
+            bytes.extend(child);
+        }
+        let length = bytes.len() as u16;
+        bytes.pwrite_with(length, 0, scroll::LE).unwrap();
+        bytes.pwrite_with(value_length, 2, scroll::LE).unwrap();
+        bytes.pwrite_with(value_type, 4, scroll::LE).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn version_info() {
+        let fixed_file_info = FixedFileInfo {
+            signature: FIXED_FILE_INFO_SIGNATURE,
+            file_version_ms: 0x0001_0002,
+            file_version_ls: 0x0003_0004,
+            ..Default::default()
+        };
+        let mut fixed = vec![0u8; 52];
+        fixed.pwrite_with(fixed_file_info, 0, scroll::LE).unwrap();
+        let company = block("CompanyName", 1, 5, &utf16("Acme"), &[]);
+        let product = block("ProductName", 1, 7, &utf16("Widget"), &[]);
+        let table = block("040904b0", 1, 0, &[], &[company, product]);
+        let string_file_info = block("StringFileInfo", 1, 0, &[], &[table]);
+        let translation = block("Translation", 0, 4, &[0x09, 0x04, 0xb0, 0x04], &[]);
+        let var_file_info = block("VarFileInfo", 1, 0, &[], &[translation]);
+        let root = block("VS_VERSION_INFO", 0, 52, &fixed, &[string_file_info, var_file_info]);
+
+        let version_info = VersionInfo::parse(&root).unwrap();
+        assert_eq!(version_info.fixed_file_info.unwrap().get_file_version(), "1.2.3.4");
+        assert_eq!(version_info.string_tables.len(), 1);
+        assert_eq!(version_info.string_tables[0].language, 0x409);
+        assert_eq!(version_info.string_tables[0].codepage, 1200);
+        assert_eq!(version_info.get_string("CompanyName"), Some("Acme"));
+        assert_eq!(version_info.get_string("ProductName"), Some("Widget"));
+        assert_eq!(version_info.translations[0].language, 0x409);
+        assert_eq!(version_info.translations[0].codepage, 1200);
+    }
+
+    #[test]
+    fn version_info_deeply_nested() {
+        let mut nested = block("Leaf", 1, 0, &[], &[]);
+        for _ in 0..5000 {
+            nested = block("A", 1, 0, &[], &[nested]);
+        }
+        let version_info = VersionInfo::parse(&nested).unwrap();
+        assert!(version_info.string_tables.is_empty());
+        assert_eq!(version_info.fixed_file_info, None);
+    }
+
+    #[test]
+    fn version_info_zero_padding() {
+        let company = block("CompanyName", 1, 5, &utf16("Acme"), &[]);
+        let product = block("ProductName", 1, 7, &utf16("Widget"), &[]);
+        let table = block("040904b0", 1, 0, &[], &[company, vec![0u8; 8], product]);
+        let string_file_info = block("StringFileInfo", 1, 0, &[], &[table]);
+        let root = block("VS_VERSION_INFO", 0, 0, &[], &[string_file_info]);
+
+        let version_info = VersionInfo::parse(&root).unwrap();
+        assert_eq!(version_info.get_string("CompanyName"), Some("Acme"));
+        assert_eq!(version_info.get_string("ProductName"), None);
+    }
+}